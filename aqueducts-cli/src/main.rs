@@ -1,19 +1,80 @@
 use anyhow::Context;
 use aqueducts::prelude::*;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use env_logger::Env;
 use std::{collections::HashMap, error::Error, path::PathBuf, sync::Arc};
 
+mod validate;
+
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// path to Aqueduct configuration file
-    #[arg(short, long)]
-    file: PathBuf,
+    #[arg(short, long, required = true)]
+    file: Option<PathBuf>,
     /// k=v list of parameters to pass to the configuration file e.g. aqueduct -f file.yml -p key1=value1 -p key2=value2
     #[arg(short, long, value_parser = parse_key_val::<String, String>)]
     params: Option<Vec<(String, String)>>,
+    /// run sources and stages without writing to the destinations defined in the configuration file
+    #[arg(long)]
+    no_destination: bool,
+    /// load stages that completed in a previous run from the `checkpoint_dir` of the configuration file instead of executing them again
+    #[arg(long)]
+    resume: bool,
+    /// validate the configuration file by registering sources and the destination and planning every stage without executing it
+    #[arg(long)]
+    dry_run: bool,
+    /// format used to print the output of stages that set `show` (table, json or csv)
+    #[arg(long, default_value = "table")]
+    output: OutputFormat,
+    /// read a JSON object of parameters from stdin e.g. echo '{"key1": "value1"}' | aqueducts -f file.yml --params-from-stdin
+    /// parameters passed with --params take precedence
+    #[arg(long)]
+    params_from_stdin: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// check that a configuration file is a valid Aqueduct definition without running it
+    Validate {
+        /// path to Aqueduct configuration file
+        #[arg(short, long)]
+        file: PathBuf,
+        /// k=v list of parameters to pass to the configuration file e.g. aqueduct validate -f file.yml -p key1=value1
+        #[arg(short, long, value_parser = parse_key_val::<String, String>)]
+        params: Option<Vec<(String, String)>>,
+        /// path to the JSON schema generated by the schema generator (e.g. json_schema/aqueducts.schema.json) to additionally validate against
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
 }
 
+// optional features enabled in this build that pipelines may require
+const ENABLED_FEATURES: &[&str] = &[
+    #[cfg(feature = "s3")]
+    "s3",
+    #[cfg(feature = "gcs")]
+    "gcs",
+    #[cfg(feature = "azure")]
+    "azure",
+    #[cfg(feature = "http")]
+    "http",
+    #[cfg(feature = "odbc")]
+    "odbc",
+    #[cfg(feature = "kafka")]
+    "kafka",
+    #[cfg(feature = "iceberg")]
+    "iceberg",
+    #[cfg(feature = "bigquery")]
+    "bigquery",
+    #[cfg(feature = "mysql")]
+    "mysql",
+    #[cfg(feature = "postgres")]
+    "postgres",
+];
+
 fn parse_key_val<T, U>(s: &str) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
 where
     T: std::str::FromStr,
@@ -27,6 +88,26 @@ where
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
 
+fn parse_json_params(raw: &str) -> Result<HashMap<String, String>, anyhow::Error> {
+    let value = serde_json::from_str::<serde_json::Value>(raw)
+        .context("failed to parse parameters from stdin as JSON")?;
+    let serde_json::Value::Object(object) = value else {
+        anyhow::bail!("parameters from stdin must be a JSON object");
+    };
+
+    object
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => Ok((key, value)),
+            serde_json::Value::Number(value) => Ok((key, value.to_string())),
+            serde_json::Value::Bool(value) => Ok((key, value.to_string())),
+            _ => Err(anyhow::anyhow!(
+                "parameter '{key}' from stdin must be a string, number or boolean"
+            )),
+        })
+        .collect()
+}
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     let env = Env::default().default_filter_or("aqueducts=info");
@@ -38,27 +119,116 @@ async fn main() -> Result<(), anyhow::Error> {
 
     aqueducts::register_handlers();
 
-    let Args { file, params } = Args::parse();
-    let params = HashMap::from_iter(params.unwrap_or_default());
+    let Args {
+        command,
+        file,
+        params: cli_params,
+        no_destination,
+        resume,
+        dry_run,
+        output,
+        params_from_stdin,
+    } = Args::parse();
 
-    let aqueduct = match file.extension().and_then(|s| s.to_str()) {
-        #[cfg(feature = "toml")]
-        Some("toml") => {
-            Aqueduct::try_from_toml(file, params).context("failed to parse provided file")?
-        }
-        #[cfg(feature = "json")]
-        Some("json") => {
-            Aqueduct::try_from_json(file, params).context("failed to parse provided file")?
+    if let Some(Command::Validate {
+        file,
+        params,
+        schema,
+    }) = command
+    {
+        let params = params.unwrap_or_default().into_iter().collect();
+        if let Err(e) = validate::validate(file.as_path(), params, schema.as_deref()) {
+            eprintln!("{:?}", miette::Report::new(e));
+            std::process::exit(1);
         }
-        _ => Aqueduct::try_from_yml(file, params).context("failed to parse provided file")?,
+
+        log::info!("{} is valid", file.display());
+        return Ok(());
+    }
+
+    let file = file.expect("--file is required without a subcommand");
+
+    let mut params = if params_from_stdin {
+        let raw = std::io::read_to_string(std::io::stdin())
+            .context("failed to read parameters from stdin")?;
+        parse_json_params(raw.as_str())?
+    } else {
+        HashMap::new()
     };
+    params.extend(cli_params.unwrap_or_default());
+
+    let mut aqueduct =
+        validate::load(file.as_path(), params).context("failed to parse provided file")?;
+
+    let missing_features = aqueduct
+        .required_features()
+        .into_iter()
+        .filter(|feature| !ENABLED_FEATURES.contains(feature))
+        .collect::<Vec<_>>();
+    if !missing_features.is_empty() {
+        anyhow::bail!(
+            "the provided file requires features that were not enabled in this build, rebuild with --features {}",
+            missing_features.join(",")
+        );
+    }
 
-    let mut ctx = datafusion::prelude::SessionContext::new();
+    if no_destination && aqueduct.all_destinations().next().is_some() {
+        aqueduct.destination = None;
+        aqueduct.destinations.clear();
+        log::info!("Skipping destinations as --no-destination was provided");
+    }
+
+    if resume {
+        if aqueduct.checkpoint_dir.is_none() {
+            anyhow::bail!(
+                "--resume requires a `checkpoint_dir` to be defined in the configuration file"
+            );
+        }
+
+        aqueduct.resume = true;
+    }
+
+    let config = datafusion::prelude::SessionConfig::new().with_extension(Arc::new(output));
+    let mut ctx = datafusion::prelude::SessionContext::new_with_config(config);
     datafusion_functions_json::register_all(&mut ctx).expect("failed to register json functions");
 
-    run_pipeline(Arc::new(ctx), aqueduct)
-        .await
-        .context("failure during execution of aqueducts file")?;
+    if dry_run {
+        dry_run_pipeline(Arc::new(ctx), aqueduct)
+            .await
+            .context("failure during validation of aqueducts file")?;
+
+        return Ok(());
+    }
+
+    // the first Ctrl+C cancels the pipeline gracefully, a second one terminates the process
+    let cancellation = CancellationToken::new();
+    let cancel = cancellation.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::warn!("Cancelling pipeline ... press Ctrl+C again to terminate immediately");
+            cancel.cancel();
+        }
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+
+    // cancelled and timed out runs exit with the codes conventionally used for them (SIGINT and `timeout`)
+    let ctx = match run_pipeline_with_cancellation(Arc::new(ctx), aqueduct, cancellation).await {
+        Err(e @ aqueducts::error::Error::Cancelled { .. }) => {
+            log::error!("{e}");
+            std::process::exit(130);
+        }
+        Err(e @ aqueducts::error::Error::Timeout { .. }) => {
+            log::error!("{e}");
+            std::process::exit(124);
+        }
+        result => result.context("failure during execution of aqueducts file")?,
+    };
+
+    for (destination, version) in committed_versions(&ctx) {
+        log::info!("Delta destination '{destination}' is at version {version}");
+    }
 
     Ok(())
 }
@@ -0,0 +1,174 @@
+use aqueducts::prelude::*;
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Failure to validate an Aqueduct configuration file
+#[derive(Debug, thiserror::Error, Diagnostic)]
+pub enum ValidationError {
+    #[error("failed to read '{0}'")]
+    #[diagnostic(code(aqueducts::validate::io))]
+    Io(String, #[source] std::io::Error),
+
+    #[error("'{path}' is not a valid Aqueduct definition")]
+    #[diagnostic(
+        code(aqueducts::validate::definition),
+        help("check the definition against the schema reference and make sure all template parameters are provided")
+    )]
+    Definition {
+        path: String,
+        #[source_code]
+        source_code: NamedSource<String>,
+        #[source]
+        source: Box<aqueducts::error::Error>,
+    },
+
+    #[error("'{path}' can't be parsed after substituting params: {message}")]
+    #[diagnostic(
+        code(aqueducts::validate::parse),
+        help("make sure substituted param values don't break the syntax of the definition")
+    )]
+    Parse {
+        path: String,
+        #[source_code]
+        source_code: Arc<NamedSource<String>>,
+        #[label("invalid syntax")]
+        span: Option<SourceSpan>,
+        message: String,
+    },
+
+    #[error("failed to load JSON schema '{0}': {1}")]
+    #[diagnostic(code(aqueducts::validate::schema_file))]
+    SchemaFile(String, String),
+
+    #[error("'{path}' does not conform to the JSON schema")]
+    #[diagnostic(code(aqueducts::validate::schema), help("{}", errors.join("\n")))]
+    Schema { path: String, errors: Vec<String> },
+}
+
+/// Load an Aqueduct definition from a file, the format is selected by the file extension
+/// Files with an unrecognized extension are tried as yaml, json and toml in this order
+pub fn load(file: &Path, params: HashMap<String, String>) -> aqueducts::Result<Aqueduct> {
+    Aqueduct::try_from_file(file, params)
+}
+
+/// Validate an Aqueduct definition file without touching any storage or database
+/// When a JSON schema is provided the definition is additionally validated against it after params were substituted
+pub fn validate(
+    file: &Path,
+    params: HashMap<String, String>,
+    schema: Option<&Path>,
+) -> Result<(), ValidationError> {
+    let path = file.display().to_string();
+    let raw = std::fs::read_to_string(file).map_err(|e| ValidationError::Io(path.clone(), e))?;
+
+    load(file, params.clone()).map_err(|source| ValidationError::Definition {
+        path: path.clone(),
+        source_code: NamedSource::new(path.as_str(), raw.clone()),
+        source: Box::new(source),
+    })?;
+
+    let Some(schema) = schema else {
+        return Ok(());
+    };
+
+    let schema_path = schema.display().to_string();
+    let schema_error = |message: String| ValidationError::SchemaFile(schema_path.clone(), message);
+    let schema =
+        std::fs::read_to_string(schema).map_err(|e| ValidationError::Io(schema_path.clone(), e))?;
+    let schema = serde_json::from_str::<serde_json::Value>(schema.as_str())
+        .map_err(|e| schema_error(e.to_string()))?;
+    let validator = jsonschema::validator_for(&schema).map_err(|e| schema_error(e.to_string()))?;
+
+    let definition_error = |source| ValidationError::Definition {
+        path: path.clone(),
+        source_code: NamedSource::new(path.as_str(), raw.clone()),
+        source: Box::new(source),
+    };
+    let definition = Aqueduct::substitute_params(raw.as_str(), params).map_err(definition_error)?;
+    let definition =
+        parse_value(file, definition.as_str()).map_err(|ParseError { span, message }| {
+            ValidationError::Parse {
+                path: path.clone(),
+                source_code: Arc::new(NamedSource::new(path.as_str(), definition.clone())),
+                span,
+                message,
+            }
+        })?;
+
+    let errors = validator
+        .iter_errors(&definition)
+        .map(|e| format!("{}: {e}", e.instance_path))
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(ValidationError::Schema { path, errors });
+    }
+
+    Ok(())
+}
+
+// failure to parse a definition with the location of the error in the definition if known
+struct ParseError {
+    span: Option<SourceSpan>,
+    message: String,
+}
+
+// parse a definition into a generic JSON value to validate it against the JSON schema
+fn parse_value(file: &Path, definition: &str) -> Result<serde_json::Value, ParseError> {
+    match file.extension().and_then(|s| s.to_str()) {
+        #[cfg(feature = "toml")]
+        Some("toml") => parse_toml(definition),
+        #[cfg(feature = "json")]
+        Some("json") => parse_json(definition),
+        Some("yml" | "yaml") => parse_yaml(definition),
+        // same order as the format detection of `Aqueduct::try_from_file`
+        _ => parse_yaml(definition)
+            .or_else(|yaml| parse_json(definition).map_err(|json| (yaml.message, json.message)))
+            .or_else(|(yaml, json)| {
+                parse_toml(definition).map_err(|toml| ParseError {
+                    span: None,
+                    message: format!("yaml: {yaml}, json: {json}, toml: {}", toml.message),
+                })
+            }),
+    }
+}
+
+fn parse_yaml(definition: &str) -> Result<serde_json::Value, ParseError> {
+    serde_yml::from_str(definition).map_err(|e| ParseError {
+        span: e.location().map(|location| location.index().into()),
+        message: e.to_string(),
+    })
+}
+
+fn parse_json(definition: &str) -> Result<serde_json::Value, ParseError> {
+    serde_json::from_str(definition).map_err(|e| ParseError {
+        span: line_offset(definition, e.line(), e.column()).map(Into::into),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(definition: &str) -> Result<serde_json::Value, ParseError> {
+    toml::from_str(definition).map_err(|e| ParseError {
+        span: e.span().map(|span| (span.start, span.len()).into()),
+        message: e.message().to_string(),
+    })
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_: &str) -> Result<serde_json::Value, ParseError> {
+    Err(ParseError {
+        span: None,
+        message: "the toml feature is not enabled".into(),
+    })
+}
+
+// byte offset of a 1-based line and column as reported by serde_json
+fn line_offset(definition: &str, line: usize, column: usize) -> Option<usize> {
+    let line_start = definition
+        .split_inclusive('\n')
+        .take(line.checked_sub(1)?)
+        .map(str::len)
+        .sum::<usize>();
+
+    Some((line_start + column.saturating_sub(1)).min(definition.len()))
+}
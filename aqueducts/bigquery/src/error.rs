@@ -0,0 +1,21 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid table reference '{0}', project, dataset and table must not be empty")]
+    InvalidTable(String),
+    #[error("Failed to authenticate with BigQuery: {0}")]
+    Auth(String),
+    #[error("Invalid request metadata '{0}'")]
+    InvalidMetadata(String),
+    #[error("Read session for table '{0}' returned no arrow schema")]
+    MissingSchema(String),
+
+    // -- External
+    #[error("ArrowError({0})")]
+    ArrowError(#[from] datafusion::arrow::error::ArrowError),
+    #[error("DataFusionError({0})")]
+    DataFusionError(#[from] datafusion::error::DataFusionError),
+    #[error("TransportError({0})")]
+    TransportError(#[from] tonic::transport::Error),
+    #[error("StatusError({0})")]
+    StatusError(#[from] Box<tonic::Status>),
+}
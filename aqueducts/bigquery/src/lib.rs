@@ -0,0 +1,254 @@
+use std::{io::Cursor, sync::Arc};
+
+use datafusion::{
+    arrow::{array::RecordBatch, ipc::reader::StreamReader},
+    datasource::MemTable,
+    execution::context::SessionContext,
+};
+use google_cloud_auth::{
+    credentials::CredentialsFile, project::Config, token::DefaultTokenSourceProvider,
+};
+use google_cloud_googleapis::cloud::bigquery::storage::v1::{
+    big_query_read_client::BigQueryReadClient,
+    read_rows_response::Rows,
+    read_session::{Schema, TableReadOptions},
+    CreateReadSessionRequest, DataFormat, ReadRowsRequest, ReadSession,
+};
+use google_cloud_token::TokenSourceProvider;
+use tonic::{
+    transport::{Channel, ClientTlsConfig},
+    Request,
+};
+use tracing::{debug, info};
+
+pub mod error;
+
+pub type Result<T> = core::result::Result<T, error::Error>;
+
+const ENDPOINT: &str = "https://bigquerystorage.googleapis.com";
+const SCOPES: [&str; 1] = ["https://www.googleapis.com/auth/bigquery.readonly"];
+
+/// Options for reading a BigQuery table using the Storage Read API
+#[derive(Debug, Clone)]
+pub struct BigQueryReadOptions<'a> {
+    /// Project containing the table
+    pub project: &'a str,
+    pub dataset: &'a str,
+    pub table: &'a str,
+    /// Project billed for the read session, defaults to `project`
+    pub billing_project: Option<&'a str>,
+    /// Columns read from the table, all columns are read when empty
+    pub columns: &'a [String],
+    /// Filter applied by BigQuery before the rows are returned e.g. `country = 'DE'`
+    pub row_restriction: Option<&'a str>,
+    /// Path to a service account key file, defaults to the application default credentials
+    pub credentials_file: Option<&'a str>,
+}
+
+impl BigQueryReadOptions<'_> {
+    // path of the table as expected by the Storage Read API
+    fn table_path(&self) -> Result<String> {
+        let Self {
+            project,
+            dataset,
+            table,
+            ..
+        } = self;
+
+        if project.is_empty() || dataset.is_empty() || table.is_empty() {
+            return Err(error::Error::InvalidTable(format!(
+                "{project}.{dataset}.{table}"
+            )));
+        }
+
+        Ok(format!(
+            "projects/{project}/datasets/{dataset}/tables/{table}"
+        ))
+    }
+}
+
+/// Register a BigQuery table read into memory using the [BigQuery Storage Read API](https://cloud.google.com/bigquery/docs/reference/storage)
+/// ```rust,ignore
+/// use datafusion::prelude::SessionContext;
+///
+/// let ctx = Arc::new(SessionContext::new());
+/// let columns = vec!["name".to_string(), "number".to_string()];
+/// let options = BigQueryReadOptions {
+///     project: "bigquery-public-data",
+///     dataset: "usa_names",
+///     table: "usa_1910_current",
+///     billing_project: Some("my-project"),
+///     columns: &columns,
+///     row_restriction: Some("state = 'WA'"),
+///     credentials_file: None,
+/// };
+///
+/// register_bigquery_source(ctx.clone(), "names", options).await.unwrap();
+///
+/// let df = ctx.sql("SELECT * FROM names").await.unwrap();
+/// df.show().await.unwrap();
+/// ```
+///
+/// Column selection and the row restriction are applied by BigQuery, only the selected data is transferred and billed
+/// The streams of the read session are read eagerly, size of the data returned cannot exceed work memory
+pub async fn register_bigquery_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    options: BigQueryReadOptions<'_>,
+) -> Result<()> {
+    let table_path = options.table_path()?;
+    let token = access_token(options.credentials_file).await?;
+
+    let channel = Channel::from_static(ENDPOINT)
+        .tls_config(ClientTlsConfig::new().with_native_roots())?
+        .connect()
+        .await?;
+    let mut client = BigQueryReadClient::new(channel);
+
+    let read_session = ReadSession {
+        table: table_path.clone(),
+        data_format: DataFormat::Arrow.into(),
+        read_options: Some(TableReadOptions {
+            selected_fields: options.columns.to_vec(),
+            row_restriction: options.row_restriction.unwrap_or_default().to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let create_session = CreateReadSessionRequest {
+        parent: format!(
+            "projects/{}",
+            options.billing_project.unwrap_or(options.project)
+        ),
+        read_session: Some(read_session),
+        ..Default::default()
+    };
+
+    info!("Creating BigQuery read session for table '{table_path}'");
+    let session = client
+        .create_read_session(request(
+            create_session,
+            token.as_str(),
+            format!("read_session.table={table_path}"),
+        )?)
+        .await
+        .map_err(Box::new)?
+        .into_inner();
+
+    let Some(Schema::ArrowSchema(schema)) = session.schema else {
+        return Err(error::Error::MissingSchema(table_path));
+    };
+    let serialized_schema = schema.serialized_schema;
+    let arrow_schema =
+        StreamReader::try_new(Cursor::new(serialized_schema.as_slice()), None)?.schema();
+
+    let mut batches = vec![];
+    for stream in session.streams {
+        debug!("Reading BigQuery read stream '{}'", stream.name);
+
+        let read_rows = ReadRowsRequest {
+            read_stream: stream.name.clone(),
+            offset: 0,
+        };
+        let mut responses = client
+            .read_rows(request(
+                read_rows,
+                token.as_str(),
+                format!("read_stream={}", stream.name),
+            )?)
+            .await
+            .map_err(Box::new)?
+            .into_inner();
+
+        while let Some(response) = responses.message().await.map_err(Box::new)? {
+            if let Some(Rows::ArrowRecordBatch(batch)) = response.rows {
+                batches.extend(decode_batches(
+                    serialized_schema.as_slice(),
+                    batch.serialized_record_batch.as_slice(),
+                )?);
+            }
+        }
+    }
+
+    info!(
+        "Registering BigQuery table '{}.{}.{}' as '{source_name}'",
+        options.project, options.dataset, options.table
+    );
+    let table = MemTable::try_new(arrow_schema, vec![batches])?;
+    ctx.register_table(source_name, Arc::new(table))?;
+
+    Ok(())
+}
+
+// fetch an access token from the service account key file or the application default credentials
+async fn access_token(credentials_file: Option<&str>) -> Result<String> {
+    let config = Config::default().with_scopes(&SCOPES);
+
+    let provider = match credentials_file {
+        Some(path) => {
+            let credentials = CredentialsFile::new_from_file(path.to_string())
+                .await
+                .map_err(|e| error::Error::Auth(e.to_string()))?;
+
+            DefaultTokenSourceProvider::new_with_credentials(config, Box::new(credentials)).await
+        }
+        None => DefaultTokenSourceProvider::new(config).await,
+    }
+    .map_err(|e| error::Error::Auth(e.to_string()))?;
+
+    provider
+        .token_source()
+        .token()
+        .await
+        .map_err(|e| error::Error::Auth(e.to_string()))
+}
+
+// attach the access token and the routing header required by the Storage Read API
+fn request<T>(message: T, token: &str, request_params: String) -> Result<Request<T>> {
+    let mut request = Request::new(message);
+    let metadata = request.metadata_mut();
+
+    let authorization = token
+        .parse()
+        .map_err(|_| error::Error::InvalidMetadata("authorization".into()))?;
+    let request_params = request_params
+        .parse()
+        .map_err(|_| error::Error::InvalidMetadata(request_params.clone()))?;
+
+    metadata.insert("authorization", authorization);
+    metadata.insert("x-goog-request-params", request_params);
+
+    Ok(request)
+}
+
+// record batches are serialized without their schema, prepend the schema of the session to read them as an IPC stream
+fn decode_batches(serialized_schema: &[u8], serialized_batch: &[u8]) -> Result<Vec<RecordBatch>> {
+    let buffer = [serialized_schema, serialized_batch].concat();
+    let batches = StreamReader::try_new(Cursor::new(buffer), None)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_bigquery_source_invalid_table_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let options = BigQueryReadOptions {
+            project: "bigquery-public-data",
+            dataset: "",
+            table: "usa_1910_current",
+            billing_project: None,
+            columns: &[],
+            row_restriction: None,
+            credentials_file: None,
+        };
+
+        let result = register_bigquery_source(ctx, "names", options).await;
+
+        assert!(matches!(result, Err(error::Error::InvalidTable(_))));
+    }
+}
@@ -0,0 +1,196 @@
+use datafusion::{
+    dataframe::DataFrameWriteOptions,
+    error::DataFusionError,
+    prelude::{ParquetReadOptions, SessionContext},
+};
+use deltalake::{ObjectStore, Path};
+use futures::{StreamExt, TryStreamExt};
+use sha2::{Digest, Sha256};
+use tracing::info;
+use url::Url;
+
+use crate::{object_store_url, stages::Stage, Aqueduct};
+
+/// Name of the marker object written after the checkpoint of a stage was completely written
+/// Contains the fingerprint of the stage the checkpoint was written for, see [`fingerprint`]
+pub const SUCCESS_MARKER: &str = "_SUCCESS";
+
+/// Fingerprint of a stage at position `pos` of the pipeline
+/// Hash of the sources, the stages of the previous positions and the stage itself after the params were substituted
+/// A checkpoint is only loaded if it was written for a stage with the same fingerprint
+pub fn fingerprint(
+    aqueduct: &Aqueduct,
+    pos: usize,
+    stage: &Stage,
+) -> Result<String, DataFusionError> {
+    // converted to a value first so that maps are serialized with sorted keys
+    let definition = serde_json::to_value((&aqueduct.sources, &aqueduct.stages[..pos], stage))
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+    let hash = Sha256::digest(definition.to_string().as_bytes());
+
+    Ok(format!("{hash:x}"))
+}
+
+/// Location of the checkpoint of a stage, a directory of parquet files named after the stage inside the checkpoint directory
+pub fn stage_location(checkpoint_dir: &Url, stage: &str) -> Result<Url, DataFusionError> {
+    let mut checkpoint_dir = checkpoint_dir.clone();
+    if !checkpoint_dir.path().ends_with('/') {
+        checkpoint_dir.set_path(format!("{}/", checkpoint_dir.path()).as_str());
+    }
+
+    checkpoint_dir
+        .join(format!("{stage}/").as_str())
+        .map_err(|e| DataFusionError::External(Box::new(e)))
+}
+
+/// Register the checkpoint of a stage as a table with the name of the stage
+/// Returns `false` without registering anything if no complete checkpoint with a matching fingerprint exists for the stage
+pub async fn load(
+    ctx: &SessionContext,
+    checkpoint_dir: &Url,
+    stage: &str,
+    fingerprint: &str,
+) -> Result<bool, DataFusionError> {
+    let location = stage_location(checkpoint_dir, stage)?;
+    let store = ctx
+        .runtime_env()
+        .object_store(object_store_url(&location)?)?;
+    let marker = object_path(&location)?.child(SUCCESS_MARKER);
+
+    let checkpointed = match store.get(&marker).await {
+        Ok(result) => result.bytes().await?,
+        Err(deltalake::ObjectStoreError::NotFound { .. }) => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+
+    if checkpointed.as_ref() != fingerprint.as_bytes() {
+        info!("Ignoring checkpoint of stage {stage} ... the stage or its inputs changed since it was written");
+        return Ok(false);
+    }
+
+    ctx.register_parquet(stage, location.as_str(), ParquetReadOptions::default())
+        .await?;
+
+    Ok(true)
+}
+
+/// Persist the registered table of a stage as parquet files replacing any previous checkpoint of the stage
+/// The fingerprint of the stage is written to the success marker
+pub async fn save(
+    ctx: &SessionContext,
+    checkpoint_dir: &Url,
+    stage: &str,
+    fingerprint: &str,
+) -> Result<(), DataFusionError> {
+    let location = stage_location(checkpoint_dir, stage)?;
+    let store = ctx
+        .runtime_env()
+        .object_store(object_store_url(&location)?)?;
+    let prefix = object_path(&location)?;
+
+    // files of a previous checkpoint would otherwise be read together with the new ones
+    delete_prefix(store.as_ref(), &prefix).await?;
+
+    ctx.table(stage)
+        .await?
+        .write_parquet(location.as_str(), DataFrameWriteOptions::new(), None)
+        .await?;
+
+    store
+        .put(
+            &prefix.child(SUCCESS_MARKER),
+            fingerprint.as_bytes().to_vec().into(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Delete the checkpoints of the given stages, called once the pipeline completed successfully
+/// Only the locations of the stages are deleted so that other objects inside the checkpoint directory are kept
+pub async fn clear<'a>(
+    ctx: &SessionContext,
+    checkpoint_dir: &Url,
+    stages: impl IntoIterator<Item = &'a str>,
+) -> Result<(), DataFusionError> {
+    let store = ctx
+        .runtime_env()
+        .object_store(object_store_url(checkpoint_dir)?)?;
+
+    for stage in stages {
+        let location = stage_location(checkpoint_dir, stage)?;
+        delete_prefix(store.as_ref(), &object_path(&location)?).await?;
+    }
+
+    Ok(())
+}
+
+// delete all objects below a prefix
+async fn delete_prefix(store: &dyn ObjectStore, prefix: &Path) -> Result<(), DataFusionError> {
+    let locations = store
+        .list(Some(prefix))
+        .map_ok(|meta| meta.location)
+        .boxed();
+    store
+        .delete_stream(locations)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    Ok(())
+}
+
+// path of a location within its object store
+fn object_path(location: &Url) -> Result<Path, DataFusionError> {
+    Path::from_url_path(location.path()).map_err(|e| DataFusionError::External(Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_location_ok() {
+        let with_slash = Url::parse("s3://bucket/checkpoints/").unwrap();
+        let without_slash = Url::parse("s3://bucket/checkpoints").unwrap();
+
+        assert_eq!(
+            "s3://bucket/checkpoints/stage_1/",
+            stage_location(&with_slash, "stage_1").unwrap().as_str()
+        );
+        assert_eq!(
+            "s3://bucket/checkpoints/stage_1/",
+            stage_location(&without_slash, "stage_1").unwrap().as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_keeps_foreign_objects_ok() {
+        let checkpoint_dir = std::path::Path::new(".")
+            .canonicalize()
+            .unwrap()
+            .join("tests/output/checkpoint_clear");
+        let _ = std::fs::remove_dir_all(&checkpoint_dir);
+
+        for path in [
+            "stage_1/part-0.parquet",
+            "stage_1/_SUCCESS",
+            "stage_10/_SUCCESS",
+        ] {
+            let path = checkpoint_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(checkpoint_dir.join("foreign.csv"), "").unwrap();
+
+        let location = Url::from_directory_path(&checkpoint_dir).unwrap();
+        clear(&SessionContext::new(), &location, ["stage_1"])
+            .await
+            .unwrap();
+
+        assert!(!checkpoint_dir.join("stage_1/_SUCCESS").exists());
+        assert!(!checkpoint_dir.join("stage_1/part-0.parquet").exists());
+        assert!(checkpoint_dir.join("stage_10/_SUCCESS").exists());
+        assert!(checkpoint_dir.join("foreign.csv").exists());
+    }
+}
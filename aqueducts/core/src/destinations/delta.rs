@@ -1,16 +1,29 @@
-use aqueducts_utils::serde::deserialize_file_location;
+use aqueducts_utils::{serde::deserialize_file_location, store::unsupported_delta_options};
 use datafusion::dataframe::DataFrame;
 use deltalake::{
     arrow::datatypes::Schema,
-    kernel::{StructField, StructType},
+    kernel::{DataType, StructField, StructType, Transaction},
+    operations::{
+        optimize::OptimizeType,
+        transaction::{CommitProperties, TransactionError},
+    },
     protocol::SaveMode,
-    DeltaOps, DeltaTable,
+    DeltaOps, DeltaTable, DeltaTableError,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+use tracing::{info, warn};
 use url::Url;
 
-use super::Result;
+use super::{error, Result};
+use crate::schema_transform::schema_diff;
+
+const COMMIT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_COMMIT_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_COMMIT_RETRIES: usize = 20;
+
+// commit info key identifying the engine that wrote a commit
+const ENGINE_INFO_KEY: &str = "engineInfo";
 
 /// A delta table destination
 #[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
@@ -24,6 +37,7 @@ pub struct DeltaDestination {
     pub location: Url,
 
     /// DeltaTable storage options
+    /// Options that deltalake ignores (e.g. `role_arn` or the retry options) are rejected
     #[serde(default)]
     pub storage_options: HashMap<String, String>,
 
@@ -40,6 +54,123 @@ pub struct DeltaDestination {
     /// Table schema definition `deltalake_core::models::schema::StructField`
     #[cfg_attr(feature = "schema_gen", schemars(skip))]
     pub schema: Vec<StructField>,
+
+    /// Columns to sort the output data by before writing, as a list of `(column, ascending)` tuples
+    /// Sorting requires a full sort (shuffle) of the output data before the write and can be expensive for large outputs
+    #[new(default)]
+    #[serde(default)]
+    pub sort_by: Vec<(String, bool)>,
+
+    /// Number of times a write is retried when it conflicts with a concurrent commit to the same table
+    /// Each retry reloads the table and waits with an exponential backoff starting at 100ms and capped at 10s, at most 20 retries, defaults to 0 (no retries)
+    #[new(default)]
+    #[serde(default)]
+    pub max_commit_retries: usize,
+
+    /// Name of a column that is added to the output and populated with the timestamp (UTC) of the write
+    /// Fails the write if the output already contains a column with this name
+    #[new(default)]
+    #[serde(default)]
+    pub ingestion_timestamp_column: Option<String>,
+
+    /// Maximum number of rows that may be written, the write is aborted when the output exceeds it
+    /// Checked by reading at most `max_output_rows + 1` rows before writing, defaults to unlimited
+    #[new(default)]
+    #[serde(default)]
+    pub max_output_rows: Option<usize>,
+
+    /// Partition columns derived from SQL expressions over the output (e.g. `CAST(event_time AS DATE)`)
+    /// Generated columns are added to the table schema and partitioning after `schema` and `partition_cols`
+    #[new(default)]
+    #[serde(default)]
+    pub generated_partition_cols: Vec<GeneratedColumn>,
+
+    /// When set to `true` write permissions are checked when the destination is registered, before any stage is executed
+    /// The check writes and deletes an empty marker object (`_aqueducts_write_check_<timestamp>` in the table root, no delta commit is created), defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub precheck_write: bool,
+
+    /// When set the table is optimized (compaction and optional Z-ordering) after each successful write, defaults to no optimization
+    #[new(default)]
+    #[serde(default)]
+    pub optimize: Option<OptimizeOptions>,
+
+    /// When set files no longer referenced by the table are vacuumed after each successful write (and optimize), defaults to no vacuum
+    #[new(default)]
+    #[serde(default)]
+    pub vacuum: Option<VacuumOptions>,
+
+    /// Key/value pairs recorded in the commit info of every commit written to the table e.g. a pipeline name or execution id
+    /// `engineInfo` defaults to `aqueducts/<version>` and can be overridden
+    #[new(default)]
+    #[serde(default)]
+    pub commit_metadata: HashMap<String, String>,
+
+    /// Application id recorded together with `app_version` in the transaction log to make writes idempotent
+    /// A write is skipped when the table already contains a commit of the application with a version greater or equal to `app_version`
+    /// Both have to be set, omitting them keeps writes non-idempotent (a retried write is applied again)
+    #[new(default)]
+    #[serde(default)]
+    pub app_id: Option<String>,
+
+    /// Version of the write of the application `app_id` e.g. a monotonically increasing execution number of a scheduler
+    #[new(default)]
+    #[serde(default)]
+    pub app_version: Option<i64>,
+}
+
+/// Options for the OPTIMIZE run after a write to a delta table
+#[derive(Debug, Clone, Default, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct OptimizeOptions {
+    /// Target size of the compacted files in bytes, defaults to the `delta.targetFileSize` table property or 100MB
+    pub target_file_size: Option<i64>,
+
+    /// Columns to Z-order the data by, files are only compacted when empty
+    /// Partition columns can not be used for Z-ordering
+    #[new(default)]
+    #[serde(default)]
+    pub z_order_columns: Vec<String>,
+}
+
+/// A column computed from an SQL expression before the data is written
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct GeneratedColumn {
+    /// Name of the generated column
+    pub name: String,
+
+    /// SQL expression evaluated against the output data, its result has to be castable to `data_type`
+    pub expression: String,
+
+    /// Data type of the generated column using de-serialization provided by `deltalake::kernel::DataType`
+    #[cfg_attr(feature = "schema_gen", schemars(skip))]
+    pub data_type: DataType,
+}
+
+/// Options for the VACUUM run after a write to a delta table
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct VacuumOptions {
+    /// Retention period in hours, files removed from the table longer ago are deleted
+    /// Defaults to the `delta.deletedFileRetentionDuration` table property or 7 days
+    pub retention_hours: Option<u64>,
+
+    /// When set to `true` the files that would be deleted are only logged, defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Fail if `retention_hours` is below the retention configured for the table, defaults to `true`
+    /// Vacuuming with a shorter retention breaks time travel to older versions and concurrent readers
+    #[new(value = "true")]
+    #[serde(default = "enforce_retention_duration_default")]
+    pub enforce_retention_duration: bool,
+}
+
+fn enforce_retention_duration_default() -> bool {
+    true
 }
 
 /// Write modes for the `Destination` output.
@@ -51,10 +182,118 @@ pub enum WriteMode {
     Append,
 
     /// `Upsert`: upserts data to the `Destination` using the specified merge columns
-    Upsert(Vec<String>),
+    Upsert(UpsertParams),
 
     /// `Replace`: replaces data to the `Destination` using the specified `ReplaceCondition`s
     Replace(Vec<ReplaceCondition>),
+
+    /// `ScdType2`: keeps the history of every business key as a slowly changing dimension (type 2)
+    /// Current rows whose tracked columns changed are expired and the new versions are inserted
+    ScdType2(ScdType2Options),
+}
+
+/// Parameters for `Upsert` write mode, either a list of merge columns or `UpsertOptions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum UpsertParams {
+    /// Merge columns, null keys match other null keys
+    Columns(Vec<String>),
+
+    /// Merge columns with options
+    Options(UpsertOptions),
+}
+
+impl UpsertParams {
+    fn merge_columns(&self) -> &[String] {
+        match self {
+            UpsertParams::Columns(columns) => columns,
+            UpsertParams::Options(options) => &options.merge_columns,
+        }
+    }
+
+    fn null_equals_null(&self) -> bool {
+        match self {
+            UpsertParams::Columns(_) => true,
+            UpsertParams::Options(options) => options.null_equals_null,
+        }
+    }
+}
+
+impl From<Vec<String>> for UpsertParams {
+    fn from(columns: Vec<String>) -> Self {
+        UpsertParams::Columns(columns)
+    }
+}
+
+/// Options for `Upsert` write mode
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct UpsertOptions {
+    /// Columns used as the unique keys of the merge
+    pub merge_columns: Vec<String>,
+
+    /// When set to `true` a null in a merge column matches a null of the same column in the table, defaults to `true`
+    /// Set to `false` so that rows with null keys are never matched and always inserted
+    #[new(value = "true")]
+    #[serde(default = "null_equals_null_default")]
+    pub null_equals_null: bool,
+}
+
+fn null_equals_null_default() -> bool {
+    true
+}
+
+/// Options for writing a delta table as a slowly changing dimension (type 2)
+/// The `valid_from`, `valid_to` and `is_current` columns have to be part of the table schema and are populated by the write
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct ScdType2Options {
+    /// Columns identifying an entity of the dimension
+    pub business_key_columns: Vec<String>,
+
+    /// Columns for which a change creates a new version of the entity
+    /// Defaults to all columns of the table schema except the business key and SCD columns
+    #[new(default)]
+    #[serde(default)]
+    pub tracked_columns: Vec<String>,
+
+    /// Timestamp column holding the start of the validity of a version, defaults to `valid_from`
+    #[new(value = "valid_from_default()")]
+    #[serde(default = "valid_from_default")]
+    pub valid_from_column: String,
+
+    /// Timestamp column holding the end of the validity of a version (`NULL` for the current version), defaults to `valid_to`
+    #[new(value = "valid_to_default()")]
+    #[serde(default = "valid_to_default")]
+    pub valid_to_column: String,
+
+    /// Boolean column flagging the current version of an entity, defaults to `is_current`
+    #[new(value = "is_current_default()")]
+    #[serde(default = "is_current_default")]
+    pub is_current_column: String,
+}
+
+fn valid_from_default() -> String {
+    "valid_from".into()
+}
+
+fn valid_to_default() -> String {
+    "valid_to".into()
+}
+
+fn is_current_default() -> String {
+    "is_current".into()
+}
+
+impl ScdType2Options {
+    fn scd_columns(&self) -> [&String; 3] {
+        [
+            &self.valid_from_column,
+            &self.valid_to_column,
+            &self.is_current_column,
+        ]
+    }
 }
 
 /// Condition used to build a predicate by which data should be replaced in a `Destination`
@@ -78,30 +317,192 @@ pub(super) async fn create(table_def: &DeltaDestination) -> Result<DeltaTable> {
     .create()
     .with_save_mode(deltalake::protocol::SaveMode::Ignore)
     .with_configuration(table_def.table_properties.clone())
-    .with_columns(table_def.schema.clone())
-    .with_partition_columns(table_def.partition_cols.clone())
+    .with_columns(table_columns(table_def))
+    .with_partition_columns(partition_columns(table_def))
     .await?;
 
     Ok(table)
 }
 
+/// Checks whether the delta table exists without creating it
+pub(super) async fn exists(table_def: &DeltaDestination) -> Result<bool> {
+    // opening a missing local location fails with an invalid location error
+    if table_def.location.scheme() == "file"
+        && !table_def
+            .location
+            .to_file_path()
+            .is_ok_and(|path| path.exists())
+    {
+        return Ok(false);
+    }
+
+    match deltalake::open_table_with_storage_options(
+        table_def.location.as_str(),
+        table_def.storage_options.clone(),
+    )
+    .await
+    {
+        Ok(_) => Ok(true),
+        Err(DeltaTableError::NotATable(_)) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Validate the options of a delta destination without accessing the table
+pub(super) fn validate(table_def: &DeltaDestination) -> Result<()> {
+    if table_def.max_commit_retries > MAX_COMMIT_RETRIES {
+        return Err(error::Error::InvalidCommitRetries(
+            table_def.name.clone(),
+            MAX_COMMIT_RETRIES,
+        ));
+    }
+
+    let unsupported = unsupported_delta_options(&table_def.location, &table_def.storage_options);
+    if let Some(key) = unsupported.first() {
+        return Err(error::Error::UnsupportedDeltaStorageOption(
+            table_def.name.clone(),
+            key.to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// exponential backoff before the given retry (starting at 1), capped at `MAX_COMMIT_RETRY_BACKOFF`
+fn commit_retry_backoff(attempt: usize) -> Duration {
+    u32::try_from(attempt.saturating_sub(1))
+        .ok()
+        .and_then(|exponent| 2_u32.checked_pow(exponent))
+        .and_then(|factor| COMMIT_RETRY_BACKOFF.checked_mul(factor))
+        .map_or(MAX_COMMIT_RETRY_BACKOFF, |backoff| {
+            backoff.min(MAX_COMMIT_RETRY_BACKOFF)
+        })
+}
+
+/// Write a dataframe to the deltatable using the configured `WriteMode`
+/// When a concurrent commit conflicts with this write the table is reloaded and the write is retried up to `max_commit_retries` times with an exponential backoff
+/// If `optimize` or `vacuum` are configured the table is optimized and vacuumed (in that order) after the write succeeded
 pub(super) async fn write(table_def: &DeltaDestination, data: DataFrame) -> Result<DeltaTable> {
-    let table_schema = StructType::new(table_def.schema.clone());
+    let table_schema = StructType::new(table_columns(table_def));
     let table_schema = TryInto::<Schema>::try_into(&table_schema)?;
+    let data = with_generated_columns(&table_def.generated_partition_cols, data)?;
+    let data = match &table_def.write_mode {
+        WriteMode::ScdType2(options) => with_scd_columns(options, data)?,
+        _ => data,
+    };
     let data = validate_schema(table_schema.clone(), data)?;
 
+    if table_def.app_id.is_some() != table_def.app_version.is_some() {
+        return Err(error::Error::IncompleteAppTransaction(
+            table_def.name.clone(),
+        ));
+    }
+
+    let mut attempt = 0;
+
+    let table = loop {
+        match try_write(table_def, table_schema.clone(), data.clone()).await {
+            Err(error::Error::DeltaTableError(err))
+                if attempt < table_def.max_commit_retries && is_commit_conflict(&err) =>
+            {
+                attempt += 1;
+                let backoff = commit_retry_backoff(attempt);
+
+                warn!(
+                    "Commit conflict while writing to delta table '{}', retrying in {backoff:?} (attempt {attempt}/{}): {err}",
+                    table_def.name, table_def.max_commit_retries
+                );
+                tokio::time::sleep(backoff).await;
+            }
+            result => break result?,
+        }
+    };
+
+    let table = match &table_def.optimize {
+        Some(options) => optimize(table_def, options, table).await?,
+        None => table,
+    };
+
+    match &table_def.vacuum {
+        Some(options) => vacuum(table_def, options, table).await,
+        None => Ok(table),
+    }
+}
+
+// compact small files of the table written by this destination, reusing the object store of the written table
+async fn optimize(
+    table_def: &DeltaDestination,
+    options: &OptimizeOptions,
+    table: DeltaTable,
+) -> Result<DeltaTable> {
+    let optimize_type = if options.z_order_columns.is_empty() {
+        OptimizeType::Compact
+    } else {
+        OptimizeType::ZOrder(options.z_order_columns.clone())
+    };
+
+    let builder = DeltaOps(table).optimize().with_type(optimize_type);
+    let builder = match options.target_file_size {
+        Some(target_file_size) => builder.with_target_size(target_file_size),
+        None => builder,
+    };
+    let (table, metrics) = builder.await?;
+
+    info!(
+        "Optimized delta table '{}': compacted {} files into {} files",
+        table_def.name, metrics.num_files_removed, metrics.num_files_added
+    );
+
+    Ok(table)
+}
+
+// execute a single write attempt against the latest state of the deltatable
+async fn try_write(
+    table_def: &DeltaDestination,
+    table_schema: Schema,
+    data: DataFrame,
+) -> Result<DeltaTable> {
     let ops = DeltaOps::try_from_uri_with_storage_options(
         table_def.location.clone(),
         table_def.storage_options.clone(),
     )
     .await?;
 
+    if let (Some(app_id), Some(app_version)) = (&table_def.app_id, table_def.app_version) {
+        let committed = ops
+            .0
+            .get_app_transaction_version()
+            .get(app_id)
+            .map(|transaction| transaction.version);
+
+        if let Some(committed) = committed.filter(|committed| *committed >= app_version) {
+            info!(
+                "Skipping write to delta table '{}', version {app_version} of application '{app_id}' was already committed (latest version {committed})",
+                table_def.name
+            );
+            return Ok(ops.0);
+        }
+    }
+
     let table = match &table_def.write_mode {
         WriteMode::Append => {
             let batches = data.collect().await?;
-            ops.write(batches).with_save_mode(SaveMode::Append).await?
+            ops.write(batches)
+                .with_save_mode(SaveMode::Append)
+                .with_commit_properties(commit_properties(table_def))
+                .await?
+        }
+        WriteMode::Upsert(params) => {
+            merge(
+                ops,
+                table_schema,
+                params.merge_columns().to_vec(),
+                params.null_equals_null(),
+                data,
+                commit_properties(table_def),
+            )
+            .await?
         }
-        WriteMode::Upsert(merge_cols) => merge(ops, table_schema, merge_cols.clone(), data).await?,
         WriteMode::Replace(conditions) => {
             let batches = data.collect().await?;
 
@@ -109,63 +510,99 @@ pub(super) async fn write(table_def: &DeltaDestination, data: DataFrame) -> Resu
                 .with_schema_mode(deltalake::operations::write::SchemaMode::Overwrite)
                 .with_save_mode(SaveMode::Overwrite)
                 .with_replace_where(build_expression(conditions.clone()))
+                .with_commit_properties(commit_properties(table_def))
                 .await?
         }
+        WriteMode::ScdType2(options) => {
+            scd_type2(ops, table_def, table_schema, options, data).await?
+        }
+    };
+
+    Ok(table)
+}
+
+// properties of the commit of a write, recording the commit metadata of the destination in the commit info
+// and the application transaction if the destination is idempotent
+fn commit_properties(table_def: &DeltaDestination) -> CommitProperties {
+    let engine_info = format!("aqueducts/{}", env!("CARGO_PKG_VERSION"));
+    let metadata = std::iter::once((ENGINE_INFO_KEY.to_string(), engine_info))
+        .chain(table_def.commit_metadata.clone())
+        .collect::<HashMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| (key, serde_json::Value::String(value)));
+
+    let properties = CommitProperties::default().with_metadata(metadata);
+
+    match (&table_def.app_id, table_def.app_version) {
+        (Some(app_id), Some(app_version)) => {
+            properties.with_application_transaction(Transaction::new(app_id, app_version))
+        }
+        _ => properties,
+    }
+}
+
+// delete files no longer referenced by the table, only logging them when running as dry run
+async fn vacuum(
+    table_def: &DeltaDestination,
+    options: &VacuumOptions,
+    table: DeltaTable,
+) -> Result<DeltaTable> {
+    let builder = DeltaOps(table)
+        .vacuum()
+        .with_dry_run(options.dry_run)
+        .with_enforce_retention_duration(options.enforce_retention_duration);
+    let builder = match options.retention_hours {
+        Some(hours) => builder.with_retention_period(chrono::Duration::hours(hours as i64)),
+        None => builder,
     };
+    let (table, metrics) = builder.await?;
+
+    if metrics.dry_run {
+        info!(
+            "Vacuum dry run for delta table '{}' would delete {} files: {:?}",
+            table_def.name,
+            metrics.files_deleted.len(),
+            metrics.files_deleted
+        );
+    } else {
+        info!(
+            "Vacuumed delta table '{}': deleted {} files",
+            table_def.name,
+            metrics.files_deleted.len()
+        );
+    }
 
     Ok(table)
 }
 
+// check if the error was caused by a concurrent commit to the deltatable
+fn is_commit_conflict(error: &DeltaTableError) -> bool {
+    matches!(
+        error,
+        DeltaTableError::VersionAlreadyExists(_)
+            | DeltaTableError::Transaction {
+                source: TransactionError::VersionAlreadyExists(_)
+                    | TransactionError::CommitConflict(_)
+                    | TransactionError::MaxCommitAttempts(_)
+            }
+    )
+}
+
 /// Merge a dataframe with a deltatable
 /// This merge behaves like an upsert where the merge columns are used as the unique keys and every other column is updated to the new values provided by the dataframe
 /// Additionally this merge can check for equality on lists of primitive types where equality is determined by the contained elements but NOT on the element order
+/// Null keys only match other null keys if `null_equals_null` is set
 async fn merge(
     ops: DeltaOps,
     table_schema: Schema,
     merge_cols: Vec<String>,
+    null_equals_null: bool,
     data: DataFrame,
+    commit_properties: CommitProperties,
 ) -> Result<DeltaTable> {
-    use datafusion::arrow::datatypes::DataType;
-    use datafusion::prelude::{array_empty, array_has_all, col, Expr};
-
-    let merge_predicate = merge_cols
-        .iter()
-        .map(|column_name| {
-            let field = table_schema
-                .field_with_name(column_name.as_str())
-                .expect("field not found in schema");
+    use datafusion::prelude::col;
 
-            match field.data_type() {
-                DataType::Struct(_)
-                | DataType::Union(_, _)
-                | DataType::Dictionary(_, _)
-                | DataType::Map(_, _)
-                | DataType::Binary
-                | DataType::LargeBinary
-                | DataType::FixedSizeBinary(_) => {
-                    unimplemented!("unsupported column type for merge")
-                }
-                DataType::List(_) => array_has_all(
-                    col(format!("old.{column_name}")),
-                    col(format!("new.{column_name}")),
-                )
-                .and(array_has_all(
-                    col(format!("new.{column_name}")),
-                    col(format!("old.{column_name}")),
-                ))
-                .or(array_empty(col(format!("old.{column_name}")))
-                    .and(array_empty(col(format!("old.{column_name}")))))
-                .or(col(format!("old.{column_name}"))
-                    .is_null()
-                    .and(col(format!("new.{column_name}")).is_null())),
-                _ => col(format!("old.{column_name}"))
-                    .eq(col(format!("new.{column_name}")))
-                    .or(col(format!("old.{column_name}"))
-                        .is_null()
-                        .and(col(format!("new.{column_name}")).is_null())),
-            }
-        })
-        .collect::<Vec<Expr>>();
+    let merge_predicate = merge_predicate(&table_schema, &merge_cols, null_equals_null)?;
 
     let update_columns = table_schema
         .fields
@@ -181,15 +618,10 @@ async fn merge(
         .collect::<Vec<String>>();
 
     let (table, _) = ops
-        .merge(
-            data,
-            merge_predicate
-                .into_iter()
-                .reduce(|acc, e| acc.and(e))
-                .expect("merge predicate is empty"),
-        )
+        .merge(data, merge_predicate)
         .with_target_alias("old")
         .with_source_alias("new")
+        .with_commit_properties(commit_properties)
         .when_not_matched_insert(|insert| {
             table_schema
                 .fields
@@ -209,49 +641,375 @@ async fn merge(
     Ok(table)
 }
 
-/// Validate if the table schema matches the data that is about to be written (casts the dataframe to the output schema)
-fn validate_schema(schema: Schema, data: DataFrame) -> Result<DataFrame> {
+// predicate matching the rows of the table (`old`) with the rows of the data (`new`) on all merge columns
+// struct, map, union, dictionary and binary columns can't be compared and are rejected
+fn merge_predicate(
+    table_schema: &Schema,
+    merge_cols: &[String],
+    null_equals_null: bool,
+) -> Result<datafusion::prelude::Expr> {
     use datafusion::arrow::datatypes::DataType;
-    use datafusion::prelude::{cast, col, Expr};
+    use datafusion::prelude::{array_empty, array_has_all, col, Expr};
 
-    let columns = schema
-        .fields
-        .into_iter()
-        .map(|field| {
-            let has_input_type = |data_type: DataType| {
-                data.schema()
-                    .field_with_unqualified_name(field.name())
-                    .map(|f| f.data_type().equals_datatype(&data_type))
-                    .unwrap_or_default()
-            };
+    let merge_predicate = merge_cols
+        .iter()
+        .map(|column_name| {
+            let field = table_schema
+                .field_with_name(column_name.as_str())
+                .map_err(|_| error::Error::MissingMergeColumn(column_name.clone()))?;
 
-            match field.data_type() {
-                DataType::Utf8 if has_input_type(DataType::LargeUtf8) => {
-                    cast(col(field.name()), DataType::LargeUtf8).alias(field.name())
-                }
-                deltalake::arrow::datatypes::DataType::Binary
-                    if has_input_type(DataType::LargeBinary) =>
-                {
-                    cast(col(field.name()), DataType::LargeBinary).alias(field.name())
+            let equal = match field.data_type() {
+                DataType::Struct(_)
+                | DataType::Union(_, _)
+                | DataType::Dictionary(_, _)
+                | DataType::Map(_, _)
+                | DataType::Binary
+                | DataType::LargeBinary
+                | DataType::FixedSizeBinary(_) => {
+                    return Err(error::Error::UnsupportedMergeColumnType(
+                        column_name.clone(),
+                        field.data_type().clone(),
+                    ))
                 }
-                _ => cast(col(field.name()), field.data_type().clone()).alias(field.name()),
-            }
-        })
-        .collect::<Vec<Expr>>();
+                DataType::List(_) => array_has_all(
+                    col(format!("old.{column_name}")),
+                    col(format!("new.{column_name}")),
+                )
+                .and(array_has_all(
+                    col(format!("new.{column_name}")),
+                    col(format!("old.{column_name}")),
+                ))
+                .or(array_empty(col(format!("old.{column_name}")))
+                    .and(array_empty(col(format!("old.{column_name}"))))),
+                _ => col(format!("old.{column_name}")).eq(col(format!("new.{column_name}"))),
+            };
 
-    let result = data.select(columns)?;
-    assert!(result.schema().matches_arrow_schema(&schema));
+            Ok(match null_equals_null {
+                true => equal.or(col(format!("old.{column_name}"))
+                    .is_null()
+                    .and(col(format!("new.{column_name}")).is_null())),
+                false => equal,
+            })
+        })
+        .collect::<Result<Vec<Expr>>>()?;
 
-    Ok(result)
+    merge_predicate
+        .into_iter()
+        .reduce(|acc, e| acc.and(e))
+        .ok_or(error::Error::EmptyMergeColumns)
 }
 
-/// Build expression to replace values matching the `ReplaceCondition`s that was defined
-fn build_expression(conditions: Vec<ReplaceCondition>) -> datafusion::logical_expr::Expr {
-    use datafusion::prelude::{col, lit};
+/// Merge a dataframe into a deltatable as a slowly changing dimension (type 2)
+/// Uses a single merge over the data unioned with the changed rows keyed by `NULL`:
+/// rows matching the current version of their business key expire it when a tracked column changed,
+/// while the `NULL` keyed copies never match and insert the new versions, as well as rows of new business keys
+/// Applying the same data twice is a no-op as unchanged rows neither expire nor insert anything
+async fn scd_type2(
+    ops: DeltaOps,
+    table_def: &DeltaDestination,
+    table_schema: Schema,
+    options: &ScdType2Options,
+    data: DataFrame,
+) -> Result<DeltaTable> {
+    use datafusion::execution::context::SessionContext;
+    use datafusion::logical_expr::{binary_expr, Operator};
+    use datafusion::prelude::{cast, col, lit, Expr, JoinType};
+    use datafusion::scalar::ScalarValue;
+    use std::sync::Arc;
 
-    conditions
-        .into_iter()
-        .map(
+    if options.business_key_columns.is_empty() {
+        return Err(error::Error::MissingBusinessKey);
+    }
+
+    let missing_column = options
+        .business_key_columns
+        .iter()
+        .chain(options.tracked_columns.iter())
+        .chain(options.scd_columns())
+        .find(|column| table_schema.field_with_name(column).is_err());
+    if let Some(column) = missing_column {
+        return Err(error::Error::MissingScdColumn(column.clone()));
+    }
+
+    let tracked_columns = if options.tracked_columns.is_empty() {
+        table_schema
+            .fields
+            .iter()
+            .map(|field| field.name())
+            .filter(|name| {
+                !options.business_key_columns.contains(*name)
+                    && !options.scd_columns().contains(name)
+            })
+            .cloned()
+            .collect::<Vec<String>>()
+    } else {
+        options.tracked_columns.clone()
+    };
+
+    let merge_key = |column: &str| format!("__merge_key_{column}");
+    let is_changed = tracked_columns
+        .iter()
+        .map(|column| {
+            binary_expr(
+                col(format!("old.{column}")),
+                Operator::IsDistinctFrom,
+                col(format!("new.{column}")),
+            )
+        })
+        .reduce(|acc, e| acc.or(e))
+        .unwrap_or(lit(false));
+    let is_current =
+        |alias: &str| col(format!("{alias}.{}", options.is_current_column)).eq(lit(true));
+
+    let current = alias(
+        SessionContext::new().read_table(Arc::new(ops.0.clone()))?,
+        "old",
+    )?;
+
+    // null merge keys never match a row of the table, so the staged rows are inserted as new versions
+    let null_merge_keys = options
+        .business_key_columns
+        .iter()
+        .map(|column| {
+            let field = table_schema
+                .field_with_name(column)
+                .map_err(|_| error::Error::MissingScdColumn(column.clone()))?;
+
+            Ok(cast(lit(ScalarValue::Null), field.data_type().clone()).alias(merge_key(column)))
+        })
+        .collect::<Result<Vec<Expr>>>()?;
+
+    // rows of the data whose tracked columns differ from the current version of their business key
+    let changed = alias(data.clone(), "new")?
+        .join_on(
+            current,
+            JoinType::Inner,
+            options
+                .business_key_columns
+                .iter()
+                .map(|column| col(format!("new.{column}")).eq(col(format!("old.{column}")))),
+        )?
+        .filter(is_current("old").and(is_changed.clone()))?
+        .select(
+            table_schema
+                .fields
+                .iter()
+                .map(|field| col(format!("new.{}", field.name())).alias(field.name()))
+                .chain(null_merge_keys)
+                .collect::<Vec<Expr>>(),
+        )?;
+
+    let staged = data
+        .select(
+            table_schema
+                .fields
+                .iter()
+                .map(|field| col(field.name()))
+                .chain(
+                    options
+                        .business_key_columns
+                        .iter()
+                        .map(|column| col(column.as_str()).alias(merge_key(column))),
+                )
+                .collect::<Vec<Expr>>(),
+        )?
+        .union(changed)?;
+
+    let merge_predicate = options
+        .business_key_columns
+        .iter()
+        .map(|column| col(format!("old.{column}")).eq(col(format!("new.{}", merge_key(column)))))
+        .fold(is_current("old"), |acc, e| acc.and(e));
+
+    let (table, metrics) = DeltaOps(ops.0)
+        .merge(staged, merge_predicate)
+        .with_target_alias("old")
+        .with_source_alias("new")
+        .with_commit_properties(commit_properties(table_def))
+        .when_matched_update(|update| {
+            update
+                .predicate(is_changed)
+                .update(
+                    options.valid_to_column.as_str(),
+                    col(format!("new.{}", options.valid_from_column)),
+                )
+                .update(options.is_current_column.as_str(), lit(false))
+        })?
+        .when_not_matched_insert(|insert| {
+            table_schema
+                .fields
+                .iter()
+                .map(|field| field.name().clone())
+                .fold(insert, |acc, column_name| {
+                    acc.set(column_name.as_str(), col(format!("new.{column_name}")))
+                })
+        })?
+        .await?;
+
+    info!(
+        "SCD type 2 write to delta table '{}': expired {} versions, inserted {} versions",
+        table_def.name, metrics.num_target_rows_updated, metrics.num_target_rows_inserted
+    );
+
+    Ok(table)
+}
+
+// qualify the columns of a dataframe with an alias so that they can be referenced as `alias.column` in joins
+fn alias(data: DataFrame, alias: &str) -> Result<DataFrame> {
+    use datafusion::logical_expr::LogicalPlanBuilder;
+
+    let (state, plan) = data.into_parts();
+    let plan = LogicalPlanBuilder::from(plan).alias(alias)?.build()?;
+
+    Ok(DataFrame::new(state, plan))
+}
+
+/// Add the SCD type 2 columns to the dataframe, new versions are valid from the time of the write and current
+fn with_scd_columns(options: &ScdType2Options, data: DataFrame) -> Result<DataFrame> {
+    use datafusion::prelude::lit;
+    use datafusion::scalar::ScalarValue;
+
+    if let Some(column) = options
+        .scd_columns()
+        .into_iter()
+        .find(|column| data.schema().field_with_unqualified_name(column).is_ok())
+    {
+        return Err(error::Error::ScdColumnExists(column.clone()));
+    }
+
+    let now = chrono::Utc::now().timestamp_micros();
+    let data = data
+        .with_column(
+            options.valid_from_column.as_str(),
+            lit(ScalarValue::TimestampMicrosecond(
+                Some(now),
+                Some("UTC".into()),
+            )),
+        )?
+        .with_column(
+            options.valid_to_column.as_str(),
+            lit(ScalarValue::TimestampMicrosecond(None, Some("UTC".into()))),
+        )?
+        .with_column(options.is_current_column.as_str(), lit(true))?;
+
+    Ok(data)
+}
+
+/// Columns of the table schema including the generated partition columns
+fn table_columns(table_def: &DeltaDestination) -> Vec<StructField> {
+    let generated = table_def
+        .generated_partition_cols
+        .iter()
+        .map(|column| StructField::new(column.name.clone(), column.data_type.clone(), true));
+
+    table_def.schema.iter().cloned().chain(generated).collect()
+}
+
+/// Partition columns of the table including the generated partition columns
+fn partition_columns(table_def: &DeltaDestination) -> Vec<String> {
+    let generated = table_def
+        .generated_partition_cols
+        .iter()
+        .map(|column| column.name.clone());
+
+    table_def
+        .partition_cols
+        .iter()
+        .cloned()
+        .chain(generated)
+        .collect()
+}
+
+/// Add the generated columns to the dataframe, validating the expressions against the schema of the data
+fn with_generated_columns(columns: &[GeneratedColumn], data: DataFrame) -> Result<DataFrame> {
+    use datafusion::arrow::{compute::can_cast_types, datatypes::DataType as ArrowDataType};
+    use datafusion::logical_expr::ExprSchemable;
+    use datafusion::prelude::cast;
+
+    columns.iter().try_fold(data, |data, column| {
+        if data
+            .schema()
+            .field_with_unqualified_name(&column.name)
+            .is_ok()
+        {
+            return Err(error::Error::GeneratedColumnExists(column.name.clone()));
+        }
+
+        let expr = data
+            .parse_sql_expr(column.expression.as_str())
+            .and_then(|expr| {
+                let data_type = expr.get_type(data.schema())?;
+                Ok((expr, data_type))
+            })
+            .map_err(|e| error::Error::InvalidGeneratedColumn(column.name.clone(), e.to_string()));
+        let (expr, data_type) = expr?;
+
+        let expected = ArrowDataType::try_from(&column.data_type)?;
+        if !can_cast_types(&data_type, &expected) {
+            return Err(error::Error::GeneratedColumnType(
+                column.name.clone(),
+                expected,
+                data_type,
+            ));
+        }
+
+        Ok(data.with_column(column.name.as_str(), cast(expr, expected))?)
+    })
+}
+
+/// Validate if the table schema matches the data that is about to be written (casts the dataframe to the output schema)
+fn validate_schema(schema: Schema, data: DataFrame) -> Result<DataFrame> {
+    use datafusion::arrow::datatypes::DataType;
+    use datafusion::prelude::{cast, col, Expr};
+
+    let diff = schema_diff(&schema, data.schema().as_arrow());
+    if !diff.missing_columns.is_empty() {
+        return Err(error::Error::SchemaMismatch(diff));
+    }
+
+    let columns = schema
+        .fields
+        .into_iter()
+        .map(|field| {
+            let has_input_type = |data_type: DataType| {
+                data.schema()
+                    .field_with_unqualified_name(field.name())
+                    .map(|f| f.data_type().equals_datatype(&data_type))
+                    .unwrap_or_default()
+            };
+
+            match field.data_type() {
+                DataType::Utf8 if has_input_type(DataType::LargeUtf8) => {
+                    cast(col(field.name()), DataType::LargeUtf8).alias(field.name())
+                }
+                deltalake::arrow::datatypes::DataType::Binary
+                    if has_input_type(DataType::LargeBinary) =>
+                {
+                    cast(col(field.name()), DataType::LargeBinary).alias(field.name())
+                }
+                _ => cast(col(field.name()), field.data_type().clone()).alias(field.name()),
+            }
+        })
+        .collect::<Vec<Expr>>();
+
+    let result = data.select(columns)?;
+    if !result.schema().matches_arrow_schema(&schema) {
+        return Err(error::Error::SchemaMismatch(schema_diff(
+            &schema,
+            result.schema().as_arrow(),
+        )));
+    }
+
+    Ok(result)
+}
+
+/// Build expression to replace values matching the `ReplaceCondition`s that was defined
+fn build_expression(conditions: Vec<ReplaceCondition>) -> datafusion::logical_expr::Expr {
+    use datafusion::prelude::{col, lit};
+
+    conditions
+        .into_iter()
+        .map(
             |ReplaceCondition {
                  column: field_name,
                  value,
@@ -291,6 +1049,36 @@ mod tests {
         Url::parse(table_path.as_str()).unwrap()
     }
 
+    #[test]
+    fn test_commit_retry_backoff_ok() {
+        assert_eq!(Duration::from_millis(100), commit_retry_backoff(1));
+        assert_eq!(Duration::from_millis(800), commit_retry_backoff(4));
+        assert_eq!(MAX_COMMIT_RETRY_BACKOFF, commit_retry_backoff(8));
+        assert_eq!(MAX_COMMIT_RETRY_BACKOFF, commit_retry_backoff(64));
+        assert_eq!(MAX_COMMIT_RETRY_BACKOFF, commit_retry_backoff(usize::MAX));
+    }
+
+    #[test]
+    fn test_validate_max_commit_retries_err() {
+        let mut table_def = DeltaDestination::new(
+            "test_table".into(),
+            generate_test_table_path(),
+            HashMap::new(),
+            HashMap::new(),
+            WriteMode::Append,
+            vec![],
+            vec![],
+        );
+        assert!(validate(&table_def).is_ok());
+
+        table_def.max_commit_retries = MAX_COMMIT_RETRIES + 1;
+
+        assert!(matches!(
+            validate(&table_def),
+            Err(error::Error::InvalidCommitRetries(name, 20)) if name == "test_table"
+        ));
+    }
+
     #[tokio::test]
     async fn test_create_table_ok() {
         let ctx = SessionContext::new();
@@ -309,7 +1097,7 @@ mod tests {
             location,
             HashMap::default(),
             HashMap::default(),
-            WriteMode::Upsert(vec![String::from("col_1")]),
+            WriteMode::Upsert(vec![String::from("col_1")].into()),
             vec!["col_1".into()],
             schema.clone(),
         );
@@ -328,6 +1116,403 @@ mod tests {
         assert!(result.schema().matches_arrow_schema(&expected_schema));
     }
 
+    #[tokio::test]
+    async fn test_write_generated_partition_cols_ok() {
+        let ctx = SessionContext::new();
+        let location = generate_test_table_path();
+
+        let schema = vec![
+            StructField::new(
+                "event_time",
+                DataType::Primitive(PrimitiveType::Timestamp),
+                false,
+            ),
+            StructField::new("value", DataType::Primitive(PrimitiveType::Integer), false),
+        ];
+        let mut definition = DeltaDestination::new(
+            "test_table".into(),
+            location,
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Append,
+            vec![],
+            schema,
+        );
+        definition.generated_partition_cols = vec![GeneratedColumn::new(
+            "event_date".into(),
+            "CAST(event_time AS DATE)".into(),
+            DataType::Primitive(PrimitiveType::Date),
+        )];
+
+        let _ = create(&definition).await.unwrap();
+
+        let df = ctx
+            .sql(
+                "SELECT * FROM (VALUES (CAST('2024-01-01T10:00:00' AS TIMESTAMP), 1), (CAST('2024-01-02T12:30:00' AS TIMESTAMP), 2)) AS t(event_time, value)",
+            )
+            .await
+            .unwrap();
+        let delta_table = write(&definition, df).await.unwrap();
+
+        assert_eq!(
+            vec!["event_date".to_string()],
+            delta_table.metadata().unwrap().partition_columns
+        );
+
+        let result = ctx
+            .read_table(Arc::new(delta_table))
+            .unwrap()
+            .select_columns(&["value", "event_date"])
+            .unwrap()
+            .sort(vec![col("value").sort(true, false)])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+-------+------------+",
+                "| value | event_date |",
+                "+-------+------------+",
+                "| 1     | 2024-01-01 |",
+                "| 2     | 2024-01-02 |",
+                "+-------+------------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_optimize_ok() {
+        let ctx = SessionContext::new();
+
+        let schema = vec![
+            StructField::new("id", DataType::Primitive(PrimitiveType::Integer), false),
+            StructField::new("value", DataType::Primitive(PrimitiveType::String), false),
+        ];
+        let mut definition = DeltaDestination::new(
+            "test_table".into(),
+            generate_test_table_path(),
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Append,
+            vec![],
+            schema,
+        );
+        definition.optimize = Some(OptimizeOptions::new(None));
+
+        let _ = create(&definition).await.unwrap();
+
+        let _ = write(
+            &definition,
+            ctx.sql("SELECT CAST(1 AS INT) AS id, 'value_1' AS value")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        let table = write(
+            &definition,
+            ctx.sql("SELECT CAST(2 AS INT) AS id, 'value_2' AS value")
+                .await
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(1, table.get_files_count());
+
+        let result = ctx
+            .read_table(Arc::new(table))
+            .unwrap()
+            .sort(vec![col("id").sort(true, false)])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+----+---------+",
+                "| id | value   |",
+                "+----+---------+",
+                "| 1  | value_1 |",
+                "| 2  | value_2 |",
+                "+----+---------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_commit_metadata_ok() {
+        let ctx = SessionContext::new();
+
+        let schema = vec![StructField::new(
+            "id",
+            DataType::Primitive(PrimitiveType::Integer),
+            false,
+        )];
+        let mut definition = DeltaDestination::new(
+            "test_table".into(),
+            generate_test_table_path(),
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Append,
+            vec![],
+            schema,
+        );
+        definition.commit_metadata = HashMap::from([
+            ("pipeline".to_string(), "aggregate_readings".to_string()),
+            ("execution_id".to_string(), "42".to_string()),
+        ]);
+
+        let _ = create(&definition).await.unwrap();
+        let _ = write(
+            &definition,
+            ctx.sql("SELECT CAST(1 AS INT) AS id").await.unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let log = std::fs::read_to_string(
+            definition
+                .location
+                .to_file_path()
+                .unwrap()
+                .join("_delta_log/00000000000000000001.json"),
+        )
+        .unwrap();
+        let commit_info = log
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find_map(|action| action.get("commitInfo").cloned())
+            .unwrap();
+
+        assert_eq!("aggregate_readings", commit_info["pipeline"]);
+        assert_eq!("42", commit_info["execution_id"]);
+        assert_eq!(
+            format!("aqueducts/{}", env!("CARGO_PKG_VERSION")),
+            commit_info["engineInfo"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_app_transaction_idempotent_ok() {
+        let ctx = SessionContext::new();
+
+        let schema = vec![StructField::new(
+            "id",
+            DataType::Primitive(PrimitiveType::Integer),
+            false,
+        )];
+        let mut definition = DeltaDestination::new(
+            "test_table".into(),
+            generate_test_table_path(),
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Append,
+            vec![],
+            schema,
+        );
+        definition.app_id = Some("scheduler".into());
+        definition.app_version = Some(1);
+
+        let _ = create(&definition).await.unwrap();
+
+        let data = ctx.sql("SELECT CAST(1 AS INT) AS id").await.unwrap();
+        let _ = write(&definition, data.clone()).await.unwrap();
+        let table = write(&definition, data.clone()).await.unwrap();
+
+        let count = ctx
+            .read_table(Arc::new(table))
+            .unwrap()
+            .count()
+            .await
+            .unwrap();
+        assert_eq!(1, count);
+
+        definition.app_version = Some(2);
+        let table = write(&definition, data).await.unwrap();
+
+        let count = ctx
+            .read_table(Arc::new(table))
+            .unwrap()
+            .count()
+            .await
+            .unwrap();
+        assert_eq!(2, count);
+    }
+
+    #[tokio::test]
+    async fn test_write_incomplete_app_transaction_err() {
+        let ctx = SessionContext::new();
+
+        let mut definition = DeltaDestination::new(
+            "test_table".into(),
+            generate_test_table_path(),
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Append,
+            vec![],
+            vec![StructField::new(
+                "id",
+                DataType::Primitive(PrimitiveType::Integer),
+                false,
+            )],
+        );
+        definition.app_id = Some("scheduler".into());
+
+        let result = write(
+            &definition,
+            ctx.sql("SELECT CAST(1 AS INT) AS id").await.unwrap(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::IncompleteAppTransaction(name)) if name == "test_table"
+        ));
+    }
+
+    fn count_data_files(location: &Url) -> usize {
+        std::fs::read_dir(location.to_file_path().unwrap())
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".parquet")
+            })
+            .count()
+    }
+
+    fn vacuum_definition(vacuum: VacuumOptions) -> DeltaDestination {
+        let schema = vec![StructField::new(
+            "id",
+            DataType::Primitive(PrimitiveType::Integer),
+            false,
+        )];
+        let mut definition = DeltaDestination::new(
+            "test_table".into(),
+            generate_test_table_path(),
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Replace(vec![]),
+            vec![],
+            schema,
+        );
+        definition.vacuum = Some(vacuum);
+
+        definition
+    }
+
+    #[tokio::test]
+    async fn test_write_vacuum_ok() {
+        let ctx = SessionContext::new();
+
+        let mut vacuum = VacuumOptions::new(Some(0));
+        vacuum.enforce_retention_duration = false;
+        let definition = vacuum_definition(vacuum);
+
+        let _ = create(&definition).await.unwrap();
+
+        for id in 1..=3 {
+            let df = ctx
+                .sql(format!("SELECT CAST({id} AS INT) AS id").as_str())
+                .await
+                .unwrap();
+            let _ = write(&definition, df).await.unwrap();
+        }
+
+        assert_eq!(1, count_data_files(&definition.location));
+    }
+
+    #[tokio::test]
+    async fn test_write_vacuum_dry_run_ok() {
+        let ctx = SessionContext::new();
+
+        let mut vacuum = VacuumOptions::new(Some(0));
+        vacuum.enforce_retention_duration = false;
+        vacuum.dry_run = true;
+        let definition = vacuum_definition(vacuum);
+
+        let _ = create(&definition).await.unwrap();
+
+        for id in 1..=3 {
+            let df = ctx
+                .sql(format!("SELECT CAST({id} AS INT) AS id").as_str())
+                .await
+                .unwrap();
+            let _ = write(&definition, df).await.unwrap();
+        }
+
+        assert_eq!(3, count_data_files(&definition.location));
+    }
+
+    #[tokio::test]
+    async fn test_write_vacuum_retention_below_threshold_err() {
+        let ctx = SessionContext::new();
+        let definition = vacuum_definition(VacuumOptions::new(Some(0)));
+
+        let _ = create(&definition).await.unwrap();
+
+        let df = ctx.sql("SELECT CAST(1 AS INT) AS id").await.unwrap();
+        let result = write(&definition, df).await;
+
+        assert!(matches!(result, Err(error::Error::DeltaTableError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_write_generated_partition_cols_invalid_type_err() {
+        let ctx = SessionContext::new();
+        let definition = {
+            let mut definition = DeltaDestination::new(
+                "test_table".into(),
+                generate_test_table_path(),
+                HashMap::default(),
+                HashMap::default(),
+                WriteMode::Append,
+                vec![],
+                vec![StructField::new(
+                    "value",
+                    DataType::Primitive(PrimitiveType::Integer),
+                    false,
+                )],
+            );
+            definition.generated_partition_cols = vec![GeneratedColumn::new(
+                "flags".into(),
+                "make_array(value)".into(),
+                DataType::Primitive(PrimitiveType::Date),
+            )];
+            definition
+        };
+
+        let df = ctx.sql("SELECT CAST(1 AS INT) AS value").await.unwrap();
+        let result = write(&definition, df).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::GeneratedColumnType(_, _, _))
+        ));
+    }
+
+    #[test]
+    fn test_is_commit_conflict() {
+        assert!(is_commit_conflict(&DeltaTableError::VersionAlreadyExists(
+            1
+        )));
+        assert!(is_commit_conflict(&DeltaTableError::Transaction {
+            source: TransactionError::VersionAlreadyExists(1)
+        }));
+        assert!(!is_commit_conflict(&DeltaTableError::Generic(
+            "not a conflict".into()
+        )));
+    }
+
     #[tokio::test]
     async fn test_merge_dataframe_ok() {
         let ctx = SessionContext::new();
@@ -345,7 +1530,7 @@ mod tests {
             location,
             HashMap::default(),
             HashMap::default(),
-            WriteMode::Upsert(vec![String::from("col_1")]),
+            WriteMode::Upsert(vec![String::from("col_1")].into()),
             vec!["col_1".into()],
             schema.clone(),
         );
@@ -428,4 +1613,272 @@ mod tests {
             initial.as_slice()
         );
     }
+
+    // upsert a row with a null key into a table already containing a null key
+    async fn merge_null_keys(null_equals_null: bool) -> Vec<RecordBatch> {
+        let ctx = SessionContext::new();
+        let location = generate_test_table_path();
+
+        let schema = vec![
+            StructField::new("key", DataType::Primitive(PrimitiveType::String), true),
+            StructField::new("value", DataType::Primitive(PrimitiveType::Integer), false),
+        ];
+        let mut options = UpsertOptions::new(vec!["key".into()]);
+        options.null_equals_null = null_equals_null;
+        let definition = DeltaDestination::new(
+            "test_table".into(),
+            location,
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::Upsert(UpsertParams::Options(options)),
+            vec![],
+            schema,
+        );
+
+        let mut delta_table = create(&definition).await.unwrap();
+
+        for value in [1, 2] {
+            let batch = RecordBatch::try_from_iter(vec![
+                (
+                    "key",
+                    Arc::new(StringArray::from(vec![None, Some("a")])) as ArrayRef,
+                ),
+                (
+                    "value",
+                    Arc::new(Int32Array::from(vec![value, value])) as ArrayRef,
+                ),
+            ])
+            .unwrap();
+            delta_table = write(&definition, ctx.read_batch(batch).unwrap())
+                .await
+                .unwrap();
+        }
+
+        ctx.read_table(Arc::new(delta_table))
+            .unwrap()
+            .select_columns(&["key", "value"])
+            .unwrap()
+            .sort(vec![
+                col("key").sort(true, true),
+                col("value").sort(true, false),
+            ])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_merge_null_equals_null_ok() {
+        let result = merge_null_keys(true).await;
+
+        assert_batches_eq!(
+            [
+                "+-----+-------+",
+                "| key | value |",
+                "+-----+-------+",
+                "|     | 2     |",
+                "| a   | 2     |",
+                "+-----+-------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_null_not_equals_null_ok() {
+        let result = merge_null_keys(false).await;
+
+        assert_batches_eq!(
+            [
+                "+-----+-------+",
+                "| key | value |",
+                "+-----+-------+",
+                "|     | 1     |",
+                "|     | 2     |",
+                "| a   | 2     |",
+                "+-----+-------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_merge_predicate_struct_key_err() {
+        let key_type = StructType::new(vec![StructField::new(
+            "id",
+            DataType::Primitive(PrimitiveType::Integer),
+            false,
+        )]);
+        let schema = StructType::new(vec![
+            StructField::new("key", DataType::Struct(Box::new(key_type)), false),
+            StructField::new("value", DataType::Primitive(PrimitiveType::Integer), false),
+        ]);
+        let schema = TryInto::<Schema>::try_into(&schema).unwrap();
+
+        let result = merge_predicate(&schema, &["key".to_string()], true);
+
+        assert!(matches!(
+            result,
+            Err(error::Error::UnsupportedMergeColumnType(column, datafusion::arrow::datatypes::DataType::Struct(_)))
+                if column == "key"
+        ));
+    }
+
+    #[test]
+    fn test_merge_predicate_missing_key_err() {
+        let schema = StructType::new(vec![StructField::new(
+            "value",
+            DataType::Primitive(PrimitiveType::Integer),
+            false,
+        )]);
+        let schema = TryInto::<Schema>::try_into(&schema).unwrap();
+
+        assert!(matches!(
+            merge_predicate(&schema, &["key".to_string()], true),
+            Err(error::Error::MissingMergeColumn(column)) if column == "key"
+        ));
+        assert!(matches!(
+            merge_predicate(&schema, &[], true),
+            Err(error::Error::EmptyMergeColumns)
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_upsert_params_ok() {
+        let columns: WriteMode =
+            serde_json::from_str(r#"{"operation": "Upsert", "params": ["date"]}"#).unwrap();
+        let options: WriteMode = serde_json::from_str(
+            r#"{"operation": "Upsert", "params": {"merge_columns": ["date"], "null_equals_null": false}}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            columns,
+            WriteMode::Upsert(params) if params.merge_columns() == ["date"] && params.null_equals_null()
+        ));
+        assert!(matches!(
+            options,
+            WriteMode::Upsert(params) if params.merge_columns() == ["date"] && !params.null_equals_null()
+        ));
+    }
+
+    fn scd_definition(location: Url) -> DeltaDestination {
+        let schema = vec![
+            StructField::new("id", DataType::Primitive(PrimitiveType::Integer), false),
+            StructField::new("name", DataType::Primitive(PrimitiveType::String), true),
+            StructField::new(
+                "valid_from",
+                DataType::Primitive(PrimitiveType::Timestamp),
+                false,
+            ),
+            StructField::new(
+                "valid_to",
+                DataType::Primitive(PrimitiveType::Timestamp),
+                true,
+            ),
+            StructField::new(
+                "is_current",
+                DataType::Primitive(PrimitiveType::Boolean),
+                false,
+            ),
+        ];
+
+        DeltaDestination::new(
+            "test_table".into(),
+            location,
+            HashMap::default(),
+            HashMap::default(),
+            WriteMode::ScdType2(ScdType2Options::new(vec!["id".into()])),
+            vec![],
+            schema,
+        )
+    }
+
+    async fn scd_state(ctx: &SessionContext, table: DeltaTable) -> Vec<RecordBatch> {
+        ctx.read_table(Arc::new(table))
+            .unwrap()
+            .select(vec![
+                col("id"),
+                col("name"),
+                col("valid_to").is_null().alias("open"),
+                col("is_current"),
+            ])
+            .unwrap()
+            .sort(vec![
+                col("id").sort(true, false),
+                col("is_current").sort(true, false),
+            ])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_write_scd_type2_ok() {
+        let ctx = SessionContext::new();
+        let definition = scd_definition(generate_test_table_path());
+        let _ = create(&definition).await.unwrap();
+
+        // first load into the empty table
+        let df = ctx
+            .sql("SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS t(id, name)")
+            .await
+            .unwrap();
+        let table = write(&definition, df).await.unwrap();
+
+        assert_batches_eq!(
+            [
+                "+----+------+------+------------+",
+                "| id | name | open | is_current |",
+                "+----+------+------+------------+",
+                "| 1  | a    | true | true       |",
+                "| 2  | b    | true | true       |",
+                "+----+------+------+------------+",
+            ],
+            scd_state(&ctx, table).await.as_slice()
+        );
+
+        // id 2 changed and id 3 is new, applying the same data twice doesn't create new versions
+        for _ in 0..2 {
+            let df = ctx
+                .sql("SELECT * FROM (VALUES (1, 'a'), (2, 'c'), (3, 'd')) AS t(id, name)")
+                .await
+                .unwrap();
+            let table = write(&definition, df).await.unwrap();
+
+            assert_batches_eq!(
+                [
+                    "+----+------+-------+------------+",
+                    "| id | name | open  | is_current |",
+                    "+----+------+-------+------------+",
+                    "| 1  | a    | true  | true       |",
+                    "| 2  | b    | false | false      |",
+                    "| 2  | c    | true  | true       |",
+                    "| 3  | d    | true  | true       |",
+                    "+----+------+-------+------------+",
+                ],
+                scd_state(&ctx, table).await.as_slice()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_scd_type2_missing_column_err() {
+        let ctx = SessionContext::new();
+        let mut definition = scd_definition(generate_test_table_path());
+        let mut options = ScdType2Options::new(vec!["id".into()]);
+        options.tracked_columns = vec!["email".into()];
+        definition.write_mode = WriteMode::ScdType2(options);
+        let _ = create(&definition).await.unwrap();
+
+        let df = ctx
+            .sql("SELECT * FROM (VALUES (1, 'a')) AS t(id, name)")
+            .await
+            .unwrap();
+        let result = write(&definition, df).await;
+
+        assert!(matches!(result, Err(error::Error::MissingScdColumn(column)) if column == "email"));
+    }
 }
@@ -1,12 +1,88 @@
+use crate::schema_transform::SchemaDiff;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Unknown parquet config key: {0}")]
     UnknownConfigOption(String),
+    #[error("Sort column '{0}' not found in the output schema")]
+    MissingSortColumn(String),
+    #[error("Column options defined for unknown column '{0}'")]
+    UnknownColumn(String),
+    #[error(
+        "Column options of file destination '{0}' are only supported for the `Parquet` file type"
+    )]
+    UnsupportedColumnOptions(String),
+    #[error("Partition column '{0}' not found in the output, available columns: {1}")]
+    MissingPartitionColumn(String, String),
+    #[error("Partition columns can't be used when writing a single file")]
+    PartitionedSingleFile,
+    #[error("Overwriting partitions requires partition columns")]
+    MissingOverwritePartitionCols,
+    #[error("Data doesn't match the schema of the delta table:\n{0}")]
+    SchemaMismatch(SchemaDiff),
+    #[error("Ingestion timestamp column '{0}' already exists in the output")]
+    IngestionTimestampColumnExists(String),
+    #[error("Location '{0}' is read-only and cannot be used as a destination")]
+    ReadOnlyLocation(String),
+    #[error("Output exceeds the maximum of {0} rows, aborting write")]
+    MaxOutputRowsExceeded(usize),
+    #[error("Write check for destination '{0}' failed: {1}")]
+    WriteCheckFailed(String, String),
+    #[error("Generated column '{0}' already exists in the output")]
+    GeneratedColumnExists(String),
+    #[error("Invalid expression for generated column '{0}': {1}")]
+    InvalidGeneratedColumn(String, String),
+    #[error(
+        "Generated column '{0}' of type {1} cannot be computed from an expression of type {2}"
+    )]
+    GeneratedColumnType(
+        String,
+        datafusion::arrow::datatypes::DataType,
+        datafusion::arrow::datatypes::DataType,
+    ),
+    #[error("SCD type 2 writes require at least one business key column")]
+    MissingBusinessKey,
+    #[error("SCD type 2 column '{0}' not found in the table schema")]
+    MissingScdColumn(String),
+    #[error("SCD type 2 column '{0}' already exists in the output")]
+    ScdColumnExists(String),
+    #[error("`max_commit_retries` of delta destination '{0}' must not exceed {1}")]
+    InvalidCommitRetries(String, usize),
+    #[error("Storage option '{1}' of delta destination '{0}' is not supported for delta tables")]
+    UnsupportedDeltaStorageOption(String, String),
+    #[error("Delta destination '{0}' requires both `app_id` and `app_version` to be set")]
+    IncompleteAppTransaction(String),
+    #[error("Upsert requires at least one merge column")]
+    EmptyMergeColumns,
+    #[error("Merge column '{0}' not found in the table schema")]
+    MissingMergeColumn(String),
+    #[error("Merge column '{0}' has unsupported type {1}, struct, map, union, dictionary and binary columns can't be used as merge keys")]
+    UnsupportedMergeColumnType(String, datafusion::arrow::datatypes::DataType),
+    #[cfg(feature = "http")]
+    #[error("Batch size of webhook destination '{0}' must be greater than 0")]
+    InvalidWebhookBatchSize(String),
+    #[cfg(feature = "http")]
+    #[error("Request to webhook destination '{0}' failed: {1}")]
+    WebhookRequest(String, String),
+    #[cfg(feature = "http")]
+    #[error("Webhook destination '{0}' responded with status {1}: {2}")]
+    WebhookStatus(String, u16, String),
+
+    #[cfg(feature = "odbc")]
+    #[error(
+        "ODBC destination '{0}' only supports `create_if_missing` with the `Append` write mode"
+    )]
+    UnsupportedCreateIfMissing(String),
 
     // -- Modules
     #[cfg(feature = "odbc")]
+    #[allow(clippy::enum_variant_names)]
     #[error("OdbcError({0})")]
     OdbcError(#[from] aqueducts_odbc::error::Error),
+    #[cfg(feature = "postgres")]
+    #[allow(clippy::enum_variant_names)]
+    #[error("PostgresError({0})")]
+    PostgresError(#[from] aqueducts_postgres::error::Error),
 
     // -- External
     #[error("ArrowError({0})")]
@@ -1,12 +1,15 @@
 use aqueducts_utils::serde::deserialize_file_location;
-use datafusion::config::{ConfigField, CsvOptions, TableParquetOptions};
+use datafusion::common::parsers::CompressionTypeVariant;
+use datafusion::config::{ConfigField, CsvOptions, JsonOptions, TableParquetOptions};
 use datafusion::dataframe::DataFrameWriteOptions;
 use datafusion::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::info;
 use url::Url;
 
-use super::Result;
+use super::{error, Result};
+use crate::object_store_url;
 
 /// A file output destination
 #[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
@@ -30,9 +33,45 @@ pub struct FileDestination {
     #[serde(default)]
     pub partition_cols: Vec<String>,
 
+    /// When set to `true` the existing files of all partitions contained in the output are deleted before writing
+    /// Partitions that are not part of the output are left untouched, requires `partition_cols`, defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub overwrite_partitions: bool,
+
     /// Object store storage options
     #[serde(default)]
     pub storage_options: HashMap<String, String>,
+
+    /// Columns to sort the output data by before writing, as a list of `(column, ascending)` tuples
+    /// Sorting requires a full sort (shuffle) of the output data before the write and can be expensive for large outputs
+    #[new(default)]
+    #[serde(default)]
+    pub sort_by: Vec<(String, bool)>,
+
+    /// Column specific options for the `Parquet` file type, keyed by column name
+    /// Options that are not set fall back to the global parquet options of the file type, definitions setting them for other file types are rejected
+    #[new(default)]
+    #[serde(default)]
+    pub column_options: HashMap<String, ParquetColumnOptions>,
+
+    /// Name of a column that is added to the output and populated with the timestamp (UTC) of the write
+    /// Fails the write if the output already contains a column with this name
+    #[new(default)]
+    #[serde(default)]
+    pub ingestion_timestamp_column: Option<String>,
+
+    /// Maximum number of rows that may be written, the write is aborted when the output exceeds it
+    /// Checked by reading at most `max_output_rows + 1` rows before writing, defaults to unlimited
+    #[new(default)]
+    #[serde(default)]
+    pub max_output_rows: Option<usize>,
+
+    /// When set to `true` write permissions are checked when the destination is registered, before any stage is executed
+    /// The check writes and deletes an empty marker object (`.aqueducts_write_check_<timestamp>` next to the output location), defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub precheck_write: bool,
 }
 
 /// File type and options
@@ -46,11 +85,12 @@ pub enum FileType {
     /// CSV options
     Csv(CsvDestinationOptions),
 
-    /// Json destination, no supported options
-    Json,
+    /// Json options
+    Json(#[serde(default)] JsonDestinationOptions),
 }
 
 /// Csv options
+/// Lines are always terminated with `\n`, unlike CSV sources there is no `terminator` option as the CSV writer doesn't support a custom line terminator
 #[derive(Debug, Clone, Serialize, Deserialize, Default, derive_new::new)]
 #[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
 pub struct CsvDestinationOptions {
@@ -59,9 +99,117 @@ pub struct CsvDestinationOptions {
 
     /// Defaults to `,`, sets the delimiter char for the CSV file
     delimiter: Option<char>,
+
+    /// Defaults to `"`, sets the quote char used to enclose values
+    #[new(default)]
+    #[serde(default)]
+    quote: Option<char>,
+
+    /// Sets the escape char used for quotes within quoted values, defaults to doubling the quote char
+    #[new(default)]
+    #[serde(default)]
+    escape: Option<char>,
+
+    /// Sets the string written for null values e.g. `\N`, defaults to empty values
+    #[new(default)]
+    #[serde(default)]
+    null_value: Option<String>,
+}
+
+/// Json options
+#[derive(Debug, Clone, Serialize, Deserialize, Default, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct JsonDestinationOptions {
+    /// Compression to apply to the written NDJSON files, defaults to no compression
+    /// The file extension of the compression (e.g. `.gz`, `.zst`) is appended to the location when writing a single file that doesn't end with it
+    compression: Option<FileCompression>,
+}
+
+/// Compression codecs supported for file outputs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub enum FileCompression {
+    /// Gzip compression (`.gz`)
+    Gzip,
+    /// Bzip2 compression (`.bz2`)
+    Bzip2,
+    /// Xz compression (`.xz`)
+    Xz,
+    /// Zstd compression (`.zst`)
+    Zstd,
+}
+
+impl FileCompression {
+    fn extension(&self) -> &'static str {
+        match self {
+            FileCompression::Gzip => ".gz",
+            FileCompression::Bzip2 => ".bz2",
+            FileCompression::Xz => ".xz",
+            FileCompression::Zstd => ".zst",
+        }
+    }
+}
+
+impl From<FileCompression> for CompressionTypeVariant {
+    fn from(value: FileCompression) -> Self {
+        match value {
+            FileCompression::Gzip => CompressionTypeVariant::GZIP,
+            FileCompression::Bzip2 => CompressionTypeVariant::BZIP2,
+            FileCompression::Xz => CompressionTypeVariant::XZ,
+            FileCompression::Zstd => CompressionTypeVariant::ZSTD,
+        }
+    }
+}
+
+/// Parquet column options
+#[derive(Debug, Clone, Serialize, Deserialize, Default, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct ParquetColumnOptions {
+    /// Compression codec for this column e.g. `snappy`, `zstd(3)`, `uncompressed`
+    compression: Option<String>,
+
+    /// Encoding for this column e.g. `plain`, `delta_binary_packed`, `byte_stream_split`
+    encoding: Option<String>,
+
+    /// Enable or disable dictionary encoding for this column
+    dictionary_enabled: Option<bool>,
+}
+
+impl ParquetColumnOptions {
+    // translate into the column specific config keys of `TableParquetOptions`
+    fn config_entries(&self, column: &str) -> Vec<(String, String)> {
+        [
+            ("compression", self.compression.clone()),
+            ("encoding", self.encoding.clone()),
+            (
+                "dictionary_enabled",
+                self.dictionary_enabled.map(|enabled| enabled.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (format!("{key}::{column}"), value)))
+        .collect()
+    }
+}
+
+/// Validate the options of a file destination without accessing the location
+pub(super) fn validate(file_def: &FileDestination) -> Result<()> {
+    if !file_def.column_options.is_empty() && !matches!(file_def.file_type, FileType::Parquet(_)) {
+        return Err(error::Error::UnsupportedColumnOptions(
+            file_def.name.clone(),
+        ));
+    }
+
+    Ok(())
 }
 
 pub(super) async fn write(file_def: &FileDestination, data: DataFrame) -> Result<()> {
+    validate_partition_cols(file_def, &data)?;
+
+    if file_def.overwrite_partitions {
+        delete_partitions(file_def, &data).await?;
+    }
+
     let write_options = DataFrameWriteOptions::default()
         .with_partition_by(file_def.partition_cols.clone())
         .with_single_file_output(file_def.single_file);
@@ -74,6 +222,17 @@ pub(super) async fn write(file_def: &FileDestination, data: DataFrame) -> Result
                 .iter()
                 .try_for_each(|(k, v)| parquet_options.set(k.as_str(), v.as_str()))?;
 
+            for (column, column_options) in file_def.column_options.iter() {
+                if data.schema().field_with_unqualified_name(column).is_err() {
+                    return Err(error::Error::UnknownColumn(column.clone()));
+                }
+
+                column_options
+                    .config_entries(column)
+                    .iter()
+                    .try_for_each(|(k, v)| parquet_options.set(k.as_str(), v.as_str()))?;
+            }
+
             data.write_parquet(
                 file_def.location.as_str(),
                 write_options,
@@ -82,14 +241,35 @@ pub(super) async fn write(file_def: &FileDestination, data: DataFrame) -> Result
             .await?
         }
         FileType::Csv(csv_options) => {
-            let csv_options = CsvOptions::default()
-                .with_has_header(csv_options.has_header.unwrap_or(true))
-                .with_delimiter(csv_options.delimiter.unwrap_or(',') as u8);
+            let csv_options = CsvOptions {
+                escape: csv_options.escape.map(|e| e as u8),
+                null_value: csv_options.null_value.clone(),
+                ..CsvOptions::default()
+            }
+            .with_has_header(csv_options.has_header.unwrap_or(true))
+            .with_delimiter(csv_options.delimiter.unwrap_or(',') as u8)
+            .with_quote(csv_options.quote.unwrap_or('"') as u8);
 
             data.write_csv(file_def.location.as_str(), write_options, Some(csv_options))
                 .await?
         }
-        FileType::Json => {
+        FileType::Json(JsonDestinationOptions {
+            compression: Some(compression),
+        }) => {
+            let mut location = file_def.location.to_string();
+            if file_def.single_file && !location.ends_with(compression.extension()) {
+                location.push_str(compression.extension());
+            }
+
+            let json_options = JsonOptions {
+                compression: (*compression).into(),
+                ..Default::default()
+            };
+
+            data.write_json(location.as_str(), write_options, Some(json_options))
+                .await?
+        }
+        FileType::Json(JsonDestinationOptions { compression: None }) => {
             data.write_json(file_def.location.as_str(), write_options, None)
                 .await?
         }
@@ -98,6 +278,102 @@ pub(super) async fn write(file_def: &FileDestination, data: DataFrame) -> Result
     Ok(())
 }
 
+// partition columns must exist in the output and would be ignored for single file outputs
+fn validate_partition_cols(file_def: &FileDestination, data: &DataFrame) -> Result<()> {
+    if file_def.partition_cols.is_empty() {
+        if file_def.overwrite_partitions {
+            return Err(error::Error::MissingOverwritePartitionCols);
+        }
+
+        return Ok(());
+    }
+
+    if file_def.single_file {
+        return Err(error::Error::PartitionedSingleFile);
+    }
+
+    let schema = data.schema();
+    let missing = file_def
+        .partition_cols
+        .iter()
+        .find(|column| schema.field_with_unqualified_name(column).is_err());
+
+    if let Some(column) = missing {
+        let available = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        return Err(error::Error::MissingPartitionColumn(
+            column.clone(),
+            available,
+        ));
+    }
+
+    Ok(())
+}
+
+// delete the existing files of the partitions contained in the data so the write replaces them
+// partition paths are built the same way as by the datafusion writer, the object store path percent encodes the values
+async fn delete_partitions(file_def: &FileDestination, data: &DataFrame) -> Result<()> {
+    use datafusion::arrow::{array::AsArray, compute::cast, datatypes::DataType};
+    use datafusion::error::DataFusionError;
+    use deltalake::{ObjectStore, Path};
+    use futures::{StreamExt, TryStreamExt};
+
+    let store = data
+        .task_ctx()
+        .runtime_env()
+        .object_store(object_store_url(&file_def.location)?)?;
+    let base = Path::from_url_path(file_def.location.path())
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+    let columns = file_def
+        .partition_cols
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let partitions = data
+        .clone()
+        .select_columns(&columns)?
+        .distinct()?
+        .collect()
+        .await?;
+
+    for batch in partitions {
+        let values = batch
+            .columns()
+            .iter()
+            .map(|column| cast(column, &DataType::Utf8))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        for row in 0..batch.num_rows() {
+            let prefix = file_def.partition_cols.iter().zip(values.iter()).fold(
+                base.clone(),
+                |prefix, (column, values)| {
+                    prefix.child(format!("{column}={}", values.as_string::<i32>().value(row)))
+                },
+            );
+
+            info!("Deleting existing files of partition '{prefix}'");
+
+            let locations = store
+                .list(Some(&prefix))
+                .map_ok(|meta| meta.location)
+                .boxed();
+            store
+                .delete_stream(locations)
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(DataFusionError::from)?;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use datafusion::arrow::array::RecordBatch;
@@ -181,6 +457,234 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_write_csv_null_value_ok() {
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("csv/write_null_value.csv");
+        let mut options = CsvDestinationOptions::new(Some(true), Some(';'));
+        options.quote = Some('|');
+        options.null_value = Some("\\N".into());
+        let definition = FileDestination::new(
+            "write".into(),
+            path.clone(),
+            FileType::Csv(options),
+            true,
+            vec![],
+            Default::default(),
+        );
+
+        let col_1 = Arc::new(StringArray::from(vec![Some("a;b"), None])) as ArrayRef;
+        let col_2 = Arc::new(Int32Array::from(vec![None, Some(2)])) as ArrayRef;
+
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1), ("col_2", col_2)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+        write(&definition, df).await.unwrap();
+
+        let written = std::fs::read_to_string(path.to_file_path().unwrap()).unwrap();
+        assert_eq!("col_1;col_2\n|a;b|;\\N\n\\N;2\n", written);
+    }
+
+    #[tokio::test]
+    async fn test_write_parquet_overwrite_partitions_ok() {
+        let ctx = SessionContext::new();
+
+        let run_id = rand::random::<usize>();
+        let path =
+            generate_test_file_path(format!("parquet/overwrite_partitions_{run_id}/").as_str());
+        let mut definition = FileDestination::new(
+            "write".into(),
+            path.clone(),
+            FileType::Parquet(HashMap::default()),
+            false,
+            vec!["col_1".into()],
+            Default::default(),
+        );
+        definition.overwrite_partitions = true;
+
+        let write_batch = |col_1: Vec<&str>, col_2: Vec<i32>| {
+            let col_1 = Arc::new(StringArray::from(col_1)) as ArrayRef;
+            let col_2 = Arc::new(Int32Array::from(col_2)) as ArrayRef;
+
+            RecordBatch::try_from_iter(vec![("col_1", col_1), ("col_2", col_2)]).unwrap()
+        };
+
+        let df = ctx
+            .read_batch(write_batch(vec!["a", "b"], vec![1, 2]))
+            .unwrap();
+        write(&definition, df).await.unwrap();
+        let df = ctx
+            .read_batch(write_batch(vec!["b", "b"], vec![3, 4]))
+            .unwrap();
+        write(&definition, df).await.unwrap();
+
+        let mut partitions = vec![];
+        for partition in ["col_1=a/", "col_1=b/"] {
+            let location = path.join(partition).unwrap();
+            let batches = ctx
+                .read_parquet(location.as_str(), ParquetReadOptions::default())
+                .await
+                .unwrap()
+                .sort(vec![col("col_2").sort(true, false)])
+                .unwrap()
+                .collect()
+                .await
+                .unwrap();
+
+            partitions.push(batches);
+        }
+
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| col_2 |",
+                "+-------+",
+                "| 1     |",
+                "+-------+",
+            ],
+            partitions[0].as_slice()
+        );
+        assert_batches_eq!(
+            [
+                "+-------+",
+                "| col_2 |",
+                "+-------+",
+                "| 3     |",
+                "| 4     |",
+                "+-------+",
+            ],
+            partitions[1].as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_overwrite_partitions_without_partition_cols_err() {
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("parquet/overwrite_partitions_unpartitioned/");
+        let mut definition = FileDestination::new(
+            "write".into(),
+            path,
+            FileType::Parquet(HashMap::default()),
+            false,
+            vec![],
+            Default::default(),
+        );
+        definition.overwrite_partitions = true;
+
+        let df = ctx.sql("SELECT 1 AS col_1").await.unwrap();
+        let result = write(&definition, df).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::MissingOverwritePartitionCols)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_write_missing_partition_column_err() {
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("parquet/write_missing_partition/");
+        let definition = FileDestination::new(
+            "write".into(),
+            path,
+            FileType::Parquet(HashMap::default()),
+            false,
+            vec!["col_3".into()],
+            Default::default(),
+        );
+
+        let col_1 = Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef;
+        let col_2 = Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef;
+
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1), ("col_2", col_2)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+        let result = write(&definition, df).await;
+
+        match result {
+            Err(error::Error::MissingPartitionColumn(column, available)) => {
+                assert_eq!("col_3", column);
+                assert_eq!("col_1, col_2", available);
+            }
+            other => panic!("expected MissingPartitionColumn, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_partitioned_single_file_err() {
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("parquet/write_partitioned_single.parquet");
+        let definition = FileDestination::new(
+            "write".into(),
+            path,
+            FileType::Parquet(HashMap::default()),
+            true,
+            vec!["col_1".into()],
+            Default::default(),
+        );
+
+        let col_1 = Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef;
+
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+        let result = write(&definition, df).await;
+
+        assert!(matches!(result, Err(error::Error::PartitionedSingleFile)));
+    }
+
+    #[tokio::test]
+    async fn test_write_json_gzip_ok() {
+        use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("json/write.json");
+        let definition = FileDestination::new(
+            "write".into(),
+            path.clone(),
+            FileType::Json(JsonDestinationOptions::new(Some(FileCompression::Gzip))),
+            true,
+            vec![],
+            Default::default(),
+        );
+
+        let col_1 = Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef;
+        let col_2 = Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef;
+
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1), ("col_2", col_2)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+        write(&definition, df).await.unwrap();
+
+        let batches = ctx
+            .read_json(
+                format!("{path}.gz").as_str(),
+                NdJsonReadOptions::default()
+                    .file_extension(".json.gz")
+                    .file_compression_type(FileCompressionType::GZIP),
+            )
+            .await
+            .unwrap()
+            .sort(vec![col("col_1").sort(true, false)])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+-------+-------+",
+                "| col_1 | col_2 |",
+                "+-------+-------+",
+                "| a     | 1     |",
+                "| b     | 2     |",
+                "+-------+-------+",
+            ],
+            batches.as_slice()
+        );
+    }
+
     #[tokio::test]
     async fn test_write_parquet_ok() {
         let ctx = SessionContext::new();
@@ -237,6 +741,152 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_write_parquet_sorted_ok() {
+        let ctx = Arc::new(SessionContext::new());
+
+        let path = generate_test_file_path("parquet/write_sorted.parquet");
+        let mut definition = FileDestination::new(
+            "write".into(),
+            path.clone(),
+            FileType::Parquet(HashMap::default()),
+            true,
+            vec![],
+            Default::default(),
+        );
+        definition.sort_by = vec![("col_2".into(), false)];
+
+        // Insert records into table
+        let col_1 = Arc::new(StringArray::from(vec!["c", "a", "d", "b"])) as ArrayRef;
+        let col_2 = Arc::new(Int32Array::from(vec![3, 1, 4, 2])) as ArrayRef;
+
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1), ("col_2", col_2)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+        crate::destinations::write_to_destination(
+            ctx.clone(),
+            &crate::destinations::Destination::File(definition),
+            df,
+        )
+        .await
+        .unwrap();
+
+        let batches = ctx
+            .read_parquet(path.as_str(), ParquetReadOptions::default())
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            [
+                "+-------+-------+",
+                "| col_1 | col_2 |",
+                "+-------+-------+",
+                "| d     | 4     |",
+                "| c     | 3     |",
+                "| b     | 2     |",
+                "| a     | 1     |",
+                "+-------+-------+",
+            ],
+            batches.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_parquet_column_options_ok() {
+        use datafusion::parquet::basic::{Compression, Encoding};
+        use datafusion::parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("parquet/write_column_options.parquet");
+        let mut definition = FileDestination::new(
+            "write".into(),
+            path.clone(),
+            FileType::Parquet(HashMap::from_iter(vec![(
+                "compression".into(),
+                "snappy".into(),
+            )])),
+            true,
+            vec![],
+            Default::default(),
+        );
+        definition.column_options = HashMap::from_iter(vec![(
+            "col_1".into(),
+            ParquetColumnOptions::new(Some("zstd(3)".into()), None, Some(false)),
+        )]);
+
+        let col_1 = Arc::new(StringArray::from(vec!["a", "a", "b", "b"])) as ArrayRef;
+        let col_2 = Arc::new(StringArray::from(vec!["a", "a", "b", "b"])) as ArrayRef;
+
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1), ("col_2", col_2)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+        write(&definition, df).await.unwrap();
+
+        let file = std::fs::File::open(path.to_file_path().unwrap()).unwrap();
+        let reader = SerializedFileReader::new(file).unwrap();
+        let row_group = reader.metadata().row_group(0);
+
+        let col_1 = row_group.column(0);
+        assert!(matches!(col_1.compression(), Compression::ZSTD(_)));
+        assert!(!col_1.encodings().contains(&Encoding::RLE_DICTIONARY));
+
+        let col_2 = row_group.column(1);
+        assert!(matches!(col_2.compression(), Compression::SNAPPY));
+        assert!(col_2.encodings().contains(&Encoding::RLE_DICTIONARY));
+    }
+
+    #[tokio::test]
+    async fn test_write_parquet_column_options_unknown_column_err() {
+        let ctx = SessionContext::new();
+
+        let path = generate_test_file_path("parquet/write_column_options_err.parquet");
+        let mut definition = FileDestination::new(
+            "write".into(),
+            path.clone(),
+            FileType::Parquet(HashMap::default()),
+            true,
+            vec![],
+            Default::default(),
+        );
+        definition.column_options = HashMap::from_iter(vec![(
+            "unknown".into(),
+            ParquetColumnOptions::new(Some("zstd(3)".into()), None, None),
+        )]);
+
+        let col_1 = Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef;
+        let batch = RecordBatch::try_from_iter(vec![("col_1", col_1)]).unwrap();
+        let df = ctx.read_batch(batch).unwrap();
+
+        let result = write(&definition, df).await;
+
+        assert!(matches!(result, Err(error::Error::UnknownColumn(_))));
+    }
+
+    #[test]
+    fn test_validate_column_options_csv_err() {
+        let mut definition = FileDestination::new(
+            "write".into(),
+            generate_test_file_path("csv/write_column_options_err.csv"),
+            FileType::Csv(CsvDestinationOptions::default()),
+            true,
+            vec![],
+            Default::default(),
+        );
+        definition.column_options = HashMap::from_iter(vec![(
+            "col_1".into(),
+            ParquetColumnOptions::new(Some("zstd(3)".into()), None, None),
+        )]);
+
+        let result = validate(&definition);
+
+        assert!(matches!(
+            result,
+            Err(error::Error::UnsupportedColumnOptions(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_write_parquet_partitioned_ok() {
         // Setup
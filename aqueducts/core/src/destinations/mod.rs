@@ -1,13 +1,29 @@
 use aqueducts_utils::store::register_object_store;
-use datafusion::{dataframe::DataFrame, datasource::MemTable, execution::context::SessionContext};
+use chrono::Utc;
+use datafusion::{
+    common::ScalarValue,
+    dataframe::DataFrame,
+    datasource::MemTable,
+    execution::context::SessionContext,
+    prelude::{col, lit},
+};
+use deltalake::{ObjectStore, Path};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{info, instrument};
+use url::Url;
+
+use crate::object_store_url;
 
 pub mod delta;
 pub mod file;
 #[cfg(feature = "odbc")]
 pub mod odbc;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod stdout;
+#[cfg(feature = "http")]
+pub mod webhook;
 
 pub(crate) mod error;
 pub(crate) type Result<T> = core::result::Result<T, error::Error>;
@@ -16,6 +32,8 @@ pub(crate) type Result<T> = core::result::Result<T, error::Error>;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
+// destinations are only created once per pipeline, boxing the delta destination isn't worth the less ergonomic api
+#[allow(clippy::large_enum_variant)]
 pub enum Destination {
     /// An in-memory destination
     InMemory(InMemoryDestination),
@@ -26,6 +44,14 @@ pub enum Destination {
     #[cfg(feature = "odbc")]
     /// An ODBC insert query to write to a DB table
     Odbc(odbc::OdbcDestination),
+    #[cfg(feature = "postgres")]
+    /// A Postgres table written to using `COPY`
+    Postgres(postgres::PostgresDestination),
+    /// Prints the output to stdout
+    Stdout(stdout::StdoutDestination),
+    #[cfg(feature = "http")]
+    /// POSTs the output rows as JSON to an HTTP endpoint
+    Webhook(webhook::WebhookDestination),
 }
 
 /// An in-memory table destination
@@ -34,6 +60,154 @@ pub enum Destination {
 pub struct InMemoryDestination {
     /// Name to register the table with in the provided `SessionContext`
     pub name: String,
+
+    /// When set to `true` an existing table with the same name is replaced instead of failing the write, defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub overwrite: bool,
+
+    /// When set to `true` the table stays registered after the run so that later pipelines using the same `SessionContext` can read it with an `InMemory` source
+    /// The table is never deregistered by the pipeline, even if it replaced a stage with the same name, defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub persist: bool,
+
+    /// Name of a column that is added to the output and populated with the timestamp (UTC) of the write
+    /// Fails the write if the output already contains a column with this name
+    #[new(default)]
+    #[serde(default)]
+    pub ingestion_timestamp_column: Option<String>,
+
+    /// Maximum number of rows that may be written, the write is aborted when the output exceeds it
+    /// Checked by reading at most `max_output_rows + 1` rows before writing, defaults to unlimited
+    #[new(default)]
+    #[serde(default)]
+    pub max_output_rows: Option<usize>,
+}
+
+impl Destination {
+    /// Name of this destination, used to identify it in logs
+    pub fn name(&self) -> &str {
+        match self {
+            Destination::InMemory(mem_def) => mem_def.name.as_str(),
+            Destination::Delta(table_def) => table_def.name.as_str(),
+            Destination::File(file_def) => file_def.name.as_str(),
+            #[cfg(feature = "odbc")]
+            Destination::Odbc(odbc_def) => odbc_def.name.as_str(),
+            #[cfg(feature = "postgres")]
+            Destination::Postgres(postgres_def) => postgres_def.name.as_str(),
+            Destination::Stdout(_) => "stdout",
+            #[cfg(feature = "http")]
+            Destination::Webhook(webhook_def) => webhook_def.name.as_str(),
+        }
+    }
+
+    /// Name of the ingestion timestamp column added to the output of this destination
+    pub fn ingestion_timestamp_column(&self) -> Option<&str> {
+        match self {
+            Destination::InMemory(mem_def) => mem_def.ingestion_timestamp_column.as_deref(),
+            Destination::Delta(table_def) => table_def.ingestion_timestamp_column.as_deref(),
+            Destination::File(file_def) => file_def.ingestion_timestamp_column.as_deref(),
+            #[cfg(feature = "odbc")]
+            Destination::Odbc(odbc_def) => odbc_def.ingestion_timestamp_column.as_deref(),
+            #[cfg(feature = "postgres")]
+            Destination::Postgres(postgres_def) => {
+                postgres_def.ingestion_timestamp_column.as_deref()
+            }
+            Destination::Stdout(_) => None,
+            #[cfg(feature = "http")]
+            Destination::Webhook(webhook_def) => webhook_def.ingestion_timestamp_column.as_deref(),
+        }
+    }
+
+    /// Maximum number of rows that may be written to this destination
+    pub fn max_output_rows(&self) -> Option<usize> {
+        match self {
+            Destination::InMemory(mem_def) => mem_def.max_output_rows,
+            Destination::Delta(table_def) => table_def.max_output_rows,
+            Destination::File(file_def) => file_def.max_output_rows,
+            #[cfg(feature = "odbc")]
+            Destination::Odbc(odbc_def) => odbc_def.max_output_rows,
+            #[cfg(feature = "postgres")]
+            Destination::Postgres(postgres_def) => postgres_def.max_output_rows,
+            Destination::Stdout(_) => None,
+            #[cfg(feature = "http")]
+            Destination::Webhook(webhook_def) => webhook_def.max_output_rows,
+        }
+    }
+}
+
+/// Validate the options of a destination without accessing it
+/// Fails for options that are invalid regardless of the data or the state of the destination
+pub fn validate_destination(destination: &Destination) -> Result<()> {
+    match destination {
+        Destination::File(file_def) => file::validate(file_def),
+        Destination::Delta(table_def) => delta::validate(table_def),
+        #[cfg(feature = "odbc")]
+        Destination::Odbc(odbc_def) => odbc::validate(odbc_def),
+        _ => Ok(()),
+    }
+}
+
+/// Checks a `Destination` without modifying it
+/// Unlike `register_destination` no table is created and no write check is performed, used to validate a pipeline
+#[instrument(skip(ctx, destination), err)]
+pub async fn check_destination(ctx: Arc<SessionContext>, destination: &Destination) -> Result<()> {
+    match destination {
+        Destination::InMemory(_) | Destination::Stdout(_) => Ok(()),
+        #[cfg(feature = "http")]
+        Destination::Webhook(_) => Ok(()),
+        Destination::Delta(table_def) => {
+            ensure_writable(&table_def.location)?;
+
+            if !delta::exists(table_def).await? {
+                info!(
+                    "Delta table '{}' doesn't exist yet and will be created at location '{}'",
+                    table_def.name, table_def.location
+                );
+            }
+
+            Ok(())
+        }
+        Destination::File(file_def) => {
+            ensure_writable(&file_def.location)?;
+            register_object_store(ctx, &file_def.location, &file_def.storage_options)?;
+
+            Ok(())
+        }
+        #[cfg(feature = "odbc")]
+        Destination::Odbc(odbc_def) => {
+            let connection_string = odbc_def.connection_string.as_str();
+            if odbc_def.create_if_missing {
+                if !aqueducts_odbc::odbc_table_exists(connection_string, odbc_def.name.as_str())
+                    .await?
+                {
+                    info!(
+                        "ODBC destination table '{}' doesn't exist yet and will be created on write",
+                        odbc_def.name
+                    );
+                }
+            } else {
+                aqueducts_odbc::register_odbc_destination(
+                    connection_string,
+                    odbc_def.name.as_str(),
+                )
+                .await?;
+            }
+
+            Ok(())
+        }
+        #[cfg(feature = "postgres")]
+        Destination::Postgres(postgres_def) => {
+            aqueducts_postgres::register_postgres_destination(
+                postgres_def.connection_string.as_str(),
+                postgres_def.name.as_str(),
+            )
+            .await?;
+
+            Ok(())
+        }
+    }
 }
 
 /// Creates a `Destination`
@@ -43,39 +217,113 @@ pub async fn register_destination(
     destination: &Destination,
 ) -> Result<()> {
     match destination {
-        Destination::InMemory(_) => Ok(()),
+        Destination::InMemory(_) | Destination::Stdout(_) => Ok(()),
+        #[cfg(feature = "http")]
+        Destination::Webhook(_) => Ok(()),
         Destination::Delta(table_def) => {
+            ensure_writable(&table_def.location)?;
+
             info!(
                 "Creating delta table  (if it doesn't exist yet) '{}' at location '{}'",
                 table_def.name, table_def.location
             );
 
-            let _ = delta::create(table_def).await?;
+            let table = delta::create(table_def).await?;
+
+            if table_def.precheck_write {
+                let marker = Path::from(write_check_marker("_aqueducts_write_check").as_str());
+                write_check(table.object_store(), marker, &table_def.location).await?;
+            }
+
             Ok(())
         }
         Destination::File(file_def) => {
-            register_object_store(ctx, &file_def.location, &file_def.storage_options)?;
+            ensure_writable(&file_def.location)?;
+            register_object_store(ctx.clone(), &file_def.location, &file_def.storage_options)?;
+
+            if file_def.precheck_write {
+                let store = ctx
+                    .runtime_env()
+                    .object_store(object_store_url(&file_def.location)?)?;
+                let marker = file_write_check_marker(file_def)?;
+                write_check(store, marker, &file_def.location).await?;
+            }
+
             Ok(())
         }
         #[cfg(feature = "odbc")]
         Destination::Odbc(odbc_def) => {
-            aqueducts_odbc::register_odbc_destination(
-                odbc_def.connection_string.as_str(),
-                odbc_def.name.as_str(),
+            let connection_string = odbc_def.connection_string.as_str();
+            let exists = if odbc_def.create_if_missing {
+                aqueducts_odbc::odbc_table_exists(connection_string, odbc_def.name.as_str()).await?
+            } else {
+                aqueducts_odbc::register_odbc_destination(
+                    connection_string,
+                    odbc_def.name.as_str(),
+                )
+                .await?;
+
+                true
+            };
+
+            if !exists {
+                info!(
+                    "ODBC destination table '{}' doesn't exist yet and will be created on write",
+                    odbc_def.name
+                );
+            }
+
+            // a table that is created on write can't be probed, the write fails if it can't be created
+            if odbc_def.precheck_write && exists {
+                aqueducts_odbc::odbc_write_check(connection_string, odbc_def.name.as_str())
+                    .await
+                    .map_err(|e| {
+                        error::Error::WriteCheckFailed(odbc_def.name.clone(), e.to_string())
+                    })?;
+            }
+
+            Ok(())
+        }
+        #[cfg(feature = "postgres")]
+        Destination::Postgres(postgres_def) => {
+            aqueducts_postgres::register_postgres_destination(
+                postgres_def.connection_string.as_str(),
+                postgres_def.name.as_str(),
             )
             .await?;
+
             Ok(())
         }
     }
 }
 
 /// Write a `DataFrame` to an Aqueduct `Destination`
+/// Returns the version of the delta table after the write for `Delta` destinations
 #[instrument(skip(ctx, destination, data), err)]
 pub async fn write_to_destination(
     ctx: Arc<SessionContext>,
     destination: &Destination,
     data: DataFrame,
-) -> Result<()> {
+) -> Result<Option<i64>> {
+    let data = match destination {
+        Destination::Delta(delta::DeltaDestination { sort_by, .. })
+        | Destination::File(file::FileDestination { sort_by, .. })
+            if !sort_by.is_empty() =>
+        {
+            sort(data, sort_by)?
+        }
+        _ => data,
+    };
+
+    if let Some(max_output_rows) = destination.max_output_rows() {
+        check_max_output_rows(&data, max_output_rows).await?;
+    }
+
+    let data = match destination.ingestion_timestamp_column() {
+        Some(column) => with_ingestion_timestamp(data, column)?,
+        None => data,
+    };
+
     match destination {
         Destination::InMemory(mem_def) => {
             info!("Writing data to in-memory table '{}'", mem_def.name);
@@ -84,30 +332,188 @@ pub async fn write_to_destination(
             let partitioned = data.collect_partitioned().await?;
             let table = MemTable::try_new(Arc::new(schema.as_arrow().clone()), partitioned)?;
 
+            if mem_def.overwrite && ctx.deregister_table(mem_def.name.as_str())?.is_some() {
+                info!("Replaced existing in-memory table '{}'", mem_def.name);
+            }
+
             ctx.register_table(mem_def.name.as_str(), Arc::new(table))?;
 
-            Ok(())
+            Ok(None)
         }
         Destination::Delta(table_def) => {
             info!(
                 "Writing data to delta table '{}' at location '{}'",
                 table_def.name, table_def.location
             );
-            let _ = delta::write(table_def, data).await?;
+            let table = delta::write(table_def, data).await?;
 
-            Ok(())
+            Ok(Some(table.version()))
         }
         Destination::File(file_def) => {
             info!("Writing data to file at location '{}'", file_def.location);
             file::write(file_def, data).await?;
 
-            Ok(())
+            Ok(None)
         }
         #[cfg(feature = "odbc")]
         Destination::Odbc(odbc_def) => {
             odbc::write(odbc_def, data).await?;
 
-            Ok(())
+            Ok(None)
+        }
+        #[cfg(feature = "postgres")]
+        Destination::Postgres(postgres_def) => {
+            info!("Writing data to Postgres table '{}'", postgres_def.name);
+            postgres::write(postgres_def, data).await?;
+
+            Ok(None)
         }
+        Destination::Stdout(stdout_def) => {
+            stdout::write(stdout_def, data).await?;
+
+            Ok(None)
+        }
+        #[cfg(feature = "http")]
+        Destination::Webhook(webhook_def) => {
+            info!("Sending data to webhook destination '{}'", webhook_def.name);
+            webhook::write(webhook_def, data).await?;
+
+            Ok(None)
+        }
+    }
+}
+
+// unique name of a marker object written to check write permissions
+fn write_check_marker(prefix: &str) -> String {
+    format!("{prefix}_{}", Utc::now().timestamp_micros())
+}
+
+// marker is placed next to the output file for single file outputs and inside the output directory otherwise
+fn file_write_check_marker(file_def: &file::FileDestination) -> Result<Path> {
+    let location = file_def.location.path();
+    let invalid = |message: String| {
+        error::Error::WriteCheckFailed(
+            file_def.location.to_string(),
+            format!("can't determine the directory of the destination: {message}"),
+        )
+    };
+
+    let path = Path::from_url_path(location).map_err(|e| invalid(e.to_string()))?;
+    let mut parts = path.parts().collect::<Vec<_>>();
+
+    if file_def.single_file && (location.ends_with('/') || parts.pop().is_none()) {
+        return Err(invalid("the location has no file name".into()));
+    }
+
+    let marker = write_check_marker(".aqueducts_write_check");
+
+    Ok(Path::from_iter(
+        parts.into_iter().chain([marker.as_str().into()]),
+    ))
+}
+
+// write and delete an empty marker object to check if the destination is writable
+async fn write_check(store: Arc<dyn ObjectStore>, marker: Path, location: &Url) -> Result<()> {
+    info!("Checking write permissions for destination '{location}'");
+
+    let result = match store.put(&marker, Vec::<u8>::new().into()).await {
+        Ok(_) => store.delete(&marker).await,
+        Err(err) => Err(err),
+    };
+
+    result.map_err(|e| error::Error::WriteCheckFailed(location.to_string(), e.to_string()))
+}
+
+// read at most `max_output_rows + 1` rows to check if the output exceeds the limit without a full scan
+async fn check_max_output_rows(data: &DataFrame, max_output_rows: usize) -> Result<()> {
+    let rows = data
+        .clone()
+        .limit(0, Some(max_output_rows.saturating_add(1)))?
+        .count()
+        .await?;
+
+    if rows > max_output_rows {
+        return Err(error::Error::MaxOutputRowsExceeded(max_output_rows));
+    }
+
+    Ok(())
+}
+
+// `http`/`https` locations are only supported for reading
+fn ensure_writable(location: &Url) -> Result<()> {
+    match location.scheme() {
+        "http" | "https" => Err(error::Error::ReadOnlyLocation(location.to_string())),
+        _ => Ok(()),
+    }
+}
+
+fn with_ingestion_timestamp(data: DataFrame, column: &str) -> Result<DataFrame> {
+    if data.schema().field_with_unqualified_name(column).is_ok() {
+        return Err(error::Error::IngestionTimestampColumnExists(
+            column.to_string(),
+        ));
+    }
+
+    let timestamp =
+        ScalarValue::TimestampMicrosecond(Some(Utc::now().timestamp_micros()), Some("UTC".into()));
+
+    Ok(data.with_column(column, lit(timestamp))?)
+}
+
+// sort the data by the provided columns, validating that every column is present in the schema
+fn sort(data: DataFrame, sort_by: &[(String, bool)]) -> Result<DataFrame> {
+    let sort_exprs = sort_by
+        .iter()
+        .map(|(column, ascending)| {
+            if data.schema().field_with_unqualified_name(column).is_err() {
+                return Err(error::Error::MissingSortColumn(column.clone()));
+            }
+
+            Ok(col(column.as_str()).sort(*ascending, !ascending))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(data.sort(sort_exprs)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn file_destination(location: &str, single_file: bool) -> file::FileDestination {
+        file::FileDestination::new(
+            "output".into(),
+            Url::parse(location).unwrap(),
+            file::FileType::Csv(file::CsvDestinationOptions::default()),
+            single_file,
+            vec![],
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_file_write_check_marker_ok() {
+        let single_file = file_destination("s3://bucket/data/output.csv", true);
+        let directory = file_destination("s3://bucket/data/output/", false);
+
+        let single_file = file_write_check_marker(&single_file).unwrap();
+        let directory = file_write_check_marker(&directory).unwrap();
+
+        assert!(single_file
+            .as_ref()
+            .starts_with("data/.aqueducts_write_check_"));
+        assert!(directory
+            .as_ref()
+            .starts_with("data/output/.aqueducts_write_check_"));
+    }
+
+    #[test]
+    fn test_file_write_check_marker_without_file_name_err() {
+        let destination = file_destination("s3://bucket/data/output/", true);
+
+        let result = file_write_check_marker(&destination);
+
+        assert!(matches!(result, Err(error::Error::WriteCheckFailed(_, _))));
     }
 }
@@ -1,4 +1,4 @@
-use super::Result;
+use super::{error, Result};
 use datafusion::dataframe::DataFrame;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -19,6 +19,50 @@ pub struct OdbcDestination {
 
     /// batch size (rows) to use when inserting data
     pub batch_size: usize,
+
+    /// When set to `true` the destination table is created in case it doesn't exist yet
+    /// Defaults to `false` which requires the table to exist before the pipeline is executed
+    /// Only supported for the `Append` write mode, definitions combining it with another write mode are rejected
+    #[new(default)]
+    #[serde(default)]
+    pub create_if_missing: bool,
+
+    /// `CREATE TABLE` statement used to create the destination table when `create_if_missing` is set
+    /// When omitted the statement is derived from the schema of the data that is written
+    #[new(default)]
+    #[serde(default)]
+    pub create_statement: Option<String>,
+
+    /// Name of a column that is added to the output and populated with the timestamp (UTC) of the write
+    /// Fails the write if the output already contains a column with this name
+    #[new(default)]
+    #[serde(default)]
+    pub ingestion_timestamp_column: Option<String>,
+
+    /// Maximum number of rows that may be written, the write is aborted when the output exceeds it
+    /// Checked by reading at most `max_output_rows + 1` rows before writing, defaults to unlimited
+    #[new(default)]
+    #[serde(default)]
+    pub max_output_rows: Option<usize>,
+
+    /// When set to `true` insert permissions are checked when the destination is registered, before any stage is executed
+    /// The check inserts zero rows into the table in a transaction that is rolled back, defaults to `false`
+    /// Skipped for tables that don't exist yet and are created on write (`create_if_missing`)
+    #[new(default)]
+    #[serde(default)]
+    pub precheck_write: bool,
+
+    /// SQL statements executed in order before the data is written e.g. `TRUNCATE my_table`
+    /// Executed within the same transaction as the write, which is rolled back when any statement fails
+    #[new(default)]
+    #[serde(default)]
+    pub pre_write_sql: Vec<String>,
+
+    /// SQL statements executed in order after the data is written e.g. `ANALYZE my_table`
+    /// Executed within the same transaction as the write, which is rolled back when any statement fails
+    #[new(default)]
+    #[serde(default)]
+    pub post_write_sql: Vec<String>,
 }
 
 /// Write modes for the `Destination` output.
@@ -31,6 +75,9 @@ pub enum WriteMode {
 
     /// `Custom`: Inserts data with a prepared stament. Option to perform any number of (non-insert) preliminary statements
     Custom(CustomStatements),
+
+    /// `Delete`: Deletes the rows of the `Destination` matching the key tuples present in the data, within a single transaction
+    Delete(DeleteOptions),
 }
 
 /// SQL statements for `Custom` write mode.
@@ -44,11 +91,51 @@ pub struct CustomStatements {
     insert: String,
 }
 
+/// Options for `Delete` write mode.
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct DeleteOptions {
+    /// Columns of the data that identify the rows to delete, rows are deleted when all columns are equal
+    predicate_columns: Vec<String>,
+}
+
+/// Validate the options of an ODBC destination without connecting to it
+pub(super) fn validate(odbc_def: &OdbcDestination) -> Result<()> {
+    if odbc_def.create_if_missing && !matches!(odbc_def.write_mode, WriteMode::Append) {
+        return Err(error::Error::UnsupportedCreateIfMissing(
+            odbc_def.name.clone(),
+        ));
+    }
+
+    Ok(())
+}
+
 pub(super) async fn write(odbc_def: &OdbcDestination, data: DataFrame) -> Result<()> {
     let schema = data.schema().as_arrow().clone();
     let batches = data.collect().await?;
+    let hooks = aqueducts_odbc::WriteHooks {
+        pre_write: odbc_def.pre_write_sql.as_slice(),
+        post_write: odbc_def.post_write_sql.as_slice(),
+    };
 
     match &odbc_def.write_mode {
+        WriteMode::Append if odbc_def.create_if_missing => {
+            let create_statement = match odbc_def.create_statement.clone() {
+                Some(statement) => statement,
+                None => aqueducts_odbc::create_table_statement(odbc_def.name.as_str(), &schema)?,
+            };
+
+            aqueducts_odbc::create_and_write_arrow_batches(
+                odbc_def.connection_string.as_str(),
+                odbc_def.name.as_str(),
+                create_statement.as_str(),
+                batches,
+                Arc::new(schema),
+                odbc_def.batch_size,
+                hooks,
+            )
+            .await?
+        }
         WriteMode::Append => {
             aqueducts_odbc::write_arrow_batches(
                 odbc_def.connection_string.as_str(),
@@ -56,6 +143,7 @@ pub(super) async fn write(odbc_def: &OdbcDestination, data: DataFrame) -> Result
                 batches,
                 Arc::new(schema),
                 odbc_def.batch_size,
+                hooks,
             )
             .await?
         }
@@ -67,6 +155,19 @@ pub(super) async fn write(odbc_def: &OdbcDestination, data: DataFrame) -> Result
                 batches,
                 Arc::new(schema),
                 odbc_def.batch_size,
+                hooks,
+            )
+            .await?
+        }
+        WriteMode::Delete(delete_options) => {
+            aqueducts_odbc::delete(
+                odbc_def.connection_string.as_str(),
+                odbc_def.name.as_str(),
+                delete_options.predicate_columns.as_slice(),
+                batches,
+                Arc::new(schema),
+                odbc_def.batch_size,
+                hooks,
             )
             .await?
         }
@@ -0,0 +1,106 @@
+use datafusion::{
+    arrow::{array::RecordBatch, json::ArrayWriter},
+    dataframe::DataFrame,
+};
+use serde::{Deserialize, Serialize};
+
+use super::Result;
+use crate::stages::{format_batches, OutputFormat};
+
+/// A destination printing the output to stdout, intended for inspecting results of interactive runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct StdoutDestination {
+    /// Format the output is printed in, defaults to `Table`
+    #[new(default)]
+    #[serde(default)]
+    pub format: StdoutFormat,
+
+    /// Maximum number of rows that are printed, defaults to all rows
+    #[new(default)]
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Format of the output printed by a `StdoutDestination`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub enum StdoutFormat {
+    /// Pretty printed ASCII table
+    #[default]
+    Table,
+    /// CSV including a header row
+    Csv,
+    /// A single JSON array of objects
+    Json,
+    /// Newline delimited JSON objects
+    Ndjson,
+}
+
+pub(super) async fn write(stdout_def: &StdoutDestination, data: DataFrame) -> Result<()> {
+    let batches = data.limit(0, stdout_def.limit)?.collect().await?;
+
+    print!("{}", format_output(stdout_def.format, batches.as_slice())?);
+
+    Ok(())
+}
+
+// render the batches in the requested format, every format ends with a newline
+// only the JSON array isn't an output format of stages
+fn format_output(format: StdoutFormat, batches: &[RecordBatch]) -> Result<String> {
+    let format = match format {
+        StdoutFormat::Table => OutputFormat::Table,
+        StdoutFormat::Csv => OutputFormat::Csv,
+        StdoutFormat::Ndjson => OutputFormat::Json,
+        StdoutFormat::Json => {
+            let mut buffer = vec![];
+            let mut writer = ArrayWriter::new(&mut buffer);
+            writer.write_batches(batches.iter().collect::<Vec<_>>().as_slice())?;
+            writer.finish()?;
+            buffer.push(b'\n');
+
+            return Ok(String::from_utf8_lossy(buffer.as_slice()).into_owned());
+        }
+    };
+
+    Ok(format_batches(batches, format)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::SessionContext;
+
+    use super::*;
+
+    async fn batches() -> Vec<RecordBatch> {
+        SessionContext::new()
+            .sql("SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS t(id, name)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_format_output_ok() {
+        let batches = batches().await;
+
+        assert_eq!(
+            "id,name\n1,a\n2,b\n",
+            format_output(StdoutFormat::Csv, batches.as_slice()).unwrap()
+        );
+        assert_eq!(
+            "[{\"id\":1,\"name\":\"a\"},{\"id\":2,\"name\":\"b\"}]\n",
+            format_output(StdoutFormat::Json, batches.as_slice()).unwrap()
+        );
+        assert_eq!(
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"b\"}\n",
+            format_output(StdoutFormat::Ndjson, batches.as_slice()).unwrap()
+        );
+        assert_eq!(
+            "+----+------+\n| id | name |\n+----+------+\n| 1  | a    |\n| 2  | b    |\n+----+------+\n",
+            format_output(StdoutFormat::Table, batches.as_slice()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        compute::concat_batches,
+        json::{ArrayWriter, LineDelimitedWriter},
+    },
+    dataframe::DataFrame,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{debug, info};
+use url::Url;
+
+use super::{error, Result};
+
+/// A destination POSTing the output rows as JSON to an HTTP endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct WebhookDestination {
+    /// Name of the webhook destination, used to identify it in logs
+    pub name: String,
+
+    /// URL of the endpoint the rows are sent to, supports template params e.g. `https://api.example.com/ingest?date=${date}`
+    pub url: Url,
+
+    /// Headers sent with every request e.g. `Authorization`
+    #[new(default)]
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// Maximum number of rows sent in a single request, defaults to 1000
+    #[new(value = "default_batch_size()")]
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// Format of the request body, defaults to `Json`
+    #[new(default)]
+    #[serde(default)]
+    pub format: WebhookFormat,
+
+    /// Seconds after which a request is aborted if the response wasn't received completely, defaults to 30
+    #[new(value = "default_timeout_seconds()")]
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+
+    /// Name of a column that is added to the output and populated with the timestamp (UTC) of the write
+    /// Fails the write if the output already contains a column with this name
+    #[new(default)]
+    #[serde(default)]
+    pub ingestion_timestamp_column: Option<String>,
+
+    /// Maximum number of rows that may be written, the write is aborted when the output exceeds it
+    /// Checked by reading at most `max_output_rows + 1` rows before writing, defaults to unlimited
+    #[new(default)]
+    #[serde(default)]
+    pub max_output_rows: Option<usize>,
+}
+
+/// Format of the request body of a `WebhookDestination`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub enum WebhookFormat {
+    /// A single JSON array of objects (`application/json`)
+    #[default]
+    Json,
+    /// Newline delimited JSON objects (`application/x-ndjson`)
+    Ndjson,
+}
+
+impl WebhookFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            WebhookFormat::Json => "application/json",
+            WebhookFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+fn default_batch_size() -> usize {
+    1000
+}
+
+fn default_timeout_seconds() -> u64 {
+    30
+}
+
+/// POST the output in chunks of `batch_size` rows, the chunks are sent sequentially in order
+/// Nothing is sent if the output is empty, the write fails on the first request that doesn't succeed
+pub(super) async fn write(webhook_def: &WebhookDestination, data: DataFrame) -> Result<()> {
+    if webhook_def.batch_size == 0 {
+        return Err(error::Error::InvalidWebhookBatchSize(
+            webhook_def.name.clone(),
+        ));
+    }
+
+    let schema = Arc::new(data.schema().as_arrow().clone());
+    let batches = data.collect().await?;
+    let output = concat_batches(&schema, &batches)?;
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(webhook_def.timeout_seconds))
+        .build()
+        .map_err(|e| error::Error::WebhookRequest(webhook_def.name.clone(), e.to_string()))?;
+    let mut offset = 0;
+    while offset < output.num_rows() {
+        let length = webhook_def.batch_size.min(output.num_rows() - offset);
+        let body = serialize(webhook_def.format, &output.slice(offset, length))?;
+
+        debug!(
+            "Sending rows {offset} to {} to webhook destination '{}'",
+            offset + length,
+            webhook_def.name
+        );
+        send(&client, webhook_def, body).await?;
+
+        offset += length;
+    }
+
+    info!(
+        "Sent {} rows to webhook destination '{}'",
+        output.num_rows(),
+        webhook_def.name
+    );
+
+    Ok(())
+}
+
+// send a single request, non-2xx responses fail with their status and body
+async fn send(
+    client: &reqwest::Client,
+    webhook_def: &WebhookDestination,
+    body: Vec<u8>,
+) -> Result<()> {
+    let name = webhook_def.name.as_str();
+
+    let mut request = client.post(webhook_def.url.clone()).header(
+        reqwest::header::CONTENT_TYPE,
+        webhook_def.format.content_type(),
+    );
+    for (key, value) in webhook_def.headers.iter() {
+        request = request.header(key, value);
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| error::Error::WebhookRequest(name.to_string(), e.to_string()))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(error::Error::WebhookStatus(
+            name.to_string(),
+            status.as_u16(),
+            body,
+        ));
+    }
+
+    Ok(())
+}
+
+// render a chunk of rows as the request body
+fn serialize(format: WebhookFormat, batch: &RecordBatch) -> Result<Vec<u8>> {
+    let mut buffer = vec![];
+
+    match format {
+        WebhookFormat::Json => {
+            let mut writer = ArrayWriter::new(&mut buffer);
+            writer.write(batch)?;
+            writer.finish()?;
+        }
+        WebhookFormat::Ndjson => {
+            let mut writer = LineDelimitedWriter::new(&mut buffer);
+            writer.write(batch)?;
+            writer.finish()?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::SessionContext;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::test_server;
+
+    async fn serve(status: &'static str) -> (Url, mpsc::UnboundedReceiver<String>) {
+        test_server::serve(status, "error").await
+    }
+
+    async fn data() -> DataFrame {
+        SessionContext::new()
+            .sql("SELECT * FROM (VALUES (1, 'a'), (2, 'b'), (3, 'c')) AS t(id, name) ORDER BY id")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_write_webhook_ok() {
+        let (url, mut receiver) = serve("200 OK").await;
+        let mut webhook_def = WebhookDestination::new("ingest".into(), url);
+        webhook_def.batch_size = 2;
+
+        write(&webhook_def, data().await).await.unwrap();
+
+        assert_eq!(
+            r#"[{"id":1,"name":"a"},{"id":2,"name":"b"}]"#,
+            receiver.recv().await.unwrap()
+        );
+        assert_eq!(r#"[{"id":3,"name":"c"}]"#, receiver.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_write_webhook_ndjson_ok() {
+        let (url, mut receiver) = serve("200 OK").await;
+        let mut webhook_def = WebhookDestination::new("ingest".into(), url);
+        webhook_def.format = WebhookFormat::Ndjson;
+
+        write(&webhook_def, data().await).await.unwrap();
+
+        assert_eq!(
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"b\"}\n{\"id\":3,\"name\":\"c\"}\n",
+            receiver.recv().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_webhook_status_err() {
+        let (url, _receiver) = serve("500 Internal Server Error").await;
+        let webhook_def = WebhookDestination::new("ingest".into(), url);
+
+        let result = write(&webhook_def, data().await).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::WebhookStatus(_, 500, body)) if body == "error"
+        ));
+    }
+}
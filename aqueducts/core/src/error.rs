@@ -6,6 +6,44 @@ pub type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>;
 pub enum Error {
     #[error("Missing template parameters: {0:?}")]
     MissingParams(HashSet<String>),
+    #[error("Missing environment variables: {0:?}")]
+    MissingEnvVars(HashSet<String>),
+    #[error("Missing secrets: {0:?}")]
+    MissingSecrets(HashSet<String>),
+    #[error("Failed to resolve secret '{0}': {1}")]
+    SecretResolution(String, String),
+    #[error("Location '{0}' contains empty path segments, this is usually caused by an empty template parameter")]
+    EmptyPathSegment(String),
+    #[error("Stage '{0}' references stage '{1}' which was skipped")]
+    SkippedStageReference(String, String),
+    #[error("Stage '{0}' depends on '{1}' which is neither a source nor a prior stage")]
+    UnknownStageDependency(String, String),
+    #[error("Source '{0}' references normalization '{1}' which is not defined")]
+    MissingNormalization(String, String),
+    #[error("Stage '{stage}' timed out after {seconds} seconds")]
+    Timeout { stage: String, seconds: u64 },
+    #[error("Failed to plan stages: {0:?}")]
+    UnplannableStages(Vec<String>),
+    #[error("Failed to register UDFs: {0}")]
+    UdfRegistration(datafusion::error::DataFusionError),
+    #[error(
+        "Failed to parse definition file '{0}' in any of the enabled formats: {failures}",
+        failures = .1.join("; ")
+    )]
+    UnrecognizedFormat(String, Vec<String>),
+    #[error("Execution option '{0}' must be greater than 0")]
+    InvalidExecutionOption(String),
+    #[error("Failed to register the object store of checkpoint directory '{0}': {1}")]
+    CheckpointStore(String, deltalake::DeltaTableError),
+    #[error("Failed to checkpoint stage '{0}': {1}")]
+    Checkpoint(String, datafusion::error::DataFusionError),
+    #[error("Failed to delete the checkpoints in checkpoint directory '{0}': {1}")]
+    ClearCheckpoints(String, datafusion::error::DataFusionError),
+    #[error(
+        "Pipeline was cancelled{}",
+        .stage.as_ref().map(|stage| format!(" while running stage '{stage}'")).unwrap_or_default()
+    )]
+    Cancelled { stage: Option<String> },
 
     // -- Modules
     #[error("Failed to register source: {0}")]
@@ -35,3 +73,18 @@ pub enum Error {
     #[error("Failed to read output table: {0}")]
     ReadTableError(#[from] datafusion::error::DataFusionError),
 }
+
+impl Error {
+    // failures of a format's deserializer, other errors (e.g. missing params) don't depend on the format
+    pub(crate) fn is_format_error(&self) -> bool {
+        match self {
+            #[cfg(feature = "json")]
+            Error::JsonError(_) => true,
+            #[cfg(feature = "toml")]
+            Error::TomlDeserializationError(_) => true,
+            #[cfg(feature = "yaml")]
+            Error::YmlError(_) => true,
+            _ => false,
+        }
+    }
+}
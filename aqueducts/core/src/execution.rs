@@ -0,0 +1,61 @@
+use datafusion::execution::context::SessionContext;
+use serde::{Deserialize, Serialize};
+
+use crate::{error, Result};
+
+/// Options of the DataFusion session the pipeline is executed in, unset options keep the value configured on the provided `SessionContext`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct ExecutionOptions {
+    /// Number of partitions queries are planned with, defaults to the number of CPU cores
+    #[new(default)]
+    #[serde(default)]
+    pub target_partitions: Option<usize>,
+
+    /// Number of rows of the record batches processed by the operators, defaults to `8192`
+    #[new(default)]
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+
+    /// Set to `false` to disable combining small record batches into batches of `batch_size` rows, defaults to `true`
+    #[new(default)]
+    #[serde(default)]
+    pub coalesce_batches: Option<bool>,
+
+    /// Maximum number of sources registered concurrently, defaults to unbounded
+    /// Limits e.g. the number of simultaneous connections opened by database sources
+    #[new(default)]
+    #[serde(default)]
+    pub max_concurrent_sources: Option<usize>,
+}
+
+impl ExecutionOptions {
+    /// Apply the options to the configuration of the session
+    pub fn apply(&self, ctx: &SessionContext) -> Result<()> {
+        for (option, value) in [
+            ("target_partitions", self.target_partitions),
+            ("batch_size", self.batch_size),
+            ("max_concurrent_sources", self.max_concurrent_sources),
+        ] {
+            if value == Some(0) {
+                return Err(error::Error::InvalidExecutionOption(option.to_string()));
+            }
+        }
+
+        let state = ctx.state_ref();
+        let mut state = state.write();
+        let execution = &mut state.config_mut().options_mut().execution;
+
+        if let Some(target_partitions) = self.target_partitions {
+            execution.target_partitions = target_partitions;
+        }
+        if let Some(batch_size) = self.batch_size {
+            execution.batch_size = batch_size;
+        }
+        if let Some(coalesce_batches) = self.coalesce_batches {
+            execution.coalesce_batches = coalesce_batches;
+        }
+
+        Ok(())
+    }
+}
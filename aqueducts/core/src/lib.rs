@@ -2,18 +2,29 @@ use datafusion::execution::context::SessionContext;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     path::Path,
     sync::{Arc, OnceLock},
-    time::Instant,
+    time::{Duration, Instant},
 };
-use tokio::task::JoinHandle;
-use tracing::{debug, error, info, instrument, warn};
+use tokio::{sync::Semaphore, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, warn, Instrument};
+use url::Url;
 
+pub mod checkpoint;
 pub mod destinations;
 pub mod error;
+pub mod execution;
+pub mod profile;
+pub mod schema_transform;
+pub mod secrets;
 pub mod sources;
 pub mod stages;
+pub mod udfs;
+
+#[cfg(all(test, feature = "http"))]
+mod test_server;
 
 use destinations::*;
 use sources::*;
@@ -26,12 +37,15 @@ pub mod prelude {
     pub use super::stages::*;
     pub use super::{Aqueduct, AqueductBuilder};
 
-    pub use super::run_pipeline;
+    pub use super::{
+        committed_versions, dry_run_pipeline, run_pipeline, run_pipeline_with_cancellation,
+    };
+    pub use tokio_util::sync::CancellationToken;
 }
 
 pub type Result<T> = core::result::Result<T, error::Error>;
 
-static PARAM_REGEX: OnceLock<Regex> = OnceLock::new();
+static TEMPLATE_REGEX: OnceLock<Regex> = OnceLock::new();
 
 /// Definition for an `Aqueduct` data pipeline
 #[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
@@ -46,7 +60,64 @@ pub struct Aqueduct {
 
     /// Destination for the final step of the `Aqueduct`
     /// takes the last stage as input for the write operation
+    /// Deprecated alias for a single entry of `destinations`, when both are set it is written first
     pub destination: Option<Destination>,
+
+    /// Destinations the result of the last stage is written to in the defined order
+    /// With more than one destination the result is materialized once and written to each of them
+    #[new(default)]
+    #[serde(default)]
+    pub destinations: Vec<Destination>,
+
+    /// When set to `true` locations of sources and destinations containing empty path segments (e.g. `s3://bucket//file.csv`) are accepted
+    /// Duplicate slashes in non-file locations are then normalized, by default these locations are rejected as they are usually caused by an empty template parameter
+    #[new(default)]
+    #[serde(default)]
+    pub allow_empty_path_segments: bool,
+
+    /// Catalog used to resolve the locations of `Catalog` sources
+    #[new(default)]
+    #[serde(default)]
+    pub catalog: Option<catalog::Catalog>,
+
+    /// When set to `true` per-column null counts and approximate distinct counts of the final stage are computed and logged
+    /// Requires an additional scan of the final stage, defaults to `false`
+    #[new(default)]
+    #[serde(default)]
+    pub profile: bool,
+
+    /// Named normalization profiles (renames, casts and timezone assumptions) that sources can reference by name
+    #[new(default)]
+    #[serde(default)]
+    pub normalizations: HashMap<String, normalization::Normalization>,
+
+    /// Bundles of SQL functions (e.g. `hash`) registered on the context before the stages are executed
+    #[new(default)]
+    #[serde(default)]
+    pub udfs: Vec<udfs::UdfBundle>,
+
+    /// Location where the output of every completed stage is persisted as parquet e.g. file:///tmp/checkpoints/, s3://bucket_name/checkpoints/
+    /// Combined with `resume` a failed pipeline can be re-run without recomputing the stages that already completed
+    /// The checkpoints of the stages are deleted once the pipeline completed successfully, other objects inside the directory are kept
+    #[new(default)]
+    #[serde(default)]
+    pub checkpoint_dir: Option<Url>,
+
+    /// Storage options for the object store of `checkpoint_dir`
+    #[new(default)]
+    #[serde(default)]
+    pub checkpoint_storage_options: HashMap<String, String>,
+
+    /// When set to `true` stages with a complete checkpoint in `checkpoint_dir` are loaded from it instead of being executed, defaults to `false`
+    /// Checkpoints written for a different definition of the stage, its sources or the stages before it are ignored
+    #[new(default)]
+    #[serde(default)]
+    pub resume: bool,
+
+    /// Options of the DataFusion session (e.g. `target_partitions`, `batch_size`) applied before the sources are registered
+    #[new(default)]
+    #[serde(default)]
+    pub execution: Option<execution::ExecutionOptions>,
 }
 
 impl Aqueduct {
@@ -55,6 +126,106 @@ impl Aqueduct {
         AqueductBuilder::default()
     }
 
+    /// Resolve all `Catalog` sources into `Delta` sources using the provided resolver
+    /// Called with the configured `catalog` when running the pipeline, use this to plug in a custom `CatalogResolver`
+    pub fn resolve_catalog_sources(
+        mut self,
+        resolver: &dyn catalog::CatalogResolver,
+    ) -> Result<Self> {
+        self.sources = self
+            .sources
+            .into_iter()
+            .map(|source| match source {
+                Source::Catalog(catalog_source) => {
+                    let table = catalog_source.table.clone();
+                    let delta_source = catalog_source.resolve(resolver)?;
+                    debug!(
+                        "Resolved catalog table '{table}' to location '{}'",
+                        delta_source.location
+                    );
+
+                    Ok(Source::Delta(delta_source))
+                }
+                source => Ok(source),
+            })
+            .collect::<core::result::Result<Vec<_>, sources::error::Error>>()?;
+
+        Ok(self)
+    }
+
+    /// All destinations of this pipeline in the order they are written to, the deprecated `destination` comes first
+    pub fn all_destinations(&self) -> impl Iterator<Item = &Destination> {
+        self.destination.iter().chain(self.destinations.iter())
+    }
+
+    /// Returns the optional crate features (e.g. `s3`, `odbc`) required to run this pipeline
+    /// Inferred from the source and destination types as well as the schemes of their locations
+    pub fn required_features(&self) -> Vec<&'static str> {
+        let mut features = Vec::new();
+
+        for source in self.sources.iter() {
+            match source {
+                Source::Delta(DeltaSource { location, .. })
+                | Source::Directory(DirSource { location, .. }) => {
+                    features.extend(location_feature(location))
+                }
+                Source::File(FileSource {
+                    location,
+                    additional_locations,
+                    ..
+                }) => features.extend(
+                    std::iter::once(location)
+                        .chain(additional_locations)
+                        .filter_map(location_feature),
+                ),
+                #[cfg(feature = "odbc")]
+                Source::Odbc(_) => features.push("odbc"),
+                #[cfg(feature = "kafka")]
+                Source::Kafka(_) => features.push("kafka"),
+                #[cfg(feature = "iceberg")]
+                Source::Iceberg(_) => features.push("iceberg"),
+                #[cfg(feature = "bigquery")]
+                Source::BigQuery(_) => features.push("bigquery"),
+                #[cfg(feature = "mysql")]
+                Source::MySql(_) => features.push("mysql"),
+                #[cfg(feature = "postgres")]
+                Source::Postgres(_) => features.push("postgres"),
+                #[cfg(feature = "http")]
+                Source::Http(_) => features.push("http"),
+                Source::InMemory(_) | Source::Catalog(_) => (),
+            }
+
+            #[cfg(feature = "orc")]
+            if source_file_types(source).any(|file_type| matches!(file_type, FileType::Orc(_))) {
+                features.push("orc");
+            }
+        }
+
+        if let Some(checkpoint_dir) = &self.checkpoint_dir {
+            features.extend(location_feature(checkpoint_dir));
+        }
+
+        for destination in self.all_destinations() {
+            match destination {
+                Destination::Delta(delta::DeltaDestination { location, .. })
+                | Destination::File(file::FileDestination { location, .. }) => {
+                    features.extend(location_feature(location))
+                }
+                #[cfg(feature = "odbc")]
+                Destination::Odbc(_) => features.push("odbc"),
+                #[cfg(feature = "postgres")]
+                Destination::Postgres(_) => features.push("postgres"),
+                #[cfg(feature = "http")]
+                Destination::Webhook(_) => features.push("http"),
+                Destination::InMemory(_) | Destination::Stdout(_) => (),
+            }
+        }
+
+        features.sort_unstable();
+        features.dedup();
+        features
+    }
+
     /// Load an Aqueduct table definition from a local fs path containing a json file
     /// Provided params will be substituted throughout the file (format: `${param}`) with the corresponding value
     #[cfg(feature = "json")]
@@ -66,7 +237,7 @@ impl Aqueduct {
         let parsed = serde_json::from_str::<serde_json::Value>(raw.as_str())?;
         let parsed = serde_json::to_string(&parsed)?;
         let definition = Self::substitute_params(parsed.as_str(), params)?;
-        let aqueduct = serde_json::from_str::<Aqueduct>(definition.as_str())?;
+        let aqueduct = serde_json::from_str::<Aqueduct>(definition.as_str())?.validate()?;
 
         Ok(aqueduct)
     }
@@ -82,7 +253,7 @@ impl Aqueduct {
         let parsed = toml::from_str::<toml::Value>(raw.as_str())?;
         let parsed = toml::to_string(&parsed)?;
         let definition = Self::substitute_params(parsed.as_str(), params)?;
-        let aqueduct = toml::from_str::<Aqueduct>(definition.as_str())?;
+        let aqueduct = toml::from_str::<Aqueduct>(definition.as_str())?.validate()?;
 
         Ok(aqueduct)
     }
@@ -98,11 +269,55 @@ impl Aqueduct {
         let parsed = serde_yml::from_str::<serde_yml::Value>(raw.as_str())?;
         let parsed = serde_yml::to_string(&parsed)?;
         let definition = Self::substitute_params(parsed.as_str(), params)?;
-        let aqueduct = serde_yml::from_str::<Aqueduct>(definition.as_str())?;
+        let aqueduct = serde_yml::from_str::<Aqueduct>(definition.as_str())?.validate()?;
 
         Ok(aqueduct)
     }
 
+    /// Load an Aqueduct table definition from a local fs path, the format is selected by the file extension (`yml`, `yaml`, `json` or `toml`)
+    /// Files with any other or no extension are parsed as yaml, json and toml in this order (limited to the enabled formats), the first success is returned
+    /// Provided params will be substituted throughout the file (format: `${param}`) with the corresponding value
+    pub fn try_from_file<P>(path: P, params: HashMap<String, String>) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        match path.extension().and_then(|s| s.to_str()) {
+            #[cfg(feature = "yaml")]
+            Some("yml" | "yaml") => return Self::try_from_yml(path, params),
+            #[cfg(feature = "json")]
+            Some("json") => return Self::try_from_json(path, params),
+            #[cfg(feature = "toml")]
+            Some("toml") => return Self::try_from_toml(path, params),
+            _ => (),
+        }
+
+        type Loader = fn(&str, HashMap<String, String>) -> Result<Aqueduct>;
+        let loaders: &[(&str, Loader)] = &[
+            #[cfg(feature = "yaml")]
+            ("yaml", Self::try_from_yml_str),
+            #[cfg(feature = "json")]
+            ("json", Self::try_from_json_str),
+            #[cfg(feature = "toml")]
+            ("toml", Self::try_from_toml_str),
+        ];
+
+        let raw = std::fs::read_to_string(path)?;
+        let mut failures = vec![];
+        for (format, loader) in loaders {
+            match loader(raw.as_str(), params.clone()) {
+                Ok(aqueduct) => return Ok(aqueduct),
+                Err(e) if e.is_format_error() => failures.push(format!("{format}: {e}")),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(error::Error::UnrecognizedFormat(
+            path.display().to_string(),
+            failures,
+        ))
+    }
+
     /// Load an Aqueduct table definition from a &str containing a json string
     /// Provided params will be substituted throughout the file (format: `${param}`) with the corresponding value
     #[cfg(feature = "json")]
@@ -110,7 +325,7 @@ impl Aqueduct {
         let parsed = serde_json::from_str::<serde_json::Value>(contents)?;
         let parsed = serde_json::to_string(&parsed)?;
         let definition = Self::substitute_params(parsed.as_str(), params)?;
-        let aqueduct = serde_json::from_str::<Aqueduct>(definition.as_str())?;
+        let aqueduct = serde_json::from_str::<Aqueduct>(definition.as_str())?.validate()?;
 
         Ok(aqueduct)
     }
@@ -122,7 +337,7 @@ impl Aqueduct {
         let parsed = toml::from_str::<toml::Value>(contents)?;
         let parsed = toml::to_string(&parsed)?;
         let definition = Self::substitute_params(parsed.as_str(), params)?;
-        let aqueduct = toml::from_str::<Aqueduct>(definition.as_str())?;
+        let aqueduct = toml::from_str::<Aqueduct>(definition.as_str())?.validate()?;
 
         Ok(aqueduct)
     }
@@ -134,33 +349,143 @@ impl Aqueduct {
         let parsed = serde_yml::from_str::<serde_yml::Value>(contents)?;
         let parsed = serde_yml::to_string(&parsed)?;
         let definition = Self::substitute_params(parsed.as_str(), params)?;
-        let aqueduct = serde_yml::from_str::<Aqueduct>(definition.as_str())?;
+        let aqueduct = serde_yml::from_str::<Aqueduct>(definition.as_str())?.validate()?;
 
         Ok(aqueduct)
     }
 
-    fn substitute_params(raw: &str, params: HashMap<String, String>) -> Result<String> {
-        let mut definition = raw.to_string();
-
-        params.into_iter().for_each(|(name, value)| {
-            let template = format!("${{{name}}}");
-            definition = definition.replace(template.as_str(), value.as_str());
+    // reject or normalize empty path segments in the locations after the params have been substituted
+    // and reject invalid destination options
+    fn validate(mut self) -> Result<Self> {
+        self.all_destinations().try_for_each(validate_destination)?;
+
+        let allow_empty_path_segments = self.allow_empty_path_segments;
+
+        let source_locations = self.sources.iter_mut().flat_map(|source| match source {
+            Source::Delta(DeltaSource { location, .. })
+            | Source::Directory(DirSource { location, .. }) => vec![location],
+            Source::File(FileSource {
+                location,
+                additional_locations,
+                ..
+            }) => std::iter::once(location)
+                .chain(additional_locations.iter_mut())
+                .collect(),
+            _ => vec![],
         });
+        let destination_location = self
+            .destination
+            .iter_mut()
+            .chain(self.destinations.iter_mut())
+            .filter_map(|destination| match destination {
+                Destination::Delta(delta::DeltaDestination { location, .. })
+                | Destination::File(file::FileDestination { location, .. }) => Some(location),
+                _ => None,
+            });
+
+        for location in source_locations
+            .chain(destination_location)
+            .chain(self.checkpoint_dir.iter_mut())
+        {
+            if location.scheme() == "file" || !has_empty_path_segments(location) {
+                continue;
+            }
+
+            if !allow_empty_path_segments {
+                let error = error::Error::EmptyPathSegment(location.to_string());
+
+                error!("{error}");
+                return Err(error);
+            }
 
-        let captures = PARAM_REGEX
-            .get_or_init(|| Regex::new("\\$\\{([a-zA-Z0-9_]+)\\}").expect("invalid regex"))
-            .captures_iter(definition.as_str());
-
-        let missing_params = captures
-            .map(|capture| {
-                let param = capture
-                    .get(1)
-                    .expect("no capture group found")
-                    .as_str()
-                    .to_string();
-                param
+            let normalized = normalize_path(location.path());
+            debug!("Normalizing location {location} to path {normalized}");
+            location.set_path(normalized.as_str());
+        }
+
+        Ok(self)
+    }
+
+    /// Substitute `${name}` templates with params, remaining `${env:NAME}` templates are resolved from environment variables
+    /// `${name:-default}` templates fall back to the literal default (up to the closing `}`) if the param is not provided
+    /// `${secret:name}` templates are resolved by the registered [`secrets::SecretResolver`]
+    /// Param and secret values are inserted as is and never parsed as templates
+    pub fn substitute_params(raw: &str, params: HashMap<String, String>) -> Result<String> {
+        Self::substitute_params_with_resolver(raw, params, secrets::secret_resolver().as_ref())
+    }
+
+    /// Substitute templates like [`Aqueduct::substitute_params`] resolving `${secret:name}` templates with the given resolver
+    pub fn substitute_params_with_resolver(
+        raw: &str,
+        params: HashMap<String, String>,
+        resolver: &dyn secrets::SecretResolver,
+    ) -> Result<String> {
+        // all templates are resolved in a single pass over the raw definition
+        // so substituted values (including secrets) are never parsed as templates
+        let mut missing_params = HashSet::new();
+        let mut missing_env_vars = HashSet::new();
+        let mut missing_secrets = HashSet::new();
+        let mut failed_secret = None;
+        let definition = TEMPLATE_REGEX
+            .get_or_init(|| Regex::new("\\$\\{([^}]*)\\}").expect("invalid regex"))
+            .replace_all(raw, |captures: &regex::Captures| {
+                let template = &captures[1];
+
+                if let Some(value) = params.get(template) {
+                    return value.clone();
+                }
+
+                if let Some(name) = template
+                    .strip_prefix("env:")
+                    .filter(|name| is_template_name(name))
+                {
+                    return std::env::var(name).unwrap_or_else(|_| {
+                        missing_env_vars.insert(name.to_string());
+                        String::new()
+                    });
+                }
+
+                if let Some(name) = template
+                    .strip_prefix("secret:")
+                    .filter(|name| is_secret_name(name))
+                {
+                    return match resolver.resolve(name) {
+                        Ok(Some(value)) => value,
+                        Ok(None) => {
+                            missing_secrets.insert(name.to_string());
+                            String::new()
+                        }
+                        Err(message) => {
+                            failed_secret.get_or_insert((name.to_string(), message));
+                            String::new()
+                        }
+                    };
+                }
+
+                if let Some((name, default)) = template
+                    .split_once(":-")
+                    .filter(|(name, _)| is_template_name(name))
+                {
+                    return params
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_else(|| default.to_string());
+                }
+
+                if is_template_name(template) {
+                    missing_params.insert(template.to_string());
+                }
+
+                captures[0].to_string()
             })
-            .collect::<HashSet<String>>();
+            .into_owned();
+
+        if !missing_env_vars.is_empty() {
+            let error = error::Error::MissingEnvVars(missing_env_vars);
+
+            error!("{error}");
+            return Err(error);
+        }
 
         if !missing_params.is_empty() {
             let error = error::Error::MissingParams(missing_params);
@@ -169,6 +494,20 @@ impl Aqueduct {
             return Err(error);
         }
 
+        if let Some((name, message)) = failed_secret {
+            let error = error::Error::SecretResolution(name, message);
+
+            error!("{error}");
+            return Err(error);
+        }
+
+        if !missing_secrets.is_empty() {
+            let error = error::Error::MissingSecrets(missing_secrets);
+
+            error!("{error}");
+            return Err(error);
+        }
+
         Ok(definition)
     }
 }
@@ -215,106 +554,314 @@ pub fn register_handlers() {
 
 /// Execute an `Aqueduct` pipeline, using a provided datafusion `SessionContext`
 /// Returns the provided context once the pipeline completes
-#[instrument(skip(ctx, aqueduct), err)]
 pub async fn run_pipeline(
     ctx: Arc<SessionContext>,
     aqueduct: Aqueduct,
+) -> Result<Arc<SessionContext>> {
+    run_pipeline_with_cancellation(ctx, aqueduct, CancellationToken::new()).await
+}
+
+/// Execute an `Aqueduct` pipeline that can be cancelled using the provided `CancellationToken`
+/// The token is checked before each stage and before each destination write, running stages are aborted when it is cancelled
+/// A destination write that already started is completed, destinations are never partially written due to a cancellation
+#[instrument(skip(ctx, aqueduct, cancellation), err)]
+pub async fn run_pipeline_with_cancellation(
+    ctx: Arc<SessionContext>,
+    aqueduct: Aqueduct,
+    cancellation: CancellationToken,
 ) -> Result<Arc<SessionContext>> {
     let mut stage_ttls: HashMap<String, usize> = HashMap::new();
     let start_time = Instant::now();
 
     info!("Running Aqueduct ...");
 
-    if let Some(destination) = &aqueduct.destination {
+    let aqueduct = match aqueduct.catalog.clone() {
+        Some(catalog) => aqueduct.resolve_catalog_sources(&catalog)?,
+        None => aqueduct,
+    };
+
+    aqueduct.sources.iter().try_for_each(validate_source)?;
+    aqueduct
+        .all_destinations()
+        .try_for_each(validate_destination)?;
+    validate_stages(ctx.clone(), &aqueduct)?;
+    if let Some(execution) = &aqueduct.execution {
+        execution.apply(&ctx)?;
+    }
+    udfs::register_udfs(&ctx, &aqueduct.udfs).map_err(error::Error::UdfRegistration)?;
+
+    for destination in aqueduct.all_destinations() {
         let time = Instant::now();
 
-        register_destination(ctx.clone(), destination).await?;
+        register_destination(ctx.clone(), destination)
+            .await
+            .inspect_err(|e| {
+                error!("Failed to create destination '{}': {e}", destination.name())
+            })?;
 
         info!(
-            "Created destination ... Elapsed time: {:.2?}",
+            "Created destination {} ... Elapsed time: {:.2?}",
+            destination.name(),
             time.elapsed()
         );
     }
 
-    let handles = aqueduct
-        .sources
-        .iter()
-        .enumerate()
-        .map(|(pos, source)| {
-            let time = Instant::now();
-            let source_ = source.clone();
-            let ctx_ = ctx.clone();
-
-            let handle = tokio::spawn(async move {
-                register_source(ctx_, source_).await?;
-
-                Ok(())
-            });
+    if let Some(checkpoint_dir) = &aqueduct.checkpoint_dir {
+        aqueducts_utils::store::register_object_store(
+            ctx.clone(),
+            checkpoint_dir,
+            &aqueduct.checkpoint_storage_options,
+        )
+        .map_err(|e| error::Error::CheckpointStore(checkpoint_dir.to_string(), e))?;
+    }
 
-            (pos, time, handle)
-        })
-        .collect::<Vec<(usize, Instant, JoinHandle<Result<()>>)>>();
+    // offsets consumed by a failed run or a dry run on the same context must not be committed by this run
+    #[cfg(feature = "kafka")]
+    discard_kafka_offsets(&ctx);
 
-    for (pos, time, handle) in handles {
-        handle.await.expect("failed to join task")?;
+    register_sources(ctx.clone(), &aqueduct).await?;
 
-        info!(
-            "Registered source #{pos} ... Elapsed time: {:.2?}",
-            time.elapsed()
-        );
-    }
+    let mut skipped_stages: HashSet<String> = HashSet::new();
 
     for (pos, parallel) in aqueduct.stages.iter().enumerate() {
-        let mut handles: Vec<JoinHandle<Result<()>>> = Vec::new();
+        if cancellation.is_cancelled() {
+            warn!("Pipeline cancelled ... skipping remaining stages");
+            abort_stages(ctx.clone(), std::iter::empty(), &aqueduct.stages[..pos]).await;
+            return Err(error::Error::Cancelled { stage: None });
+        }
+
+        let mut handles: Vec<(String, JoinHandle<Result<bool>>)> = Vec::new();
 
         for (sub, stage) in parallel.iter().enumerate() {
             let stage_ = stage.clone();
             let ctx_ = ctx.clone();
             let name = stage.name.clone();
+            let skipped_stages_ = skipped_stages.clone();
+            let checkpoint_dir = aqueduct.checkpoint_dir.clone();
+            let resume = aqueduct.resume;
+            let fingerprint = match checkpoint_dir {
+                Some(_) => checkpoint::fingerprint(&aqueduct, pos, stage)
+                    .map_err(|e| error::Error::Checkpoint(name.clone(), e))?,
+                None => String::new(),
+            };
+
+            let task = async move {
+                let checkpoint_error = |e: datafusion::error::DataFusionError| {
+                    error::Error::Checkpoint(name.clone(), e)
+                };
+
+                if let Some(checkpoint_dir) = checkpoint_dir.as_ref().filter(|_| resume) {
+                    if checkpoint::load(&ctx_, checkpoint_dir, name.as_str(), &fingerprint)
+                        .await
+                        .map_err(checkpoint_error)?
+                    {
+                        info!("Loaded stage {name} #{pos}:{sub} from checkpoint");
+                        return Ok(true);
+                    }
+                }
+
+                if !evaluate_condition(ctx_.clone(), &stage_).await? {
+                    info!("Skipping stage {name} #{pos}:{sub} ... condition evaluated to false");
+                    return Ok(false);
+                }
+
+                for skipped in skipped_stages_.iter() {
+                    if references_stage(&stage_, skipped, &stage_reference_regex(skipped)?) {
+                        let error =
+                            error::Error::SkippedStageReference(name.clone(), skipped.clone());
+
+                        error!("{error}");
+                        return Err(error);
+                    }
+                }
+
+                // sorted so that the metadata is logged in a stable order
+                let metadata = stage_
+                    .metadata
+                    .clone()
+                    .into_iter()
+                    .collect::<BTreeMap<_, _>>();
 
-            let handle = tokio::spawn(async move {
                 let time = Instant::now();
-                info!("Running stage {} #{pos}:{sub}", name);
-
-                process_stage(ctx_, stage_).await?;
+                info!(?metadata, "Running stage {} #{pos}:{sub}", name);
+
+                let row_count = match stage_.timeout_seconds {
+                    Some(timeout) => tokio::time::timeout(
+                        Duration::from_secs(timeout),
+                        process_stage(ctx_.clone(), stage_),
+                    )
+                    .await
+                    .map_err(|_| {
+                        let error = error::Error::Timeout {
+                            stage: name.clone(),
+                            seconds: timeout,
+                        };
+
+                        error!("{error}");
+                        error
+                    })??,
+                    None => process_stage(ctx_.clone(), stage_).await?,
+                };
+
+                if let Some(checkpoint_dir) = &checkpoint_dir {
+                    checkpoint::save(&ctx_, checkpoint_dir, name.as_str(), &fingerprint)
+                        .await
+                        .map_err(checkpoint_error)?;
+                }
+                let row_count = row_count
+                    .map(|row_count| row_count.to_string())
+                    .unwrap_or_else(|| "n/a (not materialized)".into());
 
                 info!(
-                    "Finished processing stage {name} #{pos}:{sub} ... Elapsed time: {:.2?}",
+                    ?metadata,
+                    "Finished processing stage {name} #{pos}:{sub} ... Rows: {row_count}, Elapsed time: {:.2?}",
                     time.elapsed()
                 );
-                Ok(())
-            });
+                Ok(true)
+            };
+            // the events of the stage are emitted within the span of the pipeline run
+            let handle = tokio::spawn(task.in_current_span());
 
             calculate_ttl(&mut stage_ttls, stage.name.as_str(), pos, &aqueduct.stages)?;
-            handles.push(handle);
+            handles.push((stage.name.clone(), handle));
         }
 
-        for handle in handles {
-            handle.await.expect("failed to join task")?;
+        let mut handles = handles.into_iter();
+        while let Some((name, mut handle)) = handles.next() {
+            let result = tokio::select! {
+                biased;
+                result = &mut handle => result.expect("failed to join task"),
+                _ = cancellation.cancelled() => {
+                    warn!("Pipeline cancelled ... aborting running stages");
+                    let stage = Some(name.clone());
+                    let handles = std::iter::once((name, handle)).chain(handles);
+                    abort_stages(ctx.clone(), handles, &aqueduct.stages[..=pos]).await;
+                    return Err(error::Error::Cancelled { stage });
+                }
+            };
+
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    skipped_stages.insert(name);
+                }
+                Err(error) => {
+                    abort_stages(ctx.clone(), handles, &aqueduct.stages[..=pos]).await;
+                    return Err(error);
+                }
+            }
         }
 
         deregister_stages(ctx.clone(), &stage_ttls, pos)?;
     }
 
-    if let (Some(last_stage), Some(destination)) = (
-        aqueduct.stages.last().and_then(|s| s.last()),
-        &aqueduct.destination,
-    ) {
+    if let Some(last_stage) = aqueduct
+        .stages
+        .last()
+        .and_then(|s| s.last())
+        .filter(|stage| aqueduct.profile && !skipped_stages.contains(&stage.name))
+    {
         let time = Instant::now();
 
         let df = ctx.table(last_stage.name.as_str()).await?;
-        write_to_destination(ctx.clone(), destination, df).await?;
-
-        ctx.deregister_table(last_stage.name.as_str())?;
+        for column in profile::profile(df).await? {
+            info!(
+                "Profile of stage {} column '{}': null count {}, approx. distinct count {}",
+                last_stage.name,
+                column.column,
+                column.null_count,
+                column
+                    .approx_distinct_count
+                    .map(|count| count.to_string())
+                    .unwrap_or_else(|| "n/a".into())
+            );
+        }
 
         info!(
-            "Finished writing to destination ... Elapsed time: {:.2?}",
+            "Finished profiling stage {} ... Elapsed time: {:.2?}",
+            last_stage.name,
             time.elapsed()
         );
+    }
+
+    let destinations = aqueduct.all_destinations().collect::<Vec<_>>();
+    if let Some(last_stage) = aqueduct
+        .stages
+        .last()
+        .and_then(|s| s.last())
+        .filter(|_| !destinations.is_empty())
+    {
+        let mut df = ctx.table(last_stage.name.as_str()).await?;
+
+        // a persisted in-memory destination may replace the last stage under the same name
+        let persisted = destinations.iter().any(|destination| match destination {
+            Destination::InMemory(mem_def) => mem_def.persist && mem_def.name == last_stage.name,
+            _ => false,
+        });
+
+        // materialize the result once instead of recomputing the stages for every destination
+        if destinations.len() > 1 {
+            df = df.cache().await?;
+        }
+
+        for destination in destinations {
+            if cancellation.is_cancelled() {
+                warn!(
+                    "Pipeline cancelled ... skipping write to destination {}",
+                    destination.name()
+                );
+                return Err(error::Error::Cancelled { stage: None });
+            }
+
+            let time = Instant::now();
+
+            let version = write_to_destination(ctx.clone(), destination, df.clone())
+                .await
+                .inspect_err(|e| {
+                    error!(
+                        "Failed to write to destination '{}': {e}",
+                        destination.name()
+                    )
+                })?;
+
+            if let Some(version) = version {
+                info!(
+                    version,
+                    "Delta destination '{}' is at version {version}",
+                    destination.name()
+                );
+                record_committed_version(&ctx, destination.name(), version);
+            }
+
+            info!(
+                "Finished writing to destination {} ... Elapsed time: {:.2?}",
+                destination.name(),
+                time.elapsed()
+            );
+        }
+
+        if !persisted {
+            ctx.deregister_table(last_stage.name.as_str())?;
+        }
+
+        // the consumed messages are only acknowledged once they were written
+        #[cfg(feature = "kafka")]
+        commit_kafka_offsets(&ctx);
     } else {
         warn!("No destination defined ... skipping write");
     }
 
+    if let Some(checkpoint_dir) = &aqueduct.checkpoint_dir {
+        let stages = aqueduct
+            .stages
+            .iter()
+            .flatten()
+            .map(|stage| stage.name.as_str());
+        checkpoint::clear(&ctx, checkpoint_dir, stages)
+            .await
+            .map_err(|e| error::Error::ClearCheckpoints(checkpoint_dir.to_string(), e))?;
+    }
+
     info!(
         "Finished processing pipeline ... Total time: {:.2?}",
         start_time.elapsed()
@@ -323,6 +870,238 @@ pub async fn run_pipeline(
     Ok(ctx)
 }
 
+/// Versions of the delta tables written by the destinations of a pipeline, keyed by destination name
+/// Stored as an extension of the `SessionConfig` of the context the pipeline ran on, see [`committed_versions`]
+#[derive(Debug, Clone, Default)]
+pub struct CommittedVersions(pub BTreeMap<String, i64>);
+
+/// Versions of the delta tables written by the pipelines that ran on this context, keyed by destination name
+/// A destination written by multiple runs reports the version of the latest write
+pub fn committed_versions(ctx: &SessionContext) -> BTreeMap<String, i64> {
+    ctx.state()
+        .config()
+        .get_extension::<CommittedVersions>()
+        .map(|versions| versions.0.clone())
+        .unwrap_or_default()
+}
+
+// add the version written to a delta destination to the committed versions of the context
+fn record_committed_version(ctx: &SessionContext, destination: &str, version: i64) {
+    let state = ctx.state_ref();
+    let mut state = state.write();
+
+    let mut versions = state
+        .config()
+        .get_extension::<CommittedVersions>()
+        .map(|versions| versions.as_ref().clone())
+        .unwrap_or_default();
+    versions.0.insert(destination.to_string(), version);
+
+    state.config_mut().set_extension(Arc::new(versions));
+}
+
+/// Validate an `Aqueduct` pipeline without executing its stages
+/// Sources are registered, the destinations are checked without creating them and the query of every stage is planned using `EXPLAIN`,
+/// no stage is materialized and nothing is written to the destinations
+#[instrument(skip(ctx, aqueduct), err)]
+pub async fn dry_run_pipeline(
+    ctx: Arc<SessionContext>,
+    aqueduct: Aqueduct,
+) -> Result<Arc<SessionContext>> {
+    info!("Validating Aqueduct ...");
+
+    let aqueduct = match aqueduct.catalog.clone() {
+        Some(catalog) => aqueduct.resolve_catalog_sources(&catalog)?,
+        None => aqueduct,
+    };
+
+    aqueduct.sources.iter().try_for_each(validate_source)?;
+    aqueduct
+        .all_destinations()
+        .try_for_each(validate_destination)?;
+    validate_stages(ctx.clone(), &aqueduct)?;
+    if let Some(execution) = &aqueduct.execution {
+        execution.apply(&ctx)?;
+    }
+    udfs::register_udfs(&ctx, &aqueduct.udfs).map_err(error::Error::UdfRegistration)?;
+
+    for destination in aqueduct.all_destinations() {
+        check_destination(ctx.clone(), destination)
+            .await
+            .inspect_err(|e| error!("Failed to check destination '{}': {e}", destination.name()))?;
+        info!("Checked destination {}", destination.name());
+    }
+
+    register_sources(ctx.clone(), &aqueduct).await?;
+
+    let mut failed_stages = vec![];
+    for (pos, parallel) in aqueduct.stages.iter().enumerate() {
+        for (sub, stage) in parallel.iter().enumerate() {
+            match plan_stage(ctx.clone(), stage).await {
+                Ok(plan) => info!("Planned stage {} #{pos}:{sub}\n{plan}", stage.name),
+                Err(e) => {
+                    error!("Failed to plan stage {} #{pos}:{sub}: {e}", stage.name);
+                    failed_stages.push(stage.name.clone());
+                }
+            }
+        }
+    }
+
+    if !failed_stages.is_empty() {
+        return Err(error::Error::UnplannableStages(failed_stages));
+    }
+
+    info!("Finished validating pipeline");
+
+    Ok(ctx)
+}
+
+// register all sources of the pipeline in parallel and apply their normalization profiles
+async fn register_sources(ctx: Arc<SessionContext>, aqueduct: &Aqueduct) -> Result<()> {
+    let mut normalizations = Vec::with_capacity(aqueduct.sources.len());
+    for source in aqueduct.sources.iter() {
+        let normalization = match source.normalization() {
+            Some(name) => {
+                let normalization = aqueduct.normalizations.get(name).ok_or_else(|| {
+                    error::Error::MissingNormalization(source.name().to_string(), name.to_string())
+                })?;
+
+                Some((name.to_string(), normalization.clone()))
+            }
+            None => None,
+        };
+
+        normalizations.push(normalization);
+    }
+
+    let permits = aqueduct
+        .execution
+        .as_ref()
+        .and_then(|execution| execution.max_concurrent_sources)
+        .unwrap_or(Semaphore::MAX_PERMITS);
+    let semaphore = Arc::new(Semaphore::new(permits));
+
+    let handles = aqueduct
+        .sources
+        .iter()
+        .zip(normalizations)
+        .enumerate()
+        .map(|(pos, (source, normalization))| {
+            let time = Instant::now();
+            let source_ = source.clone();
+            let ctx_ = ctx.clone();
+
+            let handle = spawn_limited(semaphore.clone(), async move {
+                let name = source_.name().to_string();
+                register_source(ctx_.clone(), source_).await?;
+
+                if let Some((normalization_name, normalization)) = normalization {
+                    debug!("Applying normalization '{normalization_name}' to source '{name}'");
+                    normalization::normalize_source(
+                        ctx_,
+                        name.as_str(),
+                        normalization_name.as_str(),
+                        &normalization,
+                    )
+                    .await?;
+                }
+
+                Ok(())
+            });
+
+            (pos, time, handle)
+        })
+        .collect::<Vec<(usize, Instant, JoinHandle<Result<()>>)>>();
+
+    for (pos, time, handle) in handles {
+        handle.await.expect("failed to join task")?;
+
+        info!(
+            "Registered source #{pos} ... Elapsed time: {:.2?}",
+            time.elapsed()
+        );
+    }
+
+    Ok(())
+}
+
+// spawn a task that only starts once it acquired a permit of the semaphore, the permit is released when the task completes
+fn spawn_limited<T: Send + 'static>(
+    semaphore: Arc<Semaphore>,
+    task: impl std::future::Future<Output = T> + Send + 'static,
+) -> JoinHandle<T> {
+    tokio::spawn(async move {
+        let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+
+        task.await
+    })
+}
+
+// url of the object store registered for the scheme and authority of a location
+pub(crate) fn object_store_url(
+    location: &Url,
+) -> datafusion::error::Result<datafusion::execution::object_store::ObjectStoreUrl> {
+    datafusion::execution::object_store::ObjectStoreUrl::parse(
+        &location[..url::Position::BeforePath],
+    )
+}
+
+// crate feature providing the object store for the scheme of the location
+fn location_feature(location: &Url) -> Option<&'static str> {
+    match location.scheme() {
+        "s3" | "s3a" => Some("s3"),
+        "gs" => Some("gcs"),
+        "az" | "adl" | "azure" | "abfs" | "abfss" => Some("azure"),
+        "http" | "https" => Some("http"),
+        _ => None,
+    }
+}
+
+// file types read by a file or directory source
+#[cfg(feature = "orc")]
+fn source_file_types(source: &Source) -> impl Iterator<Item = &FileType> {
+    let file_types = match source {
+        Source::File(file_source) => vec![&file_source.file_type],
+        Source::Directory(dir_source) => std::iter::once(&dir_source.file_type)
+            .chain(dir_source.additional_file_types.iter())
+            .collect(),
+        _ => vec![],
+    };
+
+    file_types.into_iter()
+}
+
+// check if a template name only consists of `[A-Za-z0-9_]`
+fn is_template_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// check if a secret name only consists of `[A-Za-z0-9_./-]`
+fn is_secret_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '/' | '-'))
+}
+
+// check if a location has empty path segments (e.g. `s3://bucket//file.csv`)
+fn has_empty_path_segments(location: &url::Url) -> bool {
+    location.path().contains("//")
+}
+
+// collapse consecutive slashes in a path
+fn normalize_path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+
+    path.chars().for_each(|c| {
+        if !(c == '/' && normalized.ends_with('/')) {
+            normalized.push(c);
+        }
+    });
+
+    normalized
+}
+
 // calculate time to live for a stage based on the position of the stage
 fn calculate_ttl<'a>(
     stage_ttls: &'a mut HashMap<String, usize>,
@@ -330,8 +1109,7 @@ fn calculate_ttl<'a>(
     stage_pos: usize,
     stages: &[Vec<Stage>],
 ) -> Result<()> {
-    let stage_name_r = format!("\\s{stage_name}(\\s|\\;|\\n|\\)|\\.|$)");
-    let regex = Regex::new(stage_name_r.as_str())?;
+    let regex = stage_reference_regex(stage_name)?;
 
     let ttl = stages
         .iter()
@@ -339,14 +1117,14 @@ fn calculate_ttl<'a>(
         .skip(stage_pos + 1)
         .flat_map(|(forward_pos, parallel)| parallel.iter().map(move |stage| (forward_pos, stage)))
         .filter_map(|(forward_pos, stage)| {
-            if regex.is_match(stage.query.as_str()) {
+            if references_stage(stage, stage_name, &regex) {
                 debug!("Registering TTL for {stage_name}. STAGE_POS={stage_pos} TTL={forward_pos}");
                 Some(forward_pos)
             } else {
                 None
             }
         })
-        .last()
+        .next_back()
         .unwrap_or(stage_pos + 1);
 
     stage_ttls
@@ -357,6 +1135,97 @@ fn calculate_ttl<'a>(
     Ok(())
 }
 
+// whether a stage references a prior stage
+// declared dependencies are merged with the tables referenced by the parsed query, otherwise the query text is scanned
+fn references_stage(stage: &Stage, stage_name: &str, regex: &Regex) -> bool {
+    let Some(depends_on) = &stage.depends_on else {
+        return regex.is_match(stage.query.as_str());
+    };
+
+    depends_on.iter().any(|name| name == stage_name)
+        || validate_stage_sql(stage)
+            .map(|tables| {
+                tables
+                    .iter()
+                    .any(|table| table.eq_ignore_ascii_case(stage_name))
+            })
+            .unwrap_or_else(|_| regex.is_match(stage.query.as_str()))
+}
+
+// regex matching references to a stage within a SQL query
+fn stage_reference_regex(stage_name: &str) -> Result<Regex> {
+    let stage_name_r = format!("\\s{stage_name}(\\s|\\;|\\n|\\)|\\.|$)");
+    let regex = Regex::new(stage_name_r.as_str())?;
+
+    Ok(regex)
+}
+
+// validate the SQL of all stages before anything is registered or executed
+// references to tables that are neither sources, prior stages nor registered in the context are logged as warnings
+fn validate_stages(ctx: Arc<SessionContext>, aqueduct: &Aqueduct) -> Result<()> {
+    let mut known_tables = aqueduct
+        .sources
+        .iter()
+        .map(|source| source.name().to_string())
+        .collect::<Vec<String>>();
+
+    for parallel in aqueduct.stages.iter() {
+        for stage in parallel.iter() {
+            if stage.repartition == Some(0) {
+                return Err(stages::error::Error::InvalidRepartition(stage.name.clone()).into());
+            }
+
+            for dependency in stage.depends_on.iter().flatten() {
+                if !known_tables.contains(dependency)
+                    && !ctx.table_exist(dependency.as_str()).unwrap_or_default()
+                {
+                    return Err(error::Error::UnknownStageDependency(
+                        stage.name.clone(),
+                        dependency.clone(),
+                    ));
+                }
+            }
+
+            for table in validate_stage_sql(stage)? {
+                let is_known = known_tables
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(table.as_str()));
+
+                if !is_known && !ctx.table_exist(table.as_str()).unwrap_or_default() {
+                    warn!(
+                        "Stage {} references table '{table}' which is neither a source nor a prior stage",
+                        stage.name
+                    );
+                }
+            }
+        }
+
+        known_tables.extend(parallel.iter().map(|stage| stage.name.clone()));
+    }
+
+    Ok(())
+}
+
+// abort the still running stages of a parallel group and deregister all stages that may have been registered
+async fn abort_stages(
+    ctx: Arc<SessionContext>,
+    handles: impl Iterator<Item = (String, JoinHandle<Result<bool>>)>,
+    stages: &[Vec<Stage>],
+) {
+    for (name, handle) in handles {
+        if !handle.is_finished() {
+            handle.abort();
+            warn!("Aborting stage {name}");
+        }
+
+        let _ = handle.await;
+    }
+
+    for stage in stages.iter().flatten() {
+        let _ = ctx.deregister_table(stage.name.as_str());
+    }
+}
+
 // deregister stages from context if the current position matches the ttl of the stages
 fn deregister_stages(
     ctx: Arc<SessionContext>,
@@ -374,3 +1243,1788 @@ fn deregister_stages(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline_definition(allow_empty_path_segments: bool) -> String {
+        format!(
+            r#"
+allow_empty_path_segments: {allow_empty_path_segments}
+sources:
+  - type: File
+    name: source
+    file_type:
+      type: Csv
+      options: {{}}
+    location: s3://bucket/${{prefix}}/file.csv
+stages: []
+"#
+        )
+    }
+
+    fn source_location(aqueduct: &Aqueduct) -> String {
+        match aqueduct.sources.first() {
+            Some(Source::File(file_source)) => file_source.location.to_string(),
+            _ => panic!("expected file source"),
+        }
+    }
+
+    fn conditional_stage(name: &str, query: &str, when: &str) -> Stage {
+        let mut stage = Stage::new(name.into(), query.into(), None, false, false, false);
+        stage.when = Some(when.into());
+        stage
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_row_count_ok() {
+        let ctx = Arc::new(SessionContext::new());
+        let stage = Stage::new(
+            "stage_1".into(),
+            "SELECT * FROM (VALUES (1), (2), (3)) AS t(a)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+
+        let row_count = process_stage(ctx, stage).await.unwrap();
+
+        assert_eq!(Some(3), row_count);
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_repartition_ok() {
+        let ctx = Arc::new(SessionContext::new());
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT * FROM (VALUES (1), (2), (3)) AS t(a)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.repartition = Some(4);
+
+        process_stage(ctx.clone(), stage).await.unwrap();
+        let partitions = ctx
+            .table("stage_1")
+            .await
+            .unwrap()
+            .collect_partitioned()
+            .await
+            .unwrap();
+
+        assert_eq!(4, partitions.len());
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_repartition_zero_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT 1 AS a".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.repartition = Some(0);
+
+        let result = process_stage(ctx, stage).await;
+
+        assert!(matches!(
+            result,
+            Err(stages::error::Error::InvalidRepartition(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_not_materialized_ok() {
+        let ctx = Arc::new(SessionContext::new());
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT * FROM (VALUES (1), (2), (3)) AS t(a)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.materialize = Some(false);
+
+        let row_count = process_stage(ctx.clone(), stage).await.unwrap();
+        let table = ctx.table_provider("stage_1").await.unwrap();
+
+        assert_eq!(None, row_count);
+        assert_eq!(datafusion::datasource::TableType::View, table.table_type());
+        assert_eq!(
+            3,
+            ctx.table("stage_1").await.unwrap().count().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_assertions_ok() {
+        let ctx = Arc::new(SessionContext::new());
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT * FROM (VALUES (1, 'a'), (2, 'b'), (3, 'c')) AS t(id, name)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        let mut assertions = Assertions::new();
+        assertions.expressions = vec!["id IS NOT NULL".into(), "id BETWEEN 1 AND 3".into()];
+        assertions.min_rows = Some(1);
+        assertions.max_rows = Some(3);
+        stage.assertions = Some(assertions);
+
+        let row_count = process_stage(ctx, stage).await.unwrap();
+
+        assert_eq!(Some(3), row_count);
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_assertion_failed_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT * FROM (VALUES (1), (NULL), (NULL)) AS t(id)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        let mut assertions = Assertions::new();
+        assertions.expressions = vec!["id IS NOT NULL".into()];
+        stage.assertions = Some(assertions);
+
+        let result = process_stage(ctx.clone(), stage).await;
+
+        match result {
+            Err(stages::error::Error::AssertionFailed(stage, assertion, failed_rows)) => {
+                assert_eq!("stage_1", stage);
+                assert_eq!("id IS NOT NULL", assertion);
+                assert_eq!(2, failed_rows);
+            }
+            other => panic!("expected AssertionFailed, got {other:?}"),
+        }
+        assert!(ctx.table("stage_1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_stage_row_count_assertion_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT * FROM (VALUES (1), (2), (3)) AS t(id)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.materialize = Some(false);
+        let mut assertions = Assertions::new();
+        assertions.max_rows = Some(2);
+        stage.assertions = Some(assertions);
+
+        let result = process_stage(ctx, stage).await;
+
+        assert!(matches!(
+            result,
+            Err(stages::error::Error::RowCountAssertion(_, 3, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_format_batches_ok() {
+        let ctx = SessionContext::new();
+        let batches = ctx
+            .sql("SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS t(id, name)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let json = format_batches(batches.as_slice(), OutputFormat::Json).unwrap();
+        let csv = format_batches(batches.as_slice(), OutputFormat::Csv).unwrap();
+
+        assert_eq!(
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"b\"}\n",
+            json
+        );
+        assert_eq!("id,name\n1,a\n2,b\n", csv);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_pipeline_ok() {
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .stage(Stage::new(
+                "stage_2".into(),
+                "SELECT a + 1 AS b FROM stage_1".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+
+        let ctx = dry_run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        assert!(ctx.table_exist("stage_2").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_pipeline_delta_not_created_ok() {
+        use deltalake::kernel::{DataType, PrimitiveType, StructField};
+
+        let run_id = rand::random::<usize>();
+        let output_dir = Path::new(".")
+            .canonicalize()
+            .unwrap()
+            .join(format!("tests/output/dry_run/{run_id}"));
+        let mut destination = delta::DeltaDestination::new(
+            "readings".into(),
+            Url::from_directory_path(&output_dir).unwrap(),
+            HashMap::new(),
+            HashMap::new(),
+            delta::WriteMode::Append,
+            vec![],
+            vec![StructField::new(
+                "id",
+                DataType::Primitive(PrimitiveType::Integer),
+                false,
+            )],
+        );
+        destination.precheck_write = true;
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT CAST(1 AS INT) AS id".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::Delta(destination))
+            .build();
+
+        dry_run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        assert!(!output_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_pipeline_unplannable_stages_err() {
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT missing_column FROM (VALUES (1)) AS t(a)".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .stage(Stage::new(
+                "stage_2".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+
+        let result = dry_run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        match result.err() {
+            Some(error::Error::UnplannableStages(stages)) => {
+                assert_eq!(vec!["stage_1".to_string()], stages)
+            }
+            other => panic!("expected UnplannableStages, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_condition_true_ok() {
+        let aqueduct = Aqueduct::builder()
+            .stage(conditional_stage("stage_1", "SELECT 1 AS a", "1 = 1"))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        assert!(ctx.table_exist("stage_1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_udfs_ok() {
+        let stage = Stage::new(
+            "stage_1".into(),
+            "SELECT fnv1a_64('a') AS hash".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        let mut aqueduct = Aqueduct::builder().stage(stage).build();
+        aqueduct.udfs = vec![udfs::UdfBundle::Hash];
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+        let result = ctx.table("stage_1").await.unwrap().collect().await.unwrap();
+
+        datafusion::assert_batches_eq!(
+            [
+                "+----------------------+",
+                "| hash                 |",
+                "+----------------------+",
+                "| 12638187200555641996 |",
+                "+----------------------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_condition_false_skipped_ok() {
+        let aqueduct = Aqueduct::builder()
+            .stage(conditional_stage("stage_1", "SELECT 1 AS a", "1 = 0"))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        assert!(!ctx.table_exist("stage_1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_skipped_stage_reference_err() {
+        let aqueduct = Aqueduct::builder()
+            .stage(conditional_stage("stage_1", "SELECT 1 AS a", "1 = 0"))
+            .stage(Stage::new(
+                "stage_2".into(),
+                "SELECT a FROM stage_1".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::SkippedStageReference(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_explode_ok() {
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT 1 AS id, make_array(1, 2, 3) AS ids".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.explode = Some(Explode::new("ids".into(), Some("value".into())));
+
+        let aqueduct = Aqueduct::builder()
+            .stage(stage)
+            .destination(Destination::InMemory(InMemoryDestination::new(
+                "result".into(),
+            )))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+        let batches = ctx
+            .sql("SELECT id, value FROM result ORDER BY value")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        datafusion::assert_batches_eq!(
+            [
+                "+----+-------+",
+                "| id | value |",
+                "+----+-------+",
+                "| 1  | 1     |",
+                "| 1  | 2     |",
+                "| 1  | 3     |",
+                "+----+-------+",
+            ],
+            batches.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_explode_invalid_column_err() {
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT 1 AS id".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.explode = Some(Explode::new("id".into(), None));
+
+        let aqueduct = Aqueduct::builder().stage(stage).build();
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::StageError(
+                stages::error::Error::InvalidExplodeColumn(_, _)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_ingestion_timestamp_ok() {
+        let mut destination = InMemoryDestination::new("result".into());
+        destination.ingestion_timestamp_column = Some("ingested_at".into());
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a UNION ALL SELECT 2 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(destination))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+        let batches = ctx
+            .sql("SELECT count(*) AS count FROM result WHERE ingested_at IS NOT NULL")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        datafusion::assert_batches_eq!(
+            [
+                "+-------+",
+                "| count |",
+                "+-------+",
+                "| 2     |",
+                "+-------+",
+            ],
+            batches.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_ingestion_timestamp_exists_err() {
+        let mut destination = InMemoryDestination::new("result".into());
+        destination.ingestion_timestamp_column = Some("a".into());
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(destination))
+            .build();
+
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::DestinationError(
+                destinations::error::Error::IngestionTimestampColumnExists(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_committed_versions_ok() {
+        use deltalake::kernel::{DataType, PrimitiveType, StructField};
+
+        let run_id = rand::random::<usize>();
+        let location = Url::from_directory_path(
+            Path::new(".")
+                .canonicalize()
+                .unwrap()
+                .join(format!("tests/output/committed_versions/{run_id}")),
+        )
+        .unwrap();
+        let destination = delta::DeltaDestination::new(
+            "readings".into(),
+            location,
+            HashMap::new(),
+            HashMap::new(),
+            delta::WriteMode::Append,
+            vec![],
+            vec![StructField::new(
+                "id",
+                DataType::Primitive(PrimitiveType::Integer),
+                false,
+            )],
+        );
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT CAST(1 AS INT) AS id".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::Delta(destination))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct.clone())
+            .await
+            .unwrap();
+        let first = committed_versions(&ctx)["readings"];
+
+        let ctx = run_pipeline(ctx, aqueduct).await.unwrap();
+        let second = committed_versions(&ctx)["readings"];
+
+        assert_eq!(first + 1, second);
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_precheck_write_ok() {
+        let output_dir = Path::new(".")
+            .canonicalize()
+            .unwrap()
+            .join("tests/output/precheck_write");
+        let location = Url::from_file_path(output_dir.join("output.csv")).unwrap();
+
+        let mut destination = file::FileDestination::new(
+            "output".into(),
+            location,
+            file::FileType::Csv(file::CsvDestinationOptions::default()),
+            true,
+            vec![],
+            HashMap::new(),
+        );
+        destination.precheck_write = true;
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::File(destination))
+            .build();
+
+        run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        let markers = std::fs::read_dir(output_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(".aqueducts_write_check")
+            })
+            .count();
+        assert_eq!(0, markers);
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_http_destination_err() {
+        let destination = file::FileDestination::new(
+            "output".into(),
+            Url::parse("https://example.com/output.parquet").unwrap(),
+            file::FileType::Parquet(HashMap::new()),
+            true,
+            vec![],
+            HashMap::new(),
+        );
+        let aqueduct = Aqueduct::builder()
+            .destination(Destination::File(destination))
+            .build();
+
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::DestinationError(
+                destinations::error::Error::ReadOnlyLocation(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_validate_stage_sql_ok() {
+        let stage = Stage::new(
+            "stage_1".into(),
+            "WITH filtered AS (SELECT * FROM readings WHERE value > 0) SELECT * FROM filtered JOIN locations USING (id)".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+
+        let mut tables = validate_stage_sql(&stage).unwrap();
+        tables.sort();
+
+        assert_eq!(vec!["locations", "readings"], tables);
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_ddl_stage_err() {
+        let aqueduct = Aqueduct::builder()
+            .source(Source::InMemory(InMemorySource {
+                name: "readings".into(),
+                normalization: None,
+            }))
+            .stage(Stage::new(
+                "stage_1".into(),
+                "CREATE TABLE copy AS SELECT * FROM readings".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+
+        // the in-memory source doesn't exist, so the stage has to be rejected before sources are registered
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::StageError(stages::error::Error::Sql(name, _))) if name == "stage_1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_persisted_in_memory_ok() {
+        let mut destination = InMemoryDestination::new("orders".into());
+        destination.overwrite = true;
+        destination.persist = true;
+
+        let first = Aqueduct::builder()
+            .stage(Stage::new(
+                "orders".into(),
+                "SELECT * FROM (VALUES (1, 10), (2, 20)) AS t(id, amount)".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(destination))
+            .build();
+
+        let second = Aqueduct::builder()
+            .source(Source::InMemory(InMemorySource {
+                name: "orders".into(),
+                normalization: None,
+            }))
+            .stage(Stage::new(
+                "total".into(),
+                "SELECT sum(amount) AS total FROM orders".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(InMemoryDestination::new(
+                "result".into(),
+            )))
+            .build();
+
+        let ctx = Arc::new(SessionContext::new());
+        let ctx = run_pipeline(ctx, first).await.unwrap();
+        let ctx = run_pipeline(ctx, second).await.unwrap();
+
+        let result = ctx.table("result").await.unwrap().collect().await.unwrap();
+
+        datafusion::assert_batches_eq!(
+            [
+                "+-------+",
+                "| total |",
+                "+-------+",
+                "| 30    |",
+                "+-------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register_in_memory_source_missing_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let schema = Arc::new(datafusion::arrow::datatypes::Schema::empty());
+        ctx.register_batch(
+            "orders",
+            datafusion::arrow::array::RecordBatch::new_empty(schema),
+        )
+        .unwrap();
+
+        let result = register_source(
+            ctx,
+            Source::InMemory(InMemorySource {
+                name: "order".into(),
+                normalization: None,
+            }),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(sources::error::Error::MissingInMemory(name, tables))
+                if name == "order" && tables == vec!["orders".to_string()]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_stage_timeout_err() {
+        let mut slow = Stage::new(
+            "slow".into(),
+            "SELECT sum(a.value * b.value) FROM generate_series(1, 1000000) a CROSS JOIN generate_series(1, 1000000) b CROSS JOIN fast".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        slow.timeout_seconds = Some(1);
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "fast".into(),
+                "SELECT 1 AS c".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .stage(slow)
+            .build();
+
+        // with multiple partitions the execution yields to the runtime which lets the timeout fire on a single cpu
+        let config = datafusion::prelude::SessionConfig::new().with_target_partitions(4);
+        let ctx = Arc::new(SessionContext::new_with_config(config));
+        let time = Instant::now();
+        let result = run_pipeline(ctx.clone(), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::Timeout { stage, seconds: 1 }) if stage == "slow"
+        ));
+        assert!(time.elapsed() < Duration::from_secs(30));
+        assert!(!ctx.table_exist("fast").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_max_output_rows_err() {
+        let mut destination = InMemoryDestination::new("result".into());
+        destination.max_output_rows = Some(10);
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT * FROM generate_series(1, 100)".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(destination))
+            .build();
+
+        let ctx = Arc::new(SessionContext::new());
+        let result = run_pipeline(ctx.clone(), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::DestinationError(
+                destinations::error::Error::MaxOutputRowsExceeded(10)
+            ))
+        ));
+        assert!(!ctx.table_exist("result").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_max_output_rows_ok() {
+        let mut destination = InMemoryDestination::new("result".into());
+        destination.max_output_rows = Some(100);
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT * FROM generate_series(1, 100)".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(destination))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            100,
+            ctx.table("result").await.unwrap().count().await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_shared_normalization_ok() {
+        let ctx = Arc::new(SessionContext::new());
+        for (table, id) in [("raw_a", "1"), ("raw_b", "2")] {
+            let df = ctx
+                .sql(format!("SELECT '{id}' AS \"ID\", 'name_{id}' AS \"Full Name\"").as_str())
+                .await
+                .unwrap();
+            ctx.register_table(table, df.into_view()).unwrap();
+        }
+
+        let normalization = normalization::Normalization::new(
+            HashMap::from([
+                ("ID".to_string(), "id".to_string()),
+                ("Full Name".to_string(), "name".to_string()),
+            ]),
+            HashMap::from([(
+                "ID".to_string(),
+                datafusion::arrow::datatypes::DataType::Int64,
+            )]),
+            None,
+        );
+        let source = |name: &str| {
+            Source::InMemory(InMemorySource {
+                name: name.into(),
+                normalization: Some("cleanup".into()),
+            })
+        };
+
+        let mut aqueduct = Aqueduct::builder()
+            .source(source("raw_a"))
+            .source(source("raw_b"))
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT id, name FROM raw_a UNION ALL SELECT id, name FROM raw_b".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(InMemoryDestination::new(
+                "result".into(),
+            )))
+            .build();
+        aqueduct
+            .normalizations
+            .insert("cleanup".into(), normalization);
+
+        let ctx = run_pipeline(ctx, aqueduct).await.unwrap();
+        let batches = ctx
+            .sql("SELECT id, name, arrow_typeof(id) AS id_type FROM result ORDER BY id")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        datafusion::assert_batches_eq!(
+            [
+                "+----+--------+---------+",
+                "| id | name   | id_type |",
+                "+----+--------+---------+",
+                "| 1  | name_1 | Int64   |",
+                "| 2  | name_2 | Int64   |",
+                "+----+--------+---------+",
+            ],
+            batches.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_missing_normalization_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let df = ctx.sql("SELECT 1 AS a").await.unwrap();
+        ctx.register_table("raw", df.into_view()).unwrap();
+
+        let aqueduct = Aqueduct::builder()
+            .source(Source::InMemory(InMemorySource {
+                name: "raw".into(),
+                normalization: Some("missing".into()),
+            }))
+            .build();
+
+        let result = run_pipeline(ctx, aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::MissingNormalization(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_in_memory_overwrite_ok() {
+        let mut destination = InMemoryDestination::new("result".into());
+        destination.overwrite = true;
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(destination))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct.clone())
+            .await
+            .unwrap();
+        let ctx = run_pipeline(ctx, aqueduct).await.unwrap();
+
+        let count = ctx.table("result").await.unwrap().count().await.unwrap();
+        assert_eq!(1, count);
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_in_memory_exists_err() {
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(InMemoryDestination::new(
+                "result".into(),
+            )))
+            .build();
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct.clone())
+            .await
+            .unwrap();
+        let result = run_pipeline(ctx, aqueduct).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_multiple_destinations_ok() {
+        let location = Url::from_file_path(
+            Path::new(".")
+                .canonicalize()
+                .unwrap()
+                .join("tests/output/multiple_destinations/output.parquet"),
+        )
+        .unwrap();
+
+        let mut aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS t(id, name)".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+        aqueduct.destinations = vec![
+            Destination::File(file::FileDestination::new(
+                "output".into(),
+                location.clone(),
+                file::FileType::Parquet(HashMap::default()),
+                true,
+                vec![],
+                HashMap::new(),
+            )),
+            Destination::InMemory(InMemoryDestination::new("result".into())),
+        ];
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        let in_memory = ctx.table("result").await.unwrap().count().await.unwrap();
+        let parquet = ctx
+            .read_parquet(
+                location.as_str(),
+                datafusion::prelude::ParquetReadOptions::default(),
+            )
+            .await
+            .unwrap()
+            .count()
+            .await
+            .unwrap();
+        assert_eq!(2, in_memory);
+        assert_eq!(2, parquet);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_run_pipeline_resume_from_checkpoint_ok() {
+        let checkpoint_dir = Path::new(".")
+            .canonicalize()
+            .unwrap()
+            .join("tests/output/checkpoint");
+        let _ = std::fs::remove_dir_all(&checkpoint_dir);
+
+        let stage = |name: &str, query: &str| {
+            vec![Stage::new(
+                name.into(),
+                query.into(),
+                None,
+                false,
+                false,
+                false,
+            )]
+        };
+        let marker = |stage: &str| checkpoint_dir.join(stage).join(checkpoint::SUCCESS_MARKER);
+
+        let mut aqueduct = Aqueduct::new(
+            vec![],
+            vec![
+                stage("stage_1", "SELECT 1 AS a"),
+                stage("stage_2", "SELECT a + 1 AS b FROM stage_1"),
+                stage("stage_3", "SELECT b FROM missing_table"),
+            ],
+            None,
+        );
+        aqueduct.checkpoint_dir = Some(Url::from_directory_path(&checkpoint_dir).unwrap());
+
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct.clone()).await;
+        assert!(result.is_err());
+        assert!(marker("stage_1").exists());
+        assert!(marker("stage_2").exists());
+
+        // the changed stage_2 has to be executed again while stage_1 is loaded from its checkpoint
+        aqueduct.stages[1] = stage("stage_2", "SELECT a + 2 AS b FROM stage_1");
+        aqueduct.stages[2] = stage("stage_3", "SELECT b FROM stage_2");
+        aqueduct.destinations = vec![Destination::InMemory(InMemoryDestination::new(
+            "result".into(),
+        ))];
+        aqueduct.resume = true;
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+        let result = ctx.table("result").await.unwrap().collect().await.unwrap();
+
+        datafusion::assert_batches_eq!(
+            &["+---+", "| b |", "+---+", "| 3 |", "+---+"],
+            result.as_slice()
+        );
+        assert!(logs_contain("Loaded stage stage_1"));
+        assert!(!logs_contain("Loaded stage stage_2"));
+        assert!(logs_contain("Ignoring checkpoint of stage stage_2"));
+
+        // the checkpoints are deleted after the pipeline completed
+        for stage in ["stage_1", "stage_2", "stage_3"] {
+            assert!(!marker(stage).exists());
+        }
+    }
+
+    // `orders` is also used as a column alias by the last stage which the query scan mistakes for a reference
+    fn dependent_stages(declare_dependencies: bool) -> Vec<Vec<Stage>> {
+        let stage = |name: &str, query: &str, depends_on: &[&str]| {
+            let mut stage = Stage::new(name.into(), query.into(), None, false, false, false);
+            stage.depends_on = declare_dependencies
+                .then(|| depends_on.iter().map(|name| name.to_string()).collect());
+            vec![stage]
+        };
+
+        vec![
+            stage("orders", "SELECT 1 AS total", &[]),
+            stage("orders_daily", "SELECT total FROM orders", &["orders"]),
+            stage(
+                "report",
+                "SELECT total AS orders FROM orders_daily",
+                &["orders_daily"],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_calculate_ttl_depends_on_ok() {
+        let stages = dependent_stages(true);
+        let mut stage_ttls = HashMap::new();
+
+        calculate_ttl(&mut stage_ttls, "orders", 0, &stages).unwrap();
+        calculate_ttl(&mut stage_ttls, "orders_daily", 1, &stages).unwrap();
+
+        assert_eq!(Some(&1), stage_ttls.get("orders"));
+        assert_eq!(Some(&2), stage_ttls.get("orders_daily"));
+    }
+
+    #[test]
+    fn test_calculate_ttl_depends_on_merges_query_references_ok() {
+        let mut stages = dependent_stages(true);
+        stages[2][0].depends_on = Some(vec![]);
+        let mut stage_ttls = HashMap::new();
+
+        calculate_ttl(&mut stage_ttls, "orders", 0, &stages).unwrap();
+        calculate_ttl(&mut stage_ttls, "orders_daily", 1, &stages).unwrap();
+
+        assert_eq!(Some(&1), stage_ttls.get("orders"));
+        assert_eq!(Some(&2), stage_ttls.get("orders_daily"));
+    }
+
+    #[test]
+    fn test_calculate_ttl_query_scan_ok() {
+        let stages = dependent_stages(false);
+        let mut stage_ttls = HashMap::new();
+
+        calculate_ttl(&mut stage_ttls, "orders", 0, &stages).unwrap();
+
+        assert_eq!(Some(&2), stage_ttls.get("orders"));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_depends_on_ok() {
+        let mut aqueduct = Aqueduct::new(vec![], dependent_stages(true), None);
+        aqueduct.destinations = vec![Destination::InMemory(InMemoryDestination::new(
+            "result".into(),
+        ))];
+
+        let ctx = run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+        let result = ctx.table("result").await.unwrap().collect().await.unwrap();
+
+        datafusion::assert_batches_eq!(
+            &[
+                "+--------+",
+                "| orders |",
+                "+--------+",
+                "| 1      |",
+                "+--------+"
+            ],
+            result.as_slice()
+        );
+        assert!(!ctx.table_exist("orders").unwrap());
+    }
+
+    #[test]
+    fn test_validate_stages_unknown_dependency_err() {
+        let mut stages = dependent_stages(true);
+        stages.swap(0, 1);
+        let aqueduct = Aqueduct::new(vec![], stages, None);
+
+        let result = validate_stages(Arc::new(SessionContext::new()), &aqueduct);
+
+        assert!(matches!(
+            result,
+            Err(error::Error::UnknownStageDependency(stage, dependency))
+                if stage == "orders_daily" && dependency == "orders"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_pipeline_repartition_zero_err() {
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT 1 AS a".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.repartition = Some(0);
+        let aqueduct = Aqueduct::new(vec![], vec![vec![stage]], None);
+
+        let result = dry_run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::StageError(
+                stages::error::Error::InvalidRepartition(stage)
+            )) if stage == "stage_1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_limited_ok() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let semaphore = Arc::new(Semaphore::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let handles = (0..6)
+            .map(|_| {
+                let running = running.clone();
+                let max_running = max_running.clone();
+
+                spawn_limited(semaphore.clone(), async move {
+                    let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(2, max_running.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_execution_options_ok() {
+        use datafusion::physical_plan::ExecutionPlanProperties;
+
+        let mut aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT a, count(*) AS n FROM (VALUES (1), (2), (2)) AS t(a) GROUP BY a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+        let mut execution = execution::ExecutionOptions::new();
+        execution.target_partitions = Some(1);
+        execution.batch_size = Some(1024);
+        aqueduct.execution = Some(execution);
+
+        let config = datafusion::prelude::SessionConfig::new().with_target_partitions(4);
+        let ctx = run_pipeline(Arc::new(SessionContext::new_with_config(config)), aqueduct)
+            .await
+            .unwrap();
+
+        let plan = ctx
+            .table("stage_1")
+            .await
+            .unwrap()
+            .create_physical_plan()
+            .await
+            .unwrap();
+        assert_eq!(1, ctx.state().config().target_partitions());
+        assert_eq!(1024, ctx.state().config().batch_size());
+        assert_eq!(1, plan.output_partitioning().partition_count());
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_run_pipeline_stage_metadata_ok() {
+        let mut stage = Stage::new(
+            "stage_1".into(),
+            "SELECT 1 AS a".into(),
+            None,
+            false,
+            false,
+            false,
+        );
+        stage.metadata = HashMap::from([
+            ("owner".to_string(), "analytics".to_string()),
+            ("catalog_id".to_string(), "42".to_string()),
+        ]);
+        let aqueduct = Aqueduct::builder().stage(stage).build();
+
+        run_pipeline(Arc::new(SessionContext::new()), aqueduct)
+            .await
+            .unwrap();
+
+        let metadata = r#"metadata={"catalog_id": "42", "owner": "analytics"}"#;
+        logs_assert(|lines: &[&str]| {
+            for event in ["Running stage stage_1", "Finished processing stage stage_1"] {
+                if !lines
+                    .iter()
+                    .any(|line| line.contains(event) && line.contains(metadata))
+                {
+                    return Err(format!("no event '{event}' with {metadata}"));
+                }
+            }
+            Ok(())
+        });
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_cancelled_between_stages_err() {
+        use datafusion::{
+            arrow::datatypes::DataType,
+            logical_expr::{create_udf, ColumnarValue, Volatility},
+        };
+
+        let cancellation = CancellationToken::new();
+        let cancel = cancellation.clone();
+
+        // cancels the pipeline while the first stage is executed
+        let ctx = Arc::new(SessionContext::new());
+        ctx.register_udf(create_udf(
+            "cancel_pipeline",
+            vec![DataType::Int64],
+            DataType::Int64,
+            Volatility::Volatile,
+            Arc::new(move |args: &[ColumnarValue]| {
+                cancel.cancel();
+                Ok(args[0].clone())
+            }),
+        ));
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT cancel_pipeline(1) AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .stage(Stage::new(
+                "stage_2".into(),
+                "SELECT a + 1 AS b FROM stage_1".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .destination(Destination::InMemory(InMemoryDestination::new(
+                "output".into(),
+            )))
+            .build();
+
+        let result = run_pipeline_with_cancellation(ctx.clone(), aqueduct, cancellation).await;
+
+        assert!(matches!(result, Err(error::Error::Cancelled { .. })));
+        assert!(ctx.table("stage_2").await.is_err());
+        assert!(ctx.table("output").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_cancelled_before_stages_err() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let aqueduct = Aqueduct::builder()
+            .stage(Stage::new(
+                "stage_1".into(),
+                "SELECT 1 AS a".into(),
+                None,
+                false,
+                false,
+                false,
+            ))
+            .build();
+
+        let ctx = Arc::new(SessionContext::new());
+        let result = run_pipeline_with_cancellation(ctx.clone(), aqueduct, cancellation).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::Cancelled { stage: None })
+        ));
+        assert!(!ctx.table_exist("stage_1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_run_pipeline_execution_options_err() {
+        let mut aqueduct = Aqueduct::builder().build();
+        let mut execution = execution::ExecutionOptions::new();
+        execution.batch_size = Some(0);
+        aqueduct.execution = Some(execution);
+
+        let result = run_pipeline(Arc::new(SessionContext::new()), aqueduct).await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::InvalidExecutionOption(option)) if option == "batch_size"
+        ));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_stage_metadata_round_trip_ok() {
+        let definition = r#"
+sources: []
+stages:
+  - - name: stage_1
+      query: SELECT 1
+      metadata:
+        owner: analytics
+        catalog_id: "42"
+"#;
+        let aqueduct = Aqueduct::try_from_yml_str(definition, HashMap::new()).unwrap();
+        let serialized = serde_yml::to_string(&aqueduct).unwrap();
+        let aqueduct = Aqueduct::try_from_yml_str(serialized.as_str(), HashMap::new()).unwrap();
+
+        assert_eq!(
+            HashMap::from([
+                ("owner".to_string(), "analytics".to_string()),
+                ("catalog_id".to_string(), "42".to_string()),
+            ]),
+            aqueduct.stages[0][0].metadata
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_max_commit_retries_err() {
+        let definition = r#"
+sources: []
+stages: []
+destination:
+  type: Delta
+  name: destination
+  location: file:///tmp/table
+  storage_options: {}
+  table_properties: {}
+  write_mode:
+    operation: Append
+  partition_cols: []
+  schema: []
+  max_commit_retries: 1000
+"#;
+        let result = Aqueduct::try_from_yml_str(definition, HashMap::new());
+
+        assert!(matches!(
+            result,
+            Err(error::Error::DestinationError(
+                destinations::error::Error::InvalidCommitRetries(_, _)
+            ))
+        ));
+    }
+
+    #[cfg(all(feature = "yaml", feature = "odbc"))]
+    #[test]
+    fn test_odbc_create_if_missing_custom_err() {
+        let definition = r#"
+sources: []
+stages: []
+destination:
+  type: Odbc
+  name: destination
+  connection_string: Driver={PostgreSQL Unicode};Server=localhost
+  write_mode:
+    operation: Custom
+    transaction:
+      insert: INSERT INTO destination VALUES (?)
+  batch_size: 100
+  create_if_missing: true
+"#;
+        let result = Aqueduct::try_from_yml_str(definition, HashMap::new());
+
+        assert!(matches!(
+            result,
+            Err(error::Error::DestinationError(
+                destinations::error::Error::UnsupportedCreateIfMissing(_)
+            ))
+        ));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_required_features_local_ok() {
+        let definition = r#"
+sources:
+  - type: InMemory
+    name: source
+  - type: File
+    name: file_source
+    file_type:
+      type: Csv
+      options: {}
+    location: ./tests/data/mixed_formats/part_1.csv
+stages: []
+"#;
+        let aqueduct = Aqueduct::try_from_yml_str(definition, HashMap::new()).unwrap();
+
+        assert!(aqueduct.required_features().is_empty());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_required_features_object_stores_ok() {
+        let definition = r#"
+sources:
+  - type: File
+    name: source_1
+    file_type:
+      type: Csv
+      options: {}
+    location: s3://bucket/file.csv
+  - type: Delta
+    name: source_2
+    location: s3a://bucket/table
+  - type: Directory
+    name: source_3
+    file_type:
+      type: Parquet
+      options: {}
+    location: gs://bucket/prefix/
+stages: []
+destination:
+  type: Delta
+  name: destination
+  location: abfss://container@account.dfs.core.windows.net/table
+  storage_options: {}
+  table_properties: {}
+  write_mode:
+    operation: Append
+  partition_cols: []
+  schema: []
+"#;
+        let aqueduct = Aqueduct::try_from_yml_str(definition, HashMap::new()).unwrap();
+
+        assert_eq!(vec!["azure", "gcs", "s3"], aqueduct.required_features());
+    }
+
+    #[cfg(all(feature = "yaml", feature = "odbc"))]
+    #[test]
+    fn test_required_features_odbc_ok() {
+        let definition = r#"
+sources:
+  - type: Odbc
+    name: source
+    query: SELECT * FROM table
+    connection_string: Driver={PostgreSQL Unicode};Server=localhost
+stages: []
+destination:
+  type: File
+  name: destination
+  file_type:
+    type: Parquet
+    options: {}
+  location: s3://bucket/output.parquet
+  single_file: true
+  partition_cols: []
+"#;
+        let aqueduct = Aqueduct::try_from_yml_str(definition, HashMap::new()).unwrap();
+
+        assert_eq!(vec!["odbc", "s3"], aqueduct.required_features());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_substitute_location_param_ok() {
+        let params = HashMap::from_iter(vec![("prefix".into(), "some/prefix".into())]);
+        let aqueduct =
+            Aqueduct::try_from_yml_str(pipeline_definition(false).as_str(), params).unwrap();
+
+        assert_eq!(
+            "s3://bucket/some/prefix/file.csv",
+            source_location(&aqueduct)
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_substitute_empty_location_param_err() {
+        let params = HashMap::from_iter(vec![("prefix".into(), "".into())]);
+        let result = Aqueduct::try_from_yml_str(pipeline_definition(false).as_str(), params);
+
+        assert!(matches!(result, Err(error::Error::EmptyPathSegment(_))));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_substitute_empty_location_param_allowed_ok() {
+        let params = HashMap::from_iter(vec![("prefix".into(), "".into())]);
+        let aqueduct =
+            Aqueduct::try_from_yml_str(pipeline_definition(true).as_str(), params).unwrap();
+
+        assert_eq!("s3://bucket/file.csv", source_location(&aqueduct));
+    }
+
+    // write a definition to a file in the temp dir, the name is suffixed to avoid collisions between tests
+    fn definition_file(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{name}_{}", rand::random::<u32>()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_try_from_file_without_extension_ok() {
+        let path = definition_file("aqueduct", pipeline_definition(false).as_str());
+        let params = HashMap::from_iter(vec![("prefix".into(), "some/prefix".into())]);
+
+        let aqueduct = Aqueduct::try_from_file(path.as_path(), params).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            "s3://bucket/some/prefix/file.csv",
+            source_location(&aqueduct)
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_try_from_file_unrecognized_format_err() {
+        let path = definition_file("aqueduct.conf", "sources = [");
+
+        let result = Aqueduct::try_from_file(path.as_path(), HashMap::new());
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(error::Error::UnrecognizedFormat(_, failures)) if failures[0].starts_with("yaml: ")
+        ));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_try_from_file_missing_params_err() {
+        let path = definition_file("aqueduct", pipeline_definition(false).as_str());
+
+        let result = Aqueduct::try_from_file(path.as_path(), HashMap::new());
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(result, Err(error::Error::MissingParams(_))));
+    }
+
+    #[test]
+    fn test_substitute_default_params_ok() {
+        let params = HashMap::from_iter(vec![
+            ("region".into(), "eu-central-1".into()),
+            ("endpoint".into(), "http://localhost:-4566}".into()),
+        ]);
+        let definition = Aqueduct::substitute_params(
+            "${region:-us-east-1} ${prefix:-data/raw files} ${empty:-} ${endpoint}",
+            params,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "eu-central-1 data/raw files  http://localhost:-4566}",
+            definition
+        );
+    }
+
+    #[test]
+    fn test_substitute_env_params_ok() {
+        std::env::set_var("AQUEDUCTS_TEST_ENV_BUCKET", "env-bucket");
+        std::env::set_var("AQUEDUCTS_TEST_ENV_PREFIX", "env/prefix");
+
+        let params = HashMap::from_iter(vec![(
+            "env:AQUEDUCTS_TEST_ENV_PREFIX".into(),
+            "param/prefix".into(),
+        )]);
+        let definition = Aqueduct::substitute_params(
+            "s3://${env:AQUEDUCTS_TEST_ENV_BUCKET}/${env:AQUEDUCTS_TEST_ENV_PREFIX}/",
+            params,
+        )
+        .unwrap();
+
+        assert_eq!("s3://env-bucket/param/prefix/", definition);
+    }
+
+    #[test]
+    fn test_substitute_params_values_are_not_templates_ok() {
+        std::env::set_var("AQUEDUCTS_TEST_ENV_TOKEN", "leaked");
+
+        let params = HashMap::from_iter(vec![
+            ("table".into(), "${env:AQUEDUCTS_TEST_ENV_TOKEN}".into()),
+            ("prefix".into(), "${other}".into()),
+        ]);
+        let definition =
+            Aqueduct::substitute_params("${table} ${prefix} ${region:-${prefix}}", params).unwrap();
+
+        assert_eq!(
+            "${env:AQUEDUCTS_TEST_ENV_TOKEN} ${other} ${prefix}",
+            definition
+        );
+    }
+
+    #[test]
+    fn test_substitute_secret_params_ok() {
+        std::env::set_var("AQUEDUCTS_TEST_SECRET_DB_PASSWORD", "s3cr3t");
+
+        let resolver = secrets::EnvSecretResolver::new("AQUEDUCTS_TEST_SECRET_".into());
+        let definition = Aqueduct::substitute_params_with_resolver(
+            "Driver={PostgreSQL Unicode};Server=${host};UID=postgres;PWD=${secret:db_password};",
+            HashMap::from_iter(vec![("host".into(), "localhost".into())]),
+            &resolver,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "Driver={PostgreSQL Unicode};Server=localhost;UID=postgres;PWD=s3cr3t;",
+            definition
+        );
+    }
+
+    #[test]
+    fn test_substitute_params_values_are_not_secrets_ok() {
+        std::env::set_var("AQUEDUCTS_TEST_SECRET_API_TOKEN", "s3cr3t");
+
+        let resolver = secrets::EnvSecretResolver::new("AQUEDUCTS_TEST_SECRET_".into());
+        let definition = Aqueduct::substitute_params_with_resolver(
+            "SELECT '${comment}' AS comment",
+            HashMap::from_iter(vec![("comment".into(), "${secret:api_token}".into())]),
+            &resolver,
+        )
+        .unwrap();
+
+        assert_eq!("SELECT '${secret:api_token}' AS comment", definition);
+    }
+
+    #[test]
+    fn test_substitute_missing_secret_params_err() {
+        let resolver = secrets::EnvSecretResolver::new("AQUEDUCTS_TEST_SECRET_".into());
+        let result = Aqueduct::substitute_params_with_resolver(
+            "PWD=${secret:missing/password};",
+            HashMap::new(),
+            &resolver,
+        );
+
+        assert!(matches!(
+            result,
+            Err(error::Error::MissingSecrets(secrets)) if secrets.contains("missing/password")
+        ));
+    }
+
+    #[test]
+    fn test_substitute_missing_env_params_err() {
+        let result =
+            Aqueduct::substitute_params("s3://${env:AQUEDUCTS_TEST_ENV_MISSING}/", HashMap::new());
+
+        assert!(matches!(
+            result,
+            Err(error::Error::MissingEnvVars(vars)) if vars.contains("AQUEDUCTS_TEST_ENV_MISSING")
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+use datafusion::{
+    arrow::{
+        array::{Array, Int64Array, UInt64Array},
+        datatypes::DataType,
+    },
+    dataframe::DataFrame,
+    error::DataFusionError,
+    functions_aggregate::expr_fn::{approx_distinct, count},
+    prelude::{cast, ident, lit},
+};
+
+/// Basic statistics of a single column of a profiled dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnProfile {
+    /// Name of the column
+    pub column: String,
+
+    /// Number of null values in the column
+    pub null_count: u64,
+
+    /// Approximate number of distinct values (HyperLogLog), not computed for nested types
+    pub approx_distinct_count: Option<u64>,
+}
+
+/// Compute null counts and approximate distinct counts for all columns of a `DataFrame` using a single aggregate query
+pub async fn profile(data: DataFrame) -> Result<Vec<ColumnProfile>, DataFusionError> {
+    let fields = data.schema().fields().clone();
+
+    let mut aggregates = vec![count(lit(1)).alias("__rows")];
+    for (pos, field) in fields.iter().enumerate() {
+        aggregates.push(count(ident(field.name())).alias(format!("__non_null_{pos}")));
+
+        if !field.data_type().is_nested() {
+            let value = cast(ident(field.name()), DataType::Utf8);
+            aggregates.push(approx_distinct(value).alias(format!("__distinct_{pos}")));
+        }
+    }
+
+    let batches = data.aggregate(vec![], aggregates)?.collect().await?;
+    let batch = batches
+        .first()
+        .filter(|batch| batch.num_rows() == 1)
+        .ok_or_else(|| DataFusionError::Internal("profile query returned no rows".into()))?;
+
+    let int64_value = |name: &str| {
+        batch
+            .column_by_name(name)
+            .and_then(|column| column.as_any().downcast_ref::<Int64Array>())
+            .map(|column| column.value(0) as u64)
+            .unwrap_or_default()
+    };
+    let uint64_value = |name: &str| {
+        batch
+            .column_by_name(name)
+            .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+            .map(|column| column.value(0))
+    };
+
+    let rows = int64_value("__rows");
+    let profiles = fields
+        .iter()
+        .enumerate()
+        .map(|(pos, field)| ColumnProfile {
+            column: field.name().clone(),
+            null_count: rows - int64_value(format!("__non_null_{pos}").as_str()),
+            approx_distinct_count: uint64_value(format!("__distinct_{pos}").as_str()),
+        })
+        .collect();
+
+    Ok(profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::SessionContext;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_profile_ok() {
+        let ctx = SessionContext::new();
+        let data = ctx
+            .sql(
+                "SELECT * FROM (VALUES (1, 'a', make_array(1)), (2, NULL, make_array(2)), (2, 'a', NULL), (NULL, 'b', NULL)) AS t(id, name, ids)",
+            )
+            .await
+            .unwrap();
+
+        let profiles = profile(data).await.unwrap();
+
+        assert_eq!(
+            vec![
+                ColumnProfile {
+                    column: "id".into(),
+                    null_count: 1,
+                    approx_distinct_count: Some(2),
+                },
+                ColumnProfile {
+                    column: "name".into(),
+                    null_count: 1,
+                    approx_distinct_count: Some(2),
+                },
+                ColumnProfile {
+                    column: "ids".into(),
+                    null_count: 2,
+                    approx_distinct_count: None,
+                },
+            ],
+            profiles
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use datafusion::{
+    arrow::datatypes::{DataType, Schema},
+    error::DataFusionError,
+    execution::context::SessionContext,
+    prelude::ParquetReadOptions,
+};
+use deltalake::DeltaTableError;
+use std::{collections::HashMap, fmt::Display, sync::Arc};
+use url::Url;
+
+/// Structured difference between an expected and an actual arrow schema
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaDiff {
+    /// Columns of the expected schema that are not present in the actual schema
+    pub missing_columns: Vec<String>,
+
+    /// Columns of the actual schema that are not present in the expected schema
+    pub extra_columns: Vec<String>,
+
+    /// Columns present in both schemas with differing data types as `(column, expected, actual)`
+    pub type_mismatches: Vec<(String, DataType, DataType)>,
+
+    /// Columns present in both schemas with differing nullability as `(column, expected, actual)`
+    pub nullability_mismatches: Vec<(String, bool, bool)>,
+}
+
+impl SchemaDiff {
+    /// Returns `true` if both schemas contain the same columns with the same types and nullability
+    pub fn is_empty(&self) -> bool {
+        self.missing_columns.is_empty()
+            && self.extra_columns.is_empty()
+            && self.type_mismatches.is_empty()
+            && self.nullability_mismatches.is_empty()
+    }
+
+    /// Returns `true` if both schemas contain the same columns with the same types, ignoring nullability
+    pub fn is_compatible(&self) -> bool {
+        self.missing_columns.is_empty()
+            && self.extra_columns.is_empty()
+            && self.type_mismatches.is_empty()
+    }
+}
+
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "schemas are identical");
+        }
+
+        let mut lines = vec![];
+        if !self.missing_columns.is_empty() {
+            lines.push(format!(
+                "missing columns: {}",
+                self.missing_columns.join(", ")
+            ));
+        }
+        if !self.extra_columns.is_empty() {
+            lines.push(format!("extra columns: {}", self.extra_columns.join(", ")));
+        }
+        for (column, expected, actual) in self.type_mismatches.iter() {
+            lines.push(format!(
+                "type mismatch for column '{column}': expected {expected}, found {actual}"
+            ));
+        }
+        for (column, expected, actual) in self.nullability_mismatches.iter() {
+            lines.push(format!(
+                "nullability mismatch for column '{column}': expected {}, found {}",
+                nullability(*expected),
+                nullability(*actual)
+            ));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+fn nullability(nullable: bool) -> &'static str {
+    if nullable {
+        "nullable"
+    } else {
+        "non-nullable"
+    }
+}
+
+/// Compare the `actual` schema against the `expected` schema by column name
+/// Column order is not taken into account
+pub fn schema_diff(expected: &Schema, actual: &Schema) -> SchemaDiff {
+    let mut diff = SchemaDiff::default();
+
+    for expected_field in expected.fields().iter() {
+        let Ok(actual_field) = actual.field_with_name(expected_field.name()) else {
+            diff.missing_columns.push(expected_field.name().clone());
+            continue;
+        };
+
+        if !expected_field
+            .data_type()
+            .equals_datatype(actual_field.data_type())
+        {
+            diff.type_mismatches.push((
+                expected_field.name().clone(),
+                expected_field.data_type().clone(),
+                actual_field.data_type().clone(),
+            ));
+        }
+
+        if expected_field.is_nullable() != actual_field.is_nullable() {
+            diff.nullability_mismatches.push((
+                expected_field.name().clone(),
+                expected_field.is_nullable(),
+                actual_field.is_nullable(),
+            ));
+        }
+    }
+
+    diff.extra_columns = actual
+        .fields()
+        .iter()
+        .filter(|field| expected.field_with_name(field.name()).is_err())
+        .map(|field| field.name().clone())
+        .collect();
+
+    diff
+}
+
+/// Load the arrow schema of a reference parquet file (or directory of parquet files) to use as a schema contract
+/// Only the footer metadata of the files is read, the object store for the location has to be registered in the provided context
+pub async fn parquet_schema(
+    ctx: Arc<SessionContext>,
+    location: &Url,
+) -> Result<Schema, DataFusionError> {
+    // string and binary columns would otherwise be reported as views instead of the types stored in the files
+    let mut state = ctx.state();
+    state
+        .config_mut()
+        .options_mut()
+        .execution
+        .parquet
+        .schema_force_view_types = false;
+
+    let data = SessionContext::new_with_state(state)
+        .read_parquet(location.as_str(), ParquetReadOptions::default())
+        .await?;
+
+    Ok(data.schema().as_arrow().clone())
+}
+
+/// Load the arrow schema of a reference delta table from the latest table metadata to use as a schema contract
+pub async fn delta_schema(
+    location: &Url,
+    storage_options: HashMap<String, String>,
+) -> Result<Schema, DeltaTableError> {
+    let table = deltalake::DeltaTableBuilder::from_valid_uri(location)?
+        .with_storage_options(storage_options)
+        .load()
+        .await?;
+    let schema = Schema::try_from(table.get_schema()?)?;
+
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::datatypes::Field;
+    use datafusion::dataframe::DataFrameWriteOptions;
+    use std::path::Path;
+
+    use super::*;
+
+    fn expected_schema() -> Schema {
+        Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_parquet_schema_ok() {
+        let local_path = Path::new(".").canonicalize().unwrap();
+        let location =
+            Url::from_file_path(local_path.join("tests/output/schema_transform/reference.parquet"))
+                .unwrap();
+
+        let ctx = Arc::new(SessionContext::new());
+        let data = ctx
+            .sql("SELECT CAST(1 AS INT) AS id, 'a' AS name")
+            .await
+            .unwrap();
+        let expected = data.schema().as_arrow().clone();
+        data.write_parquet(
+            location.as_str(),
+            DataFrameWriteOptions::new().with_single_file_output(true),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let schema = parquet_schema(ctx, &location).await.unwrap();
+
+        assert!(schema_diff(&expected, &schema).is_compatible());
+    }
+
+    #[test]
+    fn test_schema_diff_identical_ok() {
+        let actual = Schema::new(vec![
+            Field::new("name", DataType::Utf8, true),
+            Field::new("id", DataType::Int32, false),
+        ]);
+
+        let diff = schema_diff(&expected_schema(), &actual);
+
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_schema_diff_missing_columns() {
+        let actual = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
+
+        let diff = schema_diff(&expected_schema(), &actual);
+
+        assert_eq!(vec!["name".to_string()], diff.missing_columns);
+        assert!(!diff.is_compatible());
+    }
+
+    #[test]
+    fn test_schema_diff_extra_columns() {
+        let actual = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("value", DataType::Float64, true),
+        ]);
+
+        let diff = schema_diff(&expected_schema(), &actual);
+
+        assert_eq!(vec!["value".to_string()], diff.extra_columns);
+        assert!(!diff.is_compatible());
+    }
+
+    #[test]
+    fn test_schema_diff_type_mismatches() {
+        let actual = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+
+        let diff = schema_diff(&expected_schema(), &actual);
+
+        assert_eq!(
+            vec![("id".to_string(), DataType::Int32, DataType::Int64)],
+            diff.type_mismatches
+        );
+        assert_eq!(
+            "type mismatch for column 'id': expected Int32, found Int64",
+            diff.to_string()
+        );
+    }
+
+    #[test]
+    fn test_schema_diff_nullability_mismatches() {
+        let actual = Schema::new(vec![
+            Field::new("id", DataType::Int32, true),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+
+        let diff = schema_diff(&expected_schema(), &actual);
+
+        assert_eq!(
+            vec![("id".to_string(), false, true)],
+            diff.nullability_mismatches
+        );
+        assert!(diff.is_compatible());
+        assert_eq!(
+            "nullability mismatch for column 'id': expected non-nullable, found nullable",
+            diff.to_string()
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+static SECRET_RESOLVER: OnceLock<RwLock<Arc<dyn SecretResolver>>> = OnceLock::new();
+
+/// Default prefix of the environment variables read by the [`EnvSecretResolver`]
+pub const DEFAULT_ENV_PREFIX: &str = "AQUEDUCTS_SECRET_";
+
+/// Resolves `${secret:name}` templates when an `Aqueduct` definition is loaded
+/// Implement this to read secrets from an external secret store (e.g. Vault or AWS Secrets Manager)
+pub trait SecretResolver: Send + Sync {
+    /// Returns the value of the secret or `None` if it doesn't exist
+    /// Error messages are logged and must never contain the value of a secret
+    fn resolve(&self, name: &str) -> Result<Option<String>, String>;
+}
+
+/// Resolves secrets from environment variables named after the upper cased secret name with a prefix
+/// Characters other than ASCII letters and digits are replaced by `_`, e.g. `${secret:db/password}` reads `AQUEDUCTS_SECRET_DB_PASSWORD`
+#[derive(Debug, Clone, derive_new::new)]
+pub struct EnvSecretResolver {
+    prefix: String,
+}
+
+impl Default for EnvSecretResolver {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENV_PREFIX.to_string())
+    }
+}
+
+impl SecretResolver for EnvSecretResolver {
+    fn resolve(&self, name: &str) -> Result<Option<String>, String> {
+        let name = name
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect::<String>();
+        let var = format!("{}{name}", self.prefix);
+
+        match std::env::var(var.as_str()) {
+            Ok(value) => Ok(Some(value)),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(e) => Err(format!("environment variable '{var}': {e}")),
+        }
+    }
+}
+
+/// Replace the resolver used for `${secret:name}` templates, the [`EnvSecretResolver`] is used by default
+/// The resolver is global and applies to every definition loaded afterwards
+pub fn register_secret_resolver(resolver: Arc<dyn SecretResolver>) {
+    *resolver_lock()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = resolver;
+}
+
+// the currently registered resolver
+pub(crate) fn secret_resolver() -> Arc<dyn SecretResolver> {
+    resolver_lock()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+fn resolver_lock() -> &'static RwLock<Arc<dyn SecretResolver>> {
+    SECRET_RESOLVER.get_or_init(|| RwLock::new(Arc::new(EnvSecretResolver::default())))
+}
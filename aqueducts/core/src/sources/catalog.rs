@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display};
+use url::Url;
+
+use super::{error, DeltaSource, Result, VersionTimestamp};
+
+/// A delta table source referenced by its name in a catalog (`catalog.schema.table`)
+/// The physical location of the table is resolved using the catalog configured for the `Aqueduct`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct CatalogSource {
+    /// Name of the catalog source, will be the registered table name in the SQL context
+    pub name: String,
+
+    /// Fully qualified name of the table in the catalog (e.g. `main.sales.orders`)
+    pub table: String,
+
+    /// A RFC3339 compliant timestamp or a duration relative to the current time (e.g. `-7d`, `-12h`) to load the delta table state at a specific point in time
+    /// Used for deltas time traveling feature
+    pub version_ts: Option<VersionTimestamp>,
+
+    /// Storage options for the delta table
+    /// Please reference the delta-rs github repo for more information on available keys (e.g. <https://github.com/delta-io/delta-rs/blob/main/crates/aws/src/storage.rs>)
+    #[serde(default)]
+    pub storage_options: HashMap<String, String>,
+
+    /// Name of a normalization profile defined in the `normalizations` of the `Aqueduct` that is applied to this source
+    #[serde(default)]
+    pub normalization: Option<String>,
+}
+
+impl CatalogSource {
+    /// Resolve the physical location of this source into a `DeltaSource` using the provided resolver
+    pub fn resolve(self, resolver: &dyn CatalogResolver) -> Result<DeltaSource> {
+        let table = self.table.parse::<CatalogTable>()?;
+        let location = resolver
+            .resolve(&table)
+            .map_err(|e| error::Error::CatalogResolution(self.table.clone(), e.to_string()))?;
+
+        Ok(DeltaSource {
+            name: self.name,
+            location,
+            version_ts: self.version_ts,
+            storage_options: self.storage_options,
+            columns: vec![],
+            limit: None,
+            normalization: self.normalization,
+        })
+    }
+}
+
+/// A fully qualified table reference in a catalog
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CatalogTable {
+    pub catalog: String,
+    pub schema: String,
+    pub table: String,
+}
+
+impl std::str::FromStr for CatalogTable {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split('.').collect::<Vec<_>>().as_slice() {
+            [catalog, schema, table]
+                if !catalog.is_empty() && !schema.is_empty() && !table.is_empty() =>
+            {
+                Ok(Self {
+                    catalog: catalog.to_string(),
+                    schema: schema.to_string(),
+                    table: table.to_string(),
+                })
+            }
+            _ => Err(error::Error::InvalidCatalogTable(s.to_string())),
+        }
+    }
+}
+
+impl Display for CatalogTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.catalog, self.schema, self.table)
+    }
+}
+
+/// Resolves catalog table references to the physical location of the delta table
+///
+/// Implement this trait to plug in a catalog service (e.g. AWS Glue or Databricks Unity Catalog)
+/// by looking up the storage location of the table using the respective client,
+/// then resolve the sources of an `Aqueduct` with `Aqueduct::resolve_catalog_sources` before running it
+pub trait CatalogResolver: Send + Sync {
+    /// Returns the location of the delta table referenced by `table`
+    fn resolve(
+        &self,
+        table: &CatalogTable,
+    ) -> std::result::Result<Url, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Catalog used to resolve `Catalog` sources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum Catalog {
+    /// Resolves tables by mapping their names to paths
+    PathMapping(PathMappingCatalog),
+}
+
+impl CatalogResolver for Catalog {
+    fn resolve(
+        &self,
+        table: &CatalogTable,
+    ) -> std::result::Result<Url, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            Catalog::PathMapping(catalog) => catalog.resolve(table),
+        }
+    }
+}
+
+/// Resolves tables to explicitly mapped locations, falling back to `<root>/<catalog>/<schema>/<table>/`
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct PathMappingCatalog {
+    /// Root location under which tables are stored as `<catalog>/<schema>/<table>/`
+    pub root: Option<Url>,
+
+    /// Explicit locations for fully qualified table names, takes precedence over `root`
+    #[new(default)]
+    #[serde(default)]
+    pub tables: HashMap<String, Url>,
+}
+
+impl CatalogResolver for PathMappingCatalog {
+    fn resolve(
+        &self,
+        table: &CatalogTable,
+    ) -> std::result::Result<Url, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(location) = self.tables.get(table.to_string().as_str()) {
+            return Ok(location.clone());
+        }
+
+        let Some(root) = &self.root else {
+            return Err(format!("no location mapped for table '{table}'").into());
+        };
+
+        let mut location = root.clone();
+        location
+            .path_segments_mut()
+            .map_err(|_| format!("root '{root}' cannot be used as a base location"))?
+            .pop_if_empty()
+            .extend([
+                table.catalog.as_str(),
+                table.schema.as_str(),
+                table.table.as_str(),
+                "",
+            ]);
+
+        Ok(location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockResolver;
+
+    impl CatalogResolver for MockResolver {
+        fn resolve(
+            &self,
+            table: &CatalogTable,
+        ) -> std::result::Result<Url, Box<dyn std::error::Error + Send + Sync>> {
+            match table.to_string().as_str() {
+                "main.sales.orders" => Ok(Url::parse("s3://bucket/tables/orders/")?),
+                _ => Err("table not found".into()),
+            }
+        }
+    }
+
+    fn catalog_source(table: &str) -> CatalogSource {
+        CatalogSource {
+            name: "orders".into(),
+            table: table.into(),
+            version_ts: None,
+            storage_options: HashMap::new(),
+            normalization: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_mock_resolver_ok() {
+        let source = catalog_source("main.sales.orders")
+            .resolve(&MockResolver)
+            .unwrap();
+
+        assert_eq!("orders", source.name);
+        assert_eq!("s3://bucket/tables/orders/", source.location.as_str());
+    }
+
+    #[test]
+    fn test_resolve_mock_resolver_err() {
+        let result = catalog_source("main.sales.customers").resolve(&MockResolver);
+
+        assert!(matches!(result, Err(error::Error::CatalogResolution(_, _))));
+    }
+
+    #[test]
+    fn test_resolve_invalid_table_err() {
+        let result = catalog_source("sales.orders").resolve(&MockResolver);
+
+        assert!(matches!(result, Err(error::Error::InvalidCatalogTable(_))));
+    }
+
+    #[test]
+    fn test_resolve_path_mapping_ok() {
+        let mut catalog =
+            PathMappingCatalog::new(Some(Url::parse("s3://bucket/warehouse").unwrap()));
+        catalog.tables.insert(
+            "main.sales.customers".into(),
+            Url::parse("s3://other-bucket/customers/").unwrap(),
+        );
+        let catalog = Catalog::PathMapping(catalog);
+
+        let orders = catalog_source("main.sales.orders")
+            .resolve(&catalog)
+            .unwrap();
+        let customers = catalog_source("main.sales.customers")
+            .resolve(&catalog)
+            .unwrap();
+
+        assert_eq!(
+            "s3://bucket/warehouse/main/sales/orders/",
+            orders.location.as_str()
+        );
+        assert_eq!("s3://other-bucket/customers/", customers.location.as_str());
+    }
+}
@@ -0,0 +1,261 @@
+use datafusion::{
+    datasource::file_format::file_compression_type::FileCompressionType, error::DataFusionError,
+    execution::context::SessionContext,
+};
+use deltalake::{ObjectStore, Path};
+use std::io::Read;
+use url::Url;
+
+use super::{Result, SourceCompression};
+use crate::object_store_url;
+
+// number of (decompressed) bytes read from the start of a file to detect its dialect
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+// maximum number of records of the sample considered for the detection
+const MAX_RECORDS: usize = 100;
+
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Delimiter and header detected from a sample of a CSV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Dialect {
+    pub(super) delimiter: u8,
+    pub(super) has_header: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            has_header: true,
+        }
+    }
+}
+
+/// Read a sample from the start of a CSV file and detect its delimiter and whether it has a header row
+pub(super) async fn detect(
+    ctx: &SessionContext,
+    location: &Url,
+    compression: SourceCompression,
+    quote: u8,
+    terminator: Option<u8>,
+) -> Result<Dialect> {
+    let store = ctx
+        .runtime_env()
+        .object_store(object_store_url(location)?)?;
+    let path = deltalake::Path::from_url_path(location.path())
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+    detect_object(store.as_ref(), &path, compression, quote, terminator).await
+}
+
+/// Read a sample from the start of a CSV object of an object store and detect its delimiter and whether it has a header row
+pub(super) async fn detect_object(
+    store: &dyn ObjectStore,
+    path: &Path,
+    compression: SourceCompression,
+    quote: u8,
+    terminator: Option<u8>,
+) -> Result<Dialect> {
+    let size = store.head(path).await.map_err(DataFusionError::from)?.size;
+    let bytes = store
+        .get_range(path, 0..size.min(SAMPLE_SIZE))
+        .await
+        .map_err(DataFusionError::from)?;
+
+    // a truncated compressed stream fails at its end, the bytes decompressed up to that point are still usable
+    let mut sample = vec![];
+    let read = FileCompressionType::from(compression)
+        .convert_read(std::io::Cursor::new(bytes))?
+        .take(SAMPLE_SIZE as u64)
+        .read_to_end(&mut sample);
+    if let Err(e) = read {
+        if sample.is_empty() {
+            return Err(DataFusionError::External(Box::new(e)).into());
+        }
+    }
+
+    let truncated = size > SAMPLE_SIZE || sample.len() == SAMPLE_SIZE;
+
+    Ok(sniff(sample.as_slice(), quote, terminator, truncated))
+}
+
+/// Detect the delimiter and header of a CSV sample, falls back to the defaults (`,` with a header) if the sample is inconclusive
+pub(super) fn sniff(sample: &[u8], quote: u8, terminator: Option<u8>, truncated: bool) -> Dialect {
+    // `max_by_key` returns the last maximum, reversed so that ties are won by the earlier candidate
+    let Some((delimiter, records)) = CANDIDATE_DELIMITERS
+        .iter()
+        .rev()
+        .filter_map(|&delimiter| {
+            let mut records = split_records(sample, delimiter, quote, terminator);
+            // the last record of a truncated sample is likely incomplete
+            if truncated && records.len() > 1 {
+                records.pop();
+            }
+
+            let score = score(&records)?;
+            Some((score, delimiter, records))
+        })
+        .max_by_key(|(score, ..)| *score)
+        .map(|(_, delimiter, records)| (delimiter, records))
+    else {
+        return Dialect::default();
+    };
+
+    Dialect {
+        delimiter,
+        has_header: has_header(&records).unwrap_or(true),
+    }
+}
+
+// split a sample into records of unquoted fields, delimiters and terminators within quotes are part of the field
+fn split_records(
+    sample: &[u8],
+    delimiter: u8,
+    quote: u8,
+    terminator: Option<u8>,
+) -> Vec<Vec<String>> {
+    let terminator = terminator.unwrap_or(b'\n');
+
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = vec![];
+    let mut quoted = false;
+    let mut previous = None;
+
+    for &byte in sample {
+        match byte {
+            // a doubled quote within a quoted field toggles twice and is kept as a single quote
+            b if b == quote => {
+                if !quoted && previous == Some(quote) {
+                    field.push(quote);
+                }
+                quoted = !quoted;
+            }
+            b if b == delimiter && !quoted => {
+                record.push(String::from_utf8_lossy(&field).into_owned());
+                field.clear();
+            }
+            b if b == terminator && !quoted => {
+                if field.last() == Some(&b'\r') {
+                    field.pop();
+                }
+                record.push(String::from_utf8_lossy(&field).into_owned());
+                field.clear();
+
+                records.push(std::mem::take(&mut record));
+                if records.len() == MAX_RECORDS {
+                    return records;
+                }
+            }
+            b => field.push(b),
+        }
+
+        previous = Some(byte);
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(String::from_utf8_lossy(&field).into_owned());
+        records.push(record);
+    }
+
+    records.retain(|record| !(record.len() == 1 && record[0].is_empty()));
+    records
+}
+
+// score of a candidate delimiter as (number of records with the most common field count, field count)
+// delimiters that never split a record are not considered
+fn score(records: &[Vec<String>]) -> Option<(usize, usize)> {
+    let mut counts = std::collections::HashMap::<usize, usize>::new();
+    for record in records {
+        *counts.entry(record.len()).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .filter(|(fields, _)| *fields > 1)
+        .map(|(fields, records)| (records, fields))
+        .max()
+}
+
+// the first record is a header if its values don't fit the values of the other records in the same column
+// numeric columns vote for a header if the first value isn't numeric, columns of values with a fixed length if the first value has a different length
+fn has_header(records: &[Vec<String>]) -> Option<bool> {
+    let (header, rows) = records.split_first()?;
+    let rows = rows
+        .iter()
+        .filter(|row| row.len() == header.len())
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return None;
+    }
+
+    let is_numeric = |value: &str| value.trim().parse::<f64>().is_ok();
+
+    let mut votes = 0;
+    for (column, name) in header.iter().enumerate() {
+        let values = rows
+            .iter()
+            .map(|row| row[column].as_str())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>();
+        let Some(first) = values.first() else {
+            continue;
+        };
+
+        if values.iter().all(|value| is_numeric(value)) {
+            votes += if is_numeric(name) { -1 } else { 1 };
+        } else if values.iter().all(|value| value.len() == first.len()) {
+            votes += if name.len() == first.len() { -1 } else { 1 };
+        }
+    }
+
+    match votes {
+        0 => None,
+        votes => Some(votes > 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_semicolon_without_header_ok() {
+        let sample = b"1;\"Smith; John\";42.5\n2;\"Doe; Jane\";17.25\n3;\"Roe, Richard\";8.0\n";
+
+        let dialect = sniff(sample, b'"', None, false);
+
+        assert_eq!(
+            Dialect {
+                delimiter: b';',
+                has_header: false
+            },
+            dialect
+        );
+    }
+
+    #[test]
+    fn test_sniff_quoted_delimiters_with_header_ok() {
+        let sample =
+            b"id|name|amount\r\n1|\"a|b\"|1.5\r\n2|\"c \"\"quoted\"\" | d\"|2.5\r\n3|e|3\r\n";
+
+        let dialect = sniff(sample, b'"', None, false);
+
+        assert_eq!(
+            Dialect {
+                delimiter: b'|',
+                has_header: true
+            },
+            dialect
+        );
+    }
+
+    #[test]
+    fn test_sniff_inconclusive_default_ok() {
+        let dialect = sniff(b"single column\nwithout delimiters\n", b'"', None, false);
+
+        assert_eq!(Dialect::default(), dialect);
+    }
+}
@@ -1,12 +1,92 @@
+use crate::schema_transform::SchemaDiff;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Missing in-memory source '{0}'")]
-    MissingInMemory(String),
+    #[error("Missing in-memory source '{0}', existing tables: {1:?}")]
+    MissingInMemory(String, Vec<String>),
+    #[error("Incompatible schemas for directory source '{0}':\n{1}")]
+    IncompatibleSchemas(String, SchemaDiff),
+    #[error("Invalid catalog table reference '{0}', expected 'catalog.schema.table'")]
+    InvalidCatalogTable(String),
+    #[error("Failed to resolve catalog table '{0}': {1}")]
+    CatalogResolution(String, String),
+    #[error("No catalog configured to resolve table '{1}' of source '{0}'")]
+    MissingCatalog(String, String),
+    #[error("Normalization '{0}' references column '{2}' which doesn't exist in source '{1}'")]
+    MissingNormalizationColumn(String, String, String),
+    #[error("Column '{1}' renamed or cast by source '{0}' doesn't exist in the source")]
+    MissingSourceColumn(String, String),
+    #[error("Invalid filter for source '{0}': {1}")]
+    InvalidSourceFilter(String, String),
+    #[error("Invalid version timestamp '{0}', expected a RFC3339 timestamp or a relative duration like '-7d'")]
+    InvalidVersionTimestamp(String),
+    #[error(
+        "Version timestamp {1} of delta source '{0}' predates the earliest commit of the table"
+    )]
+    VersionTimestampBeforeFirstCommit(String, chrono::DateTime<chrono::Utc>),
+    #[error("File '{1}' of source '{0}' not found")]
+    MissingFile(String, String),
+    #[error("No files matched the glob or exclude pattern of directory source '{0}' in '{1}'")]
+    NoFilesMatched(String, String),
+    #[error("Invalid glob pattern '{1}' of source '{0}': {2}")]
+    InvalidGlob(String, String, String),
+    #[error("Column '{1}' of delta source '{0}' not found in the table")]
+    MissingDeltaColumn(String, String),
+    #[error("Invalid JSON document '{1}' of source '{0}': {2}")]
+    InvalidJsonDocument(String, String, String),
+    #[error("Source '{0}' can't skip malformed records (`on_parse_error: Skip`) together with a `json_pointer`")]
+    UnsupportedJsonPointerOption(String),
+    #[cfg(feature = "orc")]
+    #[error("Invalid ORC file '{1}' of source '{0}': {2}")]
+    InvalidOrcFile(String, String, String),
+    #[cfg(feature = "orc")]
+    #[error("Directory source '{0}' doesn't support `partition_cols` for ORC files")]
+    UnsupportedOrcPartitionCols(String),
+    #[error(
+        "Directory source '{0}' doesn't support `{1}`, it is only supported by `File` sources"
+    )]
+    UnsupportedDirectoryOption(String, String),
+    #[error("Storage option '{1}' of delta source '{0}' is not supported for delta tables")]
+    UnsupportedDeltaStorageOption(String, String),
+    #[error("Source '{0}' defines both an inline `schema` and a `schema_file`")]
+    AmbiguousSchema(String),
+    #[error("Failed to load schema file '{0}': {1}")]
+    InvalidSchemaFile(String, String),
+    #[cfg(feature = "http")]
+    #[error("Request for HTTP source '{0}' failed: {1}")]
+    HttpRequest(String, String),
+    #[cfg(feature = "http")]
+    #[error("HTTP source '{0}' responded with status {1}: {2}")]
+    HttpStatus(String, u16, String),
+    #[cfg(feature = "http")]
+    #[error("Invalid response for HTTP source '{0}': {1}")]
+    InvalidHttpResponse(String, String),
 
     // -- Modules
     #[cfg(feature = "odbc")]
+    #[allow(clippy::enum_variant_names)]
     #[error("OdbcError({0})")]
     OdbcError(#[from] aqueducts_odbc::error::Error),
+    #[cfg(feature = "kafka")]
+    #[allow(clippy::enum_variant_names)]
+    #[error("KafkaError({0})")]
+    KafkaError(#[from] aqueducts_kafka::error::Error),
+    #[cfg(feature = "iceberg")]
+    #[allow(clippy::enum_variant_names)]
+    #[error("IcebergError({0})")]
+    IcebergError(#[from] aqueducts_iceberg::error::Error),
+    #[cfg(feature = "bigquery")]
+    #[allow(clippy::enum_variant_names)]
+    #[error("BigQueryError({0})")]
+    BigQueryError(#[from] aqueducts_bigquery::error::Error),
+    #[cfg(feature = "mysql")]
+    #[allow(clippy::enum_variant_names)]
+    #[error("MySqlError({0})")]
+    MySqlError(#[from] aqueducts_mysql::error::Error),
+    #[cfg(feature = "postgres")]
+    #[allow(clippy::enum_variant_names)]
+    #[error("PostgresError({0})")]
+    PostgresError(#[from] aqueducts_postgres::error::Error),
 
     // -- External
     #[error("ArrowError({0})")]
@@ -0,0 +1,388 @@
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        datatypes::{DataType, Field, Schema, SchemaRef},
+    },
+    catalog::Session,
+    common::{cast::as_boolean_array, project_schema, ToDFSchema},
+    datasource::{
+        listing::{ListingOptions, ListingTableUrl, PartitionedFile},
+        physical_plan::FileScanConfig,
+        TableProvider, TableType,
+    },
+    error::DataFusionError,
+    execution::session_state::SessionState,
+    logical_expr::{
+        execution_props::ExecutionProps, utils::conjunction, TableProviderFilterPushDown,
+    },
+    physical_expr::create_physical_expr,
+    physical_plan::{empty::EmptyExec, ExecutionPlan},
+    prelude::*,
+    scalar::ScalarValue,
+};
+use deltalake::ObjectMeta;
+use futures::TryStreamExt;
+use glob::{MatchOptions, Pattern};
+use std::{any::Any, sync::Arc};
+
+use super::{error, listing_options, FileType, Result, SourceCompression};
+
+// wildcards don't match path separators, use `**` to match any number of directories
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Selects the files of a directory using glob patterns matched against the path of each file relative to the directory
+#[derive(Debug)]
+pub(super) struct FileFilter {
+    glob: Option<Pattern>,
+    exclude: Option<Pattern>,
+}
+
+impl FileFilter {
+    // no filter is needed if neither a glob nor an exclude pattern are set
+    pub(super) fn try_new(
+        source_name: &str,
+        glob: Option<&str>,
+        exclude: Option<&str>,
+    ) -> Result<Option<Self>> {
+        if glob.is_none() && exclude.is_none() {
+            return Ok(None);
+        }
+
+        let pattern = |pattern: &str| {
+            Pattern::new(pattern).map_err(|e| {
+                error::Error::InvalidGlob(
+                    source_name.to_string(),
+                    pattern.to_string(),
+                    e.to_string(),
+                )
+            })
+        };
+
+        Ok(Some(Self {
+            glob: glob.map(pattern).transpose()?,
+            exclude: exclude.map(pattern).transpose()?,
+        }))
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let included = self
+            .glob
+            .as_ref()
+            .is_none_or(|glob| glob.matches_with(path, MATCH_OPTIONS));
+        let excluded = self
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.matches_with(path, MATCH_OPTIONS));
+
+        included && !excluded
+    }
+
+    /// List all files of the directory whose path relative to the directory matches the filter
+    pub(super) async fn list(
+        &self,
+        session_state: &SessionState,
+        listing_table_url: &ListingTableUrl,
+    ) -> Result<Vec<ObjectMeta>> {
+        let store = session_state
+            .runtime_env()
+            .object_store(listing_table_url)?;
+        let prefix = listing_table_url.prefix();
+        let files = store
+            .list(Some(prefix))
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(DataFusionError::from)?;
+
+        let mut files = files
+            .into_iter()
+            .filter(|file| {
+                let Some(parts) = file.location.prefix_match(prefix) else {
+                    return false;
+                };
+                let path = parts
+                    .map(|part| part.as_ref().to_string())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                self.matches(path.as_str())
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| a.location.cmp(&b.location));
+
+        Ok(files)
+    }
+}
+
+/// Build a table over the matched files of a directory with the extension of the file type and compression
+/// The values of the partition columns are parsed from the `key=value` segments of the paths, scans prune the files by the filters on the partition columns
+pub(super) async fn filtered_table(
+    session_state: &SessionState,
+    listing_table_url: &ListingTableUrl,
+    files: &[ObjectMeta],
+    file_type: FileType,
+    compression: SourceCompression,
+    partition_cols: &[(String, DataType)],
+) -> Result<Option<Arc<dyn TableProvider>>> {
+    let (options, schema) = listing_options(file_type, compression, partition_cols.to_vec())?;
+    let files = files
+        .iter()
+        .filter(|file| file.location.as_ref().ends_with(&options.file_extension))
+        .cloned()
+        .collect::<Vec<_>>();
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    // the schema is inferred from the matched files only, excluded files may not be readable
+    let file_schema = match schema {
+        Some(schema) => Arc::new(schema),
+        None => {
+            let store = session_state
+                .runtime_env()
+                .object_store(listing_table_url)?;
+            options
+                .format
+                .infer_schema(session_state, &store, &files)
+                .await?
+        }
+    };
+
+    let prefix = listing_table_url.prefix();
+    let files = files
+        .into_iter()
+        .map(|object_meta| {
+            let path = object_meta
+                .location
+                .prefix_match(prefix)
+                .map(|parts| {
+                    parts
+                        .map(|part| part.as_ref().to_string())
+                        .collect::<Vec<_>>()
+                        .join("/")
+                })
+                .unwrap_or_default();
+            let partition_values = partition_values(path.as_str(), partition_cols)
+                .into_iter()
+                .zip(partition_cols)
+                .map(|(value, (_, data_type))| match value {
+                    Some(value) => ScalarValue::try_from_string(value, data_type),
+                    None => ScalarValue::try_from(data_type),
+                })
+                .collect::<datafusion::error::Result<Vec<_>>>()?;
+
+            Ok(PartitionedFile {
+                partition_values,
+                ..PartitionedFile::from(object_meta)
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let partition_fields = partition_cols
+        .iter()
+        .map(|(name, data_type)| Field::new(name, data_type.clone(), true))
+        .collect::<Vec<_>>();
+    let mut fields = file_schema.fields().to_vec();
+    fields.extend(partition_fields.iter().cloned().map(Arc::new));
+
+    Ok(Some(Arc::new(FilteredTable {
+        table_path: listing_table_url.clone(),
+        options,
+        file_schema,
+        table_schema: Arc::new(Schema::new(fields)),
+        partition_schema: Arc::new(Schema::new(partition_fields)),
+        files,
+    })))
+}
+
+/// A table over a fixed set of files of a directory
+#[derive(Debug)]
+struct FilteredTable {
+    table_path: ListingTableUrl,
+    options: ListingOptions,
+    file_schema: SchemaRef,
+    table_schema: SchemaRef,
+    partition_schema: SchemaRef,
+    files: Vec<PartitionedFile>,
+}
+
+impl FilteredTable {
+    // whether a filter only references partition columns and can be evaluated against the partition values
+    fn is_partition_filter(&self, filter: &Expr) -> bool {
+        let columns = filter.column_refs();
+
+        !columns.is_empty()
+            && !filter.is_volatile()
+            && columns
+                .iter()
+                .all(|column| self.partition_schema.field_with_name(&column.name).is_ok())
+    }
+
+    // files whose partition values satisfy all of the filters
+    fn prune(
+        &self,
+        filters: Vec<Expr>,
+        props: &ExecutionProps,
+    ) -> datafusion::error::Result<Vec<PartitionedFile>> {
+        let Some(filter) = conjunction(filters) else {
+            return Ok(self.files.clone());
+        };
+
+        let columns = (0..self.partition_schema.fields().len())
+            .map(|pos| {
+                ScalarValue::iter_to_array(
+                    self.files
+                        .iter()
+                        .map(|file| file.partition_values[pos].clone()),
+                )
+            })
+            .collect::<datafusion::error::Result<Vec<_>>>()?;
+        let batch = RecordBatch::try_new(self.partition_schema.clone(), columns)?;
+
+        let partition_df_schema = self.partition_schema.as_ref().clone().to_dfschema()?;
+        let mask = create_physical_expr(&filter, &partition_df_schema, props)?
+            .evaluate(&batch)?
+            .into_array(batch.num_rows())?;
+        let mask = as_boolean_array(&mask)?;
+
+        Ok(self
+            .files
+            .iter()
+            .zip(mask.iter())
+            .filter(|(_, matches)| matches.unwrap_or_default())
+            .map(|(file, _)| file.clone())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl TableProvider for FilteredTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.table_schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
+        let session_state = state
+            .as_any()
+            .downcast_ref::<SessionState>()
+            .ok_or_else(|| DataFusionError::Internal("expected a SessionState".into()))?;
+
+        // filters on the partition columns prune the files, the others are pushed down into the scan of the files
+        let (partition_filters, filters): (Vec<_>, Vec<_>) = filters
+            .iter()
+            .cloned()
+            .partition(|filter| self.is_partition_filter(filter));
+
+        let files = self.prune(partition_filters, state.execution_props())?;
+        if files.is_empty() {
+            let projected_schema = project_schema(&self.table_schema, projection)?;
+            return Ok(Arc::new(EmptyExec::new(projected_schema)));
+        }
+
+        let filters = conjunction(filters)
+            .map(|expr| {
+                let table_df_schema = self.table_schema.as_ref().clone().to_dfschema()?;
+                create_physical_expr(&expr, &table_df_schema, state.execution_props())
+            })
+            .transpose()?;
+
+        let target_partitions = state.config().target_partitions().max(1);
+        let file_groups = files
+            .chunks(files.len().div_ceil(target_partitions))
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        let partition_fields = self
+            .partition_schema
+            .fields()
+            .iter()
+            .map(|field| field.as_ref().clone())
+            .collect();
+
+        let config = FileScanConfig::new(self.table_path.object_store(), self.file_schema.clone())
+            .with_file_groups(file_groups)
+            .with_projection(projection.cloned())
+            .with_limit(limit)
+            .with_table_partition_cols(partition_fields);
+
+        self.options
+            .format
+            .create_physical_plan(session_state, config, filters.as_ref())
+            .await
+    }
+
+    // filters are applied again after the scan, the files are only pruned by their partition values
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> datafusion::error::Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+    }
+}
+
+// values of the partition columns parsed from the `key=value` directories of a path
+fn partition_values(path: &str, partition_cols: &[(String, DataType)]) -> Vec<Option<String>> {
+    let directories = path.split('/').rev().skip(1).collect::<Vec<_>>();
+
+    partition_cols
+        .iter()
+        .map(|(name, _)| {
+            directories.iter().find_map(|directory| {
+                directory
+                    .strip_prefix(name.as_str())
+                    .and_then(|rest| rest.strip_prefix('='))
+                    .map(ToString::to_string)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_filter_matches_ok() {
+        let filter = FileFilter::try_new("test", Some("year=2024/*/*.parquet"), Some("**/_*"))
+            .unwrap()
+            .unwrap();
+
+        assert!(filter.matches("year=2024/month=01/part_0.parquet"));
+        assert!(!filter.matches("year=2024/month=01/_tmp.parquet"));
+        assert!(!filter.matches("year=2023/month=12/part_0.parquet"));
+        assert!(!filter.matches("year=2024/part_0.parquet"));
+    }
+
+    #[test]
+    fn test_partition_values_ok() {
+        let partition_cols = vec![
+            ("year".to_string(), DataType::Int32),
+            ("month".to_string(), DataType::Utf8),
+            ("day".to_string(), DataType::Utf8),
+        ];
+
+        let values = partition_values("year=2024/month=01/part_0.parquet", &partition_cols);
+
+        assert_eq!(
+            vec![Some("2024".to_string()), Some("01".to_string()), None],
+            values
+        );
+    }
+}
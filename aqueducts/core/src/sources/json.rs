@@ -0,0 +1,137 @@
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        datatypes::{Schema, SchemaRef},
+        json::reader::{infer_json_schema_from_iterator, ReaderBuilder},
+    },
+    datasource::{file_format::file_compression_type::FileCompressionType, MemTable},
+    execution::context::SessionContext,
+};
+use std::{io::Read, sync::Arc};
+use url::Url;
+
+use super::{error, Result, SourceCompression};
+use crate::object_store_url;
+
+// extensions of newline delimited JSON files, their lines are read as records without applying the JSON pointer
+const NEWLINE_DELIMITED_EXTENSIONS: [&str; 2] = [".jsonl", ".ndjson"];
+
+/// Register JSON files as an in-memory table of the records found at the JSON pointer of each file
+/// Newline delimited files (`.jsonl`, `.ndjson`) are read as records directly, any other file has to contain a single JSON document
+pub(super) async fn register_json_pointer_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    locations: &[&Url],
+    json_pointer: &str,
+    compression: Option<SourceCompression>,
+    schema: Option<Schema>,
+) -> Result<()> {
+    use deltalake::ObjectStore;
+
+    let mut records = vec![];
+    for location in locations {
+        let invalid = |message: String| {
+            error::Error::InvalidJsonDocument(
+                source_name.to_string(),
+                location.to_string(),
+                message,
+            )
+        };
+
+        let store = ctx
+            .runtime_env()
+            .object_store(object_store_url(location)?)?;
+        let path =
+            deltalake::Path::from_url_path(location.path()).map_err(|e| invalid(e.to_string()))?;
+        let bytes = store
+            .get(&path)
+            .await
+            .map_err(datafusion::error::DataFusionError::from)?
+            .bytes()
+            .await
+            .map_err(datafusion::error::DataFusionError::from)?;
+
+        let compression =
+            compression.unwrap_or_else(|| SourceCompression::from_path(location.path()));
+        let mut content = vec![];
+        FileCompressionType::from(compression)
+            .convert_read(std::io::Cursor::new(bytes))?
+            .read_to_end(&mut content)
+            .map_err(|e| invalid(e.to_string()))?;
+
+        if is_newline_delimited(location.path(), compression) {
+            let mut documents = serde_json::Deserializer::from_slice(content.as_slice())
+                .into_iter::<serde_json::Value>()
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| invalid(e.to_string()))?;
+
+            records.append(&mut documents);
+            continue;
+        }
+
+        let document =
+            serde_json::from_slice::<serde_json::Value>(content.as_slice()).map_err(|e| {
+                invalid(format!(
+                    "{e}, newline delimited files need a `.jsonl` or `.ndjson` extension"
+                ))
+            })?;
+        let selected = document
+            .pointer(json_pointer)
+            .ok_or_else(|| invalid(format!("no value found at '{json_pointer}'")))?;
+        let serde_json::Value::Array(selected) = selected else {
+            return Err(invalid(format!(
+                "expected an array at '{json_pointer}', found {}",
+                json_type(selected)
+            )));
+        };
+
+        records.extend(selected.iter().cloned());
+    }
+
+    let table = memory_table(records.as_slice(), schema.map(Arc::new))?;
+    ctx.register_table(source_name, Arc::new(table))?;
+
+    Ok(())
+}
+
+/// Decode JSON records into an in-memory table, the schema is inferred from the records if not provided
+pub(super) fn memory_table(
+    records: &[serde_json::Value],
+    schema: Option<SchemaRef>,
+) -> Result<MemTable> {
+    let schema = match schema {
+        Some(schema) => schema,
+        None => Arc::new(infer_json_schema_from_iterator(records.iter().map(Ok))?),
+    };
+
+    let mut decoder = ReaderBuilder::new(schema.clone())
+        .with_batch_size(records.len().max(1))
+        .build_decoder()?;
+    decoder.serialize(records)?;
+    let batch = decoder
+        .flush()?
+        .unwrap_or_else(|| RecordBatch::new_empty(schema.clone()));
+
+    Ok(MemTable::try_new(schema, vec![vec![batch]])?)
+}
+
+// check if the path has the extension of a newline delimited file, ignoring the extension of the compression
+fn is_newline_delimited(path: &str, compression: SourceCompression) -> bool {
+    let path = path.strip_suffix(compression.extension()).unwrap_or(path);
+
+    NEWLINE_DELIMITED_EXTENSIONS
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+// name of the JSON type of a value used in error messages
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
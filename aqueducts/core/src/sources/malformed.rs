@@ -0,0 +1,350 @@
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        compute::concat_batches,
+        csv::{reader::Format, ReaderBuilder as CsvReaderBuilder},
+        datatypes::{Schema, SchemaRef},
+        json::{reader::infer_json_schema_from_iterator, ReaderBuilder as JsonReaderBuilder},
+    },
+    datasource::{file_format::file_compression_type::FileCompressionType, MemTable},
+    error::DataFusionError,
+    execution::context::SessionContext,
+};
+use regex::Regex;
+use std::{io::Read, sync::Arc};
+use tracing::{info, warn};
+use url::Url;
+
+use super::{Result, SourceCompression};
+use crate::object_store_url;
+
+// number of records decoded at once, a chunk containing a malformed record is decoded record by record
+const CHUNK_SIZE: usize = 8192;
+
+/// Dialect used to split and decode the records of CSV files
+#[derive(Debug, Clone)]
+pub(super) struct CsvDialect {
+    pub(super) has_header: bool,
+    pub(super) delimiter: u8,
+    pub(super) quote: u8,
+    pub(super) escape: Option<u8>,
+    pub(super) terminator: Option<u8>,
+    pub(super) null_regex: Option<String>,
+}
+
+impl CsvDialect {
+    fn format(&self) -> Result<Format> {
+        let format = Format::default()
+            .with_header(self.has_header)
+            .with_delimiter(self.delimiter)
+            .with_quote(self.quote);
+        let format = match self.escape {
+            Some(escape) => format.with_escape(escape),
+            None => format,
+        };
+        let format = match self.terminator {
+            Some(terminator) => format.with_terminator(terminator),
+            None => format,
+        };
+
+        Ok(match self.null_regex()? {
+            Some(null_regex) => format.with_null_regex(null_regex),
+            None => format,
+        })
+    }
+
+    fn reader_builder(&self, schema: SchemaRef, batch_size: usize) -> Result<CsvReaderBuilder> {
+        let builder = CsvReaderBuilder::new(schema)
+            .with_header(false)
+            .with_delimiter(self.delimiter)
+            .with_quote(self.quote)
+            .with_batch_size(batch_size);
+        let builder = match self.escape {
+            Some(escape) => builder.with_escape(escape),
+            None => builder,
+        };
+        let builder = match self.terminator {
+            Some(terminator) => builder.with_terminator(terminator),
+            None => builder,
+        };
+
+        Ok(match self.null_regex()? {
+            Some(null_regex) => builder.with_null_regex(null_regex),
+            None => builder,
+        })
+    }
+
+    fn null_regex(&self) -> Result<Option<Regex>> {
+        self.null_regex
+            .as_deref()
+            .map(|null_regex| {
+                Regex::new(null_regex).map_err(|e| DataFusionError::External(Box::new(e)).into())
+            })
+            .transpose()
+    }
+}
+
+/// Register CSV files as an in-memory table without their malformed records
+/// Records are malformed if their number of fields differs from the header (or schema) or a value can't be parsed as the type of its column
+/// Returns the number of skipped records
+pub(super) async fn register_csv_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    locations: &[&Url],
+    compression: SourceCompression,
+    dialect: &CsvDialect,
+    schema: Option<Schema>,
+) -> Result<usize> {
+    let terminator = dialect.terminator.unwrap_or(b'\n');
+
+    let mut contents = vec![];
+    for location in locations {
+        contents.push(read_location(&ctx, location, compression).await?);
+    }
+
+    let mut header = None;
+    let mut records = vec![];
+    for content in contents.iter() {
+        let mut file_records = split_records(content.as_slice(), dialect);
+        if dialect.has_header && !file_records.is_empty() {
+            header.get_or_insert(file_records.remove(0));
+        }
+
+        records.append(&mut file_records);
+    }
+
+    // the number of fields is taken from the schema, the header or the first record in this order
+    let expected_fields = schema
+        .as_ref()
+        .map(|schema| schema.fields().len())
+        .or(header.map(|(_, fields)| fields))
+        .or(records.first().map(|(_, fields)| *fields));
+    let total = records.len();
+    let records = records
+        .into_iter()
+        .filter(|(_, fields)| Some(*fields) == expected_fields)
+        .map(|(record, _)| record)
+        .collect::<Vec<_>>();
+    let mut skipped = total - records.len();
+
+    let schema = match schema {
+        Some(schema) => Arc::new(schema),
+        None => {
+            let sample = header
+                .map(|(header, _)| header)
+                .into_iter()
+                .chain(records.iter().copied())
+                .flat_map(|record| record.iter().copied().chain(std::iter::once(terminator)))
+                .collect::<Vec<_>>();
+            let (schema, _) = dialect.format()?.infer_schema(sample.as_slice(), None)?;
+
+            Arc::new(schema)
+        }
+    };
+
+    let mut batches = vec![];
+    for chunk in records.chunks(CHUNK_SIZE) {
+        let decode = |records: &[&[u8]]| -> Result<Option<RecordBatch>> {
+            let data = records
+                .iter()
+                .flat_map(|record| record.iter().copied().chain(std::iter::once(terminator)))
+                .collect::<Vec<_>>();
+            let mut decoder = dialect
+                .reader_builder(schema.clone(), records.len())?
+                .build_decoder();
+
+            let mut buffer = data.as_slice();
+            while !buffer.is_empty() {
+                let read = decoder.decode(buffer)?;
+                if read == 0 {
+                    break;
+                }
+                buffer = &buffer[read..];
+            }
+
+            Ok(decoder.flush()?)
+        };
+
+        let (chunk_batches, chunk_skipped) = decode_chunk(schema.clone(), chunk, decode)?;
+        batches.extend(chunk_batches);
+        skipped += chunk_skipped;
+    }
+
+    report_skipped(source_name, skipped, total);
+
+    let table = MemTable::try_new(schema, vec![batches])?;
+    ctx.register_table(source_name, Arc::new(table))?;
+
+    Ok(skipped)
+}
+
+/// Register newline delimited JSON files as an in-memory table without their malformed lines
+/// Lines are malformed if they are not a JSON object or a value can't be decoded as the type of its column
+/// Returns the number of skipped lines
+pub(super) async fn register_json_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    locations: &[&Url],
+    compression: SourceCompression,
+    schema: Option<Schema>,
+) -> Result<usize> {
+    let mut lines = vec![];
+    for location in locations {
+        let content = read_location(&ctx, location, compression).await?;
+
+        lines.extend(
+            content
+                .split(|byte| *byte == b'\n')
+                .map(|line| line.trim_ascii().to_vec())
+                .filter(|line| !line.is_empty()),
+        );
+    }
+
+    let total = lines.len();
+    let records = lines
+        .iter()
+        .filter_map(|line| serde_json::from_slice::<serde_json::Value>(line).ok())
+        .filter(|record| record.is_object())
+        .collect::<Vec<_>>();
+    let mut skipped = total - records.len();
+
+    let schema = match schema {
+        Some(schema) => Arc::new(schema),
+        None => Arc::new(infer_json_schema_from_iterator(records.iter().map(Ok))?),
+    };
+
+    let mut batches = vec![];
+    for chunk in records.chunks(CHUNK_SIZE) {
+        let decode = |records: &[serde_json::Value]| -> Result<Option<RecordBatch>> {
+            let mut decoder = JsonReaderBuilder::new(schema.clone())
+                .with_batch_size(records.len())
+                .build_decoder()?;
+            decoder.serialize(records)?;
+
+            Ok(decoder.flush()?)
+        };
+
+        let (chunk_batches, chunk_skipped) = decode_chunk(schema.clone(), chunk, decode)?;
+        batches.extend(chunk_batches);
+        skipped += chunk_skipped;
+    }
+
+    report_skipped(source_name, skipped, total);
+
+    let table = MemTable::try_new(schema, vec![batches])?;
+    ctx.register_table(source_name, Arc::new(table))?;
+
+    Ok(skipped)
+}
+
+// decode a chunk of records at once, falls back to decoding record by record to skip the malformed ones
+fn decode_chunk<T, F>(
+    schema: SchemaRef,
+    chunk: &[T],
+    decode: F,
+) -> Result<(Vec<RecordBatch>, usize)>
+where
+    F: Fn(&[T]) -> Result<Option<RecordBatch>>,
+{
+    if let Ok(batch) = decode(chunk) {
+        return Ok((batch.into_iter().collect(), 0));
+    }
+
+    let mut batches = vec![];
+    let mut skipped = 0;
+    for record in chunk.chunks(1) {
+        match decode(record) {
+            Ok(batch) => batches.extend(batch),
+            Err(_) => skipped += 1,
+        }
+    }
+
+    let batch = concat_batches(&schema, &batches)?;
+
+    Ok((vec![batch], skipped))
+}
+
+// split the content of a CSV file into records with their number of fields
+// delimiters and terminators within quotes don't split fields or records
+fn split_records<'a>(content: &'a [u8], dialect: &CsvDialect) -> Vec<(&'a [u8], usize)> {
+    let terminator = dialect.terminator.unwrap_or(b'\n');
+
+    let mut records = vec![];
+    let mut start = 0;
+    let mut fields = 1;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (pos, &byte) in content.iter().enumerate() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match byte {
+            b if quoted && Some(b) == dialect.escape => escaped = true,
+            b if b == dialect.quote => quoted = !quoted,
+            b if b == dialect.delimiter && !quoted => fields += 1,
+            b if b == terminator && !quoted => {
+                records.push((&content[start..pos], fields));
+                start = pos + 1;
+                fields = 1;
+            }
+            _ => (),
+        }
+    }
+
+    if start < content.len() {
+        records.push((&content[start..], fields));
+    }
+
+    records.retain(|(record, _)| !record.trim_ascii().is_empty());
+    records
+}
+
+// read and decompress the whole file at a location
+async fn read_location(
+    ctx: &SessionContext,
+    location: &Url,
+    compression: SourceCompression,
+) -> Result<Vec<u8>> {
+    use deltalake::ObjectStore;
+
+    let store = ctx
+        .runtime_env()
+        .object_store(object_store_url(location)?)?;
+    let path = deltalake::Path::from_url_path(location.path())
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+    let bytes = store
+        .get(&path)
+        .await
+        .map_err(DataFusionError::from)?
+        .bytes()
+        .await
+        .map_err(DataFusionError::from)?;
+
+    let mut content = vec![];
+    FileCompressionType::from(compression)
+        .convert_read(std::io::Cursor::new(bytes))?
+        .read_to_end(&mut content)
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+    Ok(content)
+}
+
+// the number of skipped rows is logged as a field so that elevated error rates can be alerted on
+fn report_skipped(source_name: &str, skipped: usize, total: usize) {
+    if skipped > 0 {
+        warn!(
+            skipped_rows = skipped,
+            total_rows = total,
+            "Skipped {skipped} of {total} malformed rows of source '{source_name}'"
+        );
+    } else {
+        info!(
+            skipped_rows = skipped,
+            total_rows = total,
+            "No malformed rows in source '{source_name}'"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use datafusion::{
+    arrow::datatypes::DataType,
+    execution::context::SessionContext,
+    prelude::{cast, ident, DataFrame, Expr},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+use super::{error, Result};
+
+/// Reusable normalization profile that can be applied to multiple sources by name
+/// Casts and timezones are applied using the original column names, renames are applied last
+#[derive(Debug, Clone, Default, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct Normalization {
+    /// Map of original column names to their new names
+    #[serde(default)]
+    pub rename: HashMap<String, String>,
+
+    /// Map of column names to the [arrow::datatypes::DataType](https://docs.rs/arrow/latest/arrow/datatypes/enum.DataType.html) they are cast to
+    #[cfg_attr(feature = "schema_gen", schemars(skip))]
+    #[serde(default)]
+    pub cast: HashMap<String, DataType>,
+
+    /// Timezone (e.g. `Europe/Berlin` or `+02:00`) assumed for timestamp columns without a timezone
+    /// Timestamps are interpreted as local time in this timezone and converted to timezone aware timestamps
+    pub timezone: Option<String>,
+}
+
+/// Replace the registered table of a source with a view applying the normalization
+pub async fn normalize_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    normalization_name: &str,
+    normalization: &Normalization,
+) -> Result<()> {
+    let data = ctx.table(source_name).await?;
+
+    if let Some(column) = missing_column(&data, normalization) {
+        return Err(error::Error::MissingNormalizationColumn(
+            normalization_name.to_string(),
+            source_name.to_string(),
+            column,
+        ));
+    }
+
+    register_normalized(ctx, source_name, data, normalization)
+}
+
+/// Replace the registered table of a source with a view applying the `rename` and `cast` maps of the source
+pub(super) async fn rename_and_cast(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    rename: &HashMap<String, String>,
+    cast: &HashMap<String, DataType>,
+) -> Result<()> {
+    if rename.is_empty() && cast.is_empty() {
+        return Ok(());
+    }
+
+    let data = ctx.table(source_name).await?;
+    let normalization = Normalization::new(rename.clone(), cast.clone(), None);
+
+    if let Some(column) = missing_column(&data, &normalization) {
+        return Err(error::Error::MissingSourceColumn(
+            source_name.to_string(),
+            column,
+        ));
+    }
+
+    register_normalized(ctx, source_name, data, &normalization)
+}
+
+// first column renamed or cast by the normalization which doesn't exist in the data
+fn missing_column(data: &DataFrame, normalization: &Normalization) -> Option<String> {
+    normalization
+        .rename
+        .keys()
+        .chain(normalization.cast.keys())
+        .find(|column| data.schema().field_with_unqualified_name(column).is_err())
+        .cloned()
+}
+
+// register the data with the normalization applied in place of the source table
+fn register_normalized(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    data: DataFrame,
+    normalization: &Normalization,
+) -> Result<()> {
+    let columns = data
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| {
+            let name = field.name();
+            let expr = match (normalization.cast.get(name), field.data_type()) {
+                (Some(data_type), _) => cast(ident(name), data_type.clone()),
+                (None, DataType::Timestamp(unit, None)) if normalization.timezone.is_some() => {
+                    let timezone = normalization.timezone.clone().map(Into::into);
+                    cast(ident(name), DataType::Timestamp(*unit, timezone))
+                }
+                _ => ident(name),
+            };
+            let alias = normalization.rename.get(name).unwrap_or(name);
+
+            expr.alias(alias)
+        })
+        .collect::<Vec<Expr>>();
+
+    let data = data.select(columns)?;
+
+    ctx.deregister_table(source_name)?;
+    ctx.register_table(source_name, data.into_view())?;
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use datafusion::{
+    arrow::datatypes::{Schema, SchemaRef},
+    datasource::MemTable,
+    error::DataFusionError,
+};
+use deltalake::{ObjectStore, Path};
+use orc_rust::ArrowReaderBuilder;
+use std::sync::Arc;
+
+use super::{error, Result};
+
+/// Read ORC files into an in-memory table
+/// The schema is read from the footer of the first file unless provided, the remaining files are read with the same schema
+pub(super) async fn memory_table(
+    source_name: &str,
+    files: &[(Arc<dyn ObjectStore>, Path)],
+    schema: Option<Schema>,
+) -> Result<MemTable> {
+    let mut schema: Option<SchemaRef> = schema.map(Arc::new);
+    let mut partitions = vec![];
+
+    for (store, path) in files {
+        let invalid = |message: String| {
+            error::Error::InvalidOrcFile(source_name.to_string(), path.to_string(), message)
+        };
+
+        let bytes = store
+            .get(path)
+            .await
+            .map_err(DataFusionError::from)?
+            .bytes()
+            .await
+            .map_err(DataFusionError::from)?;
+
+        let builder = ArrowReaderBuilder::try_new(bytes).map_err(|e| invalid(e.to_string()))?;
+        let builder = match schema.as_ref() {
+            Some(schema) => builder.with_schema(schema.clone()),
+            None => builder,
+        };
+        let file_schema = builder.schema();
+
+        let batches = builder
+            .build()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| invalid(e.to_string()))?;
+
+        schema.get_or_insert(file_schema);
+        partitions.push(batches);
+    }
+
+    let schema = schema.unwrap_or_else(|| Arc::new(Schema::empty()));
+
+    Ok(MemTable::try_new(schema, partitions)?)
+}
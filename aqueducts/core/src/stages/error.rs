@@ -1,5 +1,20 @@
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error("Condition of stage '{0}' did not evaluate to a single boolean value")]
+    InvalidCondition(String),
+    #[error("Explode column '{0}' not found in the stage result")]
+    MissingExplodeColumn(String),
+    #[error("Explode column '{0}' must be an array type, found {1}")]
+    InvalidExplodeColumn(String, datafusion::arrow::datatypes::DataType),
+    #[error("Repartition of stage '{0}' must be greater than 0")]
+    InvalidRepartition(String),
+    #[error("Invalid SQL in stage '{0}': {1}")]
+    Sql(String, String),
+    #[error("Data quality assertion '{1}' of stage '{0}' failed for {2} rows")]
+    AssertionFailed(String, String, usize),
+    #[error("Data quality assertion of stage '{0}' failed: produced {1} rows, expected {2} rows")]
+    RowCountAssertion(String, u64, String),
+
     // -- External
     #[error("ArrowError({0})")]
     ArrowError(#[from] datafusion::arrow::error::ArrowError),
@@ -1,9 +1,22 @@
 use datafusion::{
+    arrow::{
+        array::{Array, BooleanArray, RecordBatch},
+        csv,
+        datatypes::DataType,
+        error::ArrowError,
+        json::LineDelimitedWriter,
+        util::pretty::pretty_format_batches,
+    },
+    dataframe::DataFrame,
     datasource::MemTable,
     execution::context::{SQLOptions, SessionContext},
+    logical_expr::Partitioning,
+    physical_plan::{
+        collect_partitioned, repartition::RepartitionExec, Partitioning as PhysicalPartitioning,
+    },
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use tracing::instrument;
 
 pub(crate) mod error;
@@ -34,25 +47,139 @@ pub struct Stage {
     /// When set to 'true' the stage will pretty print the output schema of the executed query
     #[serde(default)]
     pub print_schema: bool,
+
+    /// SQL boolean expression that determines whether this stage is executed e.g. `'${env}' = 'prod'`
+    /// When it evaluates to `false` the stage is skipped and no table is registered for it, downstream stages referencing a skipped stage will fail
+    #[new(default)]
+    pub when: Option<String>,
+
+    /// Unnests an array column of the query result into one row per element
+    #[new(default)]
+    pub explode: Option<Explode>,
+
+    /// When set to `false` the result is not collected into memory but registered as a view that is evaluated lazily by every stage referencing it
+    /// Reduces memory usage for large pass-through results at the cost of re-executing the query for each reference, defaults to `true`
+    #[new(default)]
+    #[serde(default)]
+    pub materialize: Option<bool>,
+
+    /// Number of partitions the result is redistributed into (round robin) to control the parallelism of downstream stages, must be greater than 0
+    /// Applied to the result of the query (after `explode`) before it is materialized, for stages that are not materialized it is only a hint the optimizer may drop
+    #[new(default)]
+    #[serde(default)]
+    pub repartition: Option<usize>,
+
+    /// Maximum number of seconds the stage may run, the pipeline is aborted when it is exceeded
+    /// Applies to each stage individually, also when it runs in parallel with other stages, defaults to no timeout
+    /// The timeout is checked whenever the execution yields to the runtime, a stage executed in a single partition may overrun it
+    #[new(default)]
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+
+    /// Data quality assertions evaluated against the result of the stage, the pipeline is aborted when one of them fails
+    #[new(default)]
+    #[serde(default)]
+    pub assertions: Option<Assertions>,
+
+    /// Names of the sources and prior stages this stage depends on
+    /// When set, the listed stages are merged with the tables referenced by the parsed query (instead of scanning the query text) to decide when the results of prior stages are released
+    /// Use it to keep stages registered that the query doesn't reference directly, stages referenced by the query don't have to be repeated
+    #[new(default)]
+    #[serde(default)]
+    pub depends_on: Option<Vec<String>>,
+
+    /// Arbitrary key/value pairs attached to the stage e.g. to correlate it with a data catalog entry
+    /// Not interpreted by the pipeline, included in the log events emitted when the stage starts and finishes
+    #[new(default)]
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Data quality assertions for the result of a stage
+#[derive(Debug, Clone, Default, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct Assertions {
+    /// SQL boolean expressions that have to evaluate to `true` for every row of the result e.g. `id IS NOT NULL`
+    /// Rows for which an expression evaluates to `false` or `NULL` fail the assertion
+    #[new(default)]
+    #[serde(default)]
+    pub expressions: Vec<String>,
+
+    /// Minimum number of rows the result has to contain
+    #[new(default)]
+    #[serde(default)]
+    pub min_rows: Option<u64>,
+
+    /// Maximum number of rows the result may contain
+    #[new(default)]
+    #[serde(default)]
+    pub max_rows: Option<u64>,
+}
+
+/// Definition for unnesting an array column (`List`, `LargeList` or `FixedSizeList`) into rows
+#[derive(Debug, Clone, Serialize, Deserialize, derive_new::new)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+pub struct Explode {
+    /// Name of the array column in the result of the stage query
+    pub column: String,
+
+    /// Name of the exploded column, defaults to the name of the array column
+    pub alias: Option<String>,
+}
+
+/// Evaluate the `when` condition of a stage, stages without a condition are always executed
+#[instrument(skip(ctx, stage), err)]
+pub async fn evaluate_condition(ctx: Arc<SessionContext>, stage: &Stage) -> Result<bool> {
+    let Some(condition) = stage.when.as_deref() else {
+        return Ok(true);
+    };
+
+    let query = format!("SELECT ({condition}) AS condition");
+    let batches = ctx
+        .sql_with_options(query.as_str(), sql_options())
+        .await?
+        .collect()
+        .await?;
+
+    batches
+        .first()
+        .filter(|batch| batch.num_rows() == 1)
+        .and_then(|batch| batch.column(0).as_any().downcast_ref::<BooleanArray>())
+        .filter(|condition| !condition.is_null(0))
+        .map(|condition| condition.value(0))
+        .ok_or_else(|| error::Error::InvalidCondition(stage.name.clone()))
 }
 
 /// Process a stage in the Aqueduct pipeline
 /// The result of the operation will be registered within the `SessionContext` as an
-/// in-memory table using the stages name as the table name
+/// in-memory table using the stages name as the table name, or as a view if `materialize` is disabled
 /// Does not allow for ddl/dml queries or SQL statements (e.g. SET VARIABLE, CREATE TABLE, etc.)
+/// Returns the number of rows produced by the stage, which is unknown for stages that are not materialized
 #[instrument(skip(ctx, stage), err)]
-pub async fn process_stage(ctx: Arc<SessionContext>, stage: Stage) -> Result<()> {
-    let options = SQLOptions::new()
-        .with_allow_ddl(false)
-        .with_allow_dml(false)
-        .with_allow_statements(false);
+pub async fn process_stage(ctx: Arc<SessionContext>, stage: Stage) -> Result<Option<u64>> {
+    // pipelines reject this before any stage runs, checked again for stages processed on their own
+    if stage.repartition == Some(0) {
+        return Err(error::Error::InvalidRepartition(stage.name));
+    }
 
     let result = ctx
-        .sql_with_options(stage.query.as_str(), options)
-        .await?
-        .cache()
+        .sql_with_options(stage.query.as_str(), sql_options())
         .await?;
 
+    let result = match &stage.explode {
+        Some(explode) => explode_column(result, explode)?,
+        None => result,
+    };
+    let materialize = stage.materialize.unwrap_or(true);
+    let result = match (materialize, stage.repartition) {
+        (true, Some(partitions)) => cache_repartitioned(result, partitions).await?,
+        (true, None) => result.cache().await?,
+        (false, Some(partitions)) => {
+            result.repartition(Partitioning::RoundRobinBatch(partitions))?
+        }
+        (false, None) => result,
+    };
+
     if stage.explain || stage.explain_analyze {
         println!("\n*** Stage query plan: {} ***", stage.name.as_str());
         result
@@ -63,13 +190,20 @@ pub async fn process_stage(ctx: Arc<SessionContext>, stage: Stage) -> Result<()>
         println!();
     }
 
-    match stage.show {
-        Some(0) => {
+    let output_format = ctx
+        .state()
+        .config()
+        .get_extension::<OutputFormat>()
+        .map(|format| *format)
+        .unwrap_or_default();
+
+    match (stage.show, output_format) {
+        (Some(0), OutputFormat::Table) => {
             println!("\n*** Stage output data: {} ***", stage.name.as_str());
             result.clone().show().await?;
             println!();
         }
-        Some(limit) => {
+        (Some(limit), OutputFormat::Table) => {
             println!(
                 "\n*** Stage output data (limit {limit}): {} ***",
                 stage.name.as_str()
@@ -77,6 +211,15 @@ pub async fn process_stage(ctx: Arc<SessionContext>, stage: Stage) -> Result<()>
             result.clone().show_limit(limit).await?;
             println!();
         }
+        (Some(limit), format) => {
+            let output = match limit {
+                0 => result.clone(),
+                limit => result.clone().limit(0, Some(limit))?,
+            };
+            let batches = output.collect().await?;
+
+            print!("{}", format_batches(batches.as_slice(), format)?);
+        }
         _ => (),
     };
 
@@ -88,11 +231,245 @@ pub async fn process_stage(ctx: Arc<SessionContext>, stage: Stage) -> Result<()>
         );
     }
 
+    if !materialize {
+        if let Some(assertions) = &stage.assertions {
+            check_assertions(ctx.clone(), &stage.name, assertions, result.clone(), None).await?;
+        }
+
+        ctx.register_table(stage.name.as_str(), result.into_view())?;
+
+        return Ok(None);
+    }
+
     let schema = result.schema().clone();
     let partitioned = result.collect_partitioned().await?;
-    let table = MemTable::try_new(Arc::new(schema.as_arrow().clone()), partitioned)?;
+    let row_count = partitioned
+        .iter()
+        .flatten()
+        .map(|batch| batch.num_rows() as u64)
+        .sum();
+    let table = Arc::new(MemTable::try_new(
+        Arc::new(schema.as_arrow().clone()),
+        partitioned,
+    )?);
+
+    if let Some(assertions) = &stage.assertions {
+        let data = ctx.read_table(table.clone())?;
+        check_assertions(ctx.clone(), &stage.name, assertions, data, Some(row_count)).await?;
+    }
+
+    ctx.register_table(stage.name.as_str(), table)?;
+
+    Ok(Some(row_count))
+}
+
+/// Format used to print the output of stages that set `show`
+/// Provide it as an extension of the `SessionConfig` of the context passed to `run_pipeline`, defaults to `Table`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Pretty printed ASCII table
+    #[default]
+    Table,
+    /// Newline delimited JSON objects
+    Json,
+    /// CSV including a header row
+    Csv,
+}
 
-    ctx.register_table(stage.name.as_str(), Arc::new(table))?;
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "invalid output format '{s}', expected one of table, json, csv"
+            )),
+        }
+    }
+}
+
+/// Render record batches in the provided output format
+pub fn format_batches(
+    batches: &[RecordBatch],
+    format: OutputFormat,
+) -> std::result::Result<String, ArrowError> {
+    let mut buffer = vec![];
+
+    match format {
+        OutputFormat::Table => {
+            return Ok(format!("{}\n", pretty_format_batches(batches)?));
+        }
+        OutputFormat::Json => {
+            let mut writer = LineDelimitedWriter::new(&mut buffer);
+            writer.write_batches(batches.iter().collect::<Vec<_>>().as_slice())?;
+            writer.finish()?;
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::new(&mut buffer);
+            for batch in batches {
+                writer.write(batch)?;
+            }
+        }
+    }
+
+    Ok(String::from_utf8_lossy(buffer.as_slice()).into_owned())
+}
+
+/// Plan the query of an Aqueduct stage without executing it
+/// The unmaterialized result is registered as a view so downstream stages can be planned against it
+/// Returns the query plan of the stage as produced by `EXPLAIN`
+#[instrument(skip(ctx, stage), err)]
+pub async fn plan_stage(ctx: Arc<SessionContext>, stage: &Stage) -> Result<String> {
+    let result = ctx
+        .sql_with_options(stage.query.as_str(), sql_options())
+        .await?;
+
+    let result = match &stage.explode {
+        Some(explode) => explode_column(result, explode)?,
+        None => result,
+    };
+
+    let plan = result.clone().explain(false, false)?.collect().await?;
+
+    ctx.register_table(stage.name.as_str(), result.into_view())?;
+
+    Ok(pretty_format_batches(plan.as_slice())?.to_string())
+}
+
+/// Parse the query of a stage without planning it, DDL, DML, statements and multiple queries are rejected
+/// Returns the names of the tables referenced by the query, CTEs are not included
+pub fn validate_stage_sql(stage: &Stage) -> Result<Vec<String>> {
+    use datafusion::{
+        catalog_common::resolve_table_references,
+        sql::{
+            parser::{DFParser, Statement},
+            sqlparser::ast,
+        },
+    };
+
+    let invalid = |message: String| error::Error::Sql(stage.name.clone(), message);
+
+    let mut statements =
+        DFParser::parse_sql(stage.query.as_str()).map_err(|e| invalid(e.to_string()))?;
+    if statements.len() != 1 {
+        return Err(invalid(format!(
+            "expected a single query, found {} statements",
+            statements.len()
+        )));
+    }
+
+    let statement = statements.pop_front().expect("statement not found");
+    let is_query = matches!(
+        &statement,
+        Statement::Statement(statement) if matches!(statement.as_ref(), ast::Statement::Query(_))
+    );
+    if !is_query {
+        return Err(invalid("only SELECT queries are allowed".into()));
+    }
+
+    let (tables, _) = resolve_table_references(&statement, true)?;
+
+    Ok(tables
+        .into_iter()
+        .map(|table| table.table().to_string())
+        .collect())
+}
+
+// evaluate the assertions against the result of a stage before it is registered
+// the row count is computed from the data if it wasn't already collected
+async fn check_assertions(
+    ctx: Arc<SessionContext>,
+    stage_name: &str,
+    assertions: &Assertions,
+    data: DataFrame,
+    row_count: Option<u64>,
+) -> Result<()> {
+    if assertions.min_rows.is_some() || assertions.max_rows.is_some() {
+        let row_count = match row_count {
+            Some(row_count) => row_count,
+            None => data.clone().count().await? as u64,
+        };
+        let in_range = assertions.min_rows.is_none_or(|min| row_count >= min)
+            && assertions.max_rows.is_none_or(|max| row_count <= max);
+
+        if !in_range {
+            let min = assertions.min_rows.map(|min| min.to_string());
+            let max = assertions.max_rows.map(|max| format!("={max}"));
+
+            return Err(error::Error::RowCountAssertion(
+                stage_name.to_string(),
+                row_count,
+                format!("{}..{}", min.unwrap_or_default(), max.unwrap_or_default()),
+            ));
+        }
+    }
+
+    for expression in assertions.expressions.iter() {
+        let expr = ctx.parse_sql_expr(expression.as_str(), data.schema())?;
+        let failed_rows = data.clone().filter(expr.is_not_true())?.count().await?;
+
+        if failed_rows > 0 {
+            return Err(error::Error::AssertionFailed(
+                stage_name.to_string(),
+                expression.clone(),
+                failed_rows,
+            ));
+        }
+    }
 
     Ok(())
 }
+
+// stages are restricted to queries, DDL, DML and statements are rejected
+fn sql_options() -> SQLOptions {
+    SQLOptions::new()
+        .with_allow_ddl(false)
+        .with_allow_dml(false)
+        .with_allow_statements(false)
+}
+
+// materialize the result redistributed round robin into the given number of partitions
+// the repartition is added to the optimized plan as the optimizer drops repartitions it doesn't consider beneficial
+async fn cache_repartitioned(data: DataFrame, partitions: usize) -> Result<DataFrame> {
+    let task_ctx = Arc::new(data.task_ctx());
+    let (state, plan) = data.into_parts();
+
+    let plan = state.create_physical_plan(&plan).await?;
+    let schema = plan.schema();
+    let plan = Arc::new(RepartitionExec::try_new(
+        plan,
+        PhysicalPartitioning::RoundRobinBatch(partitions),
+    )?);
+
+    let table = MemTable::try_new(schema, collect_partitioned(plan, task_ctx).await?)?;
+
+    Ok(SessionContext::new_with_state(state).read_table(Arc::new(table))?)
+}
+
+fn explode_column(data: DataFrame, explode: &Explode) -> Result<DataFrame> {
+    let column = explode.column.as_str();
+    let field = data
+        .schema()
+        .field_with_unqualified_name(column)
+        .map_err(|_| error::Error::MissingExplodeColumn(column.to_string()))?;
+
+    match field.data_type() {
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) => (),
+        data_type => {
+            return Err(error::Error::InvalidExplodeColumn(
+                column.to_string(),
+                data_type.clone(),
+            ))
+        }
+    }
+
+    let data = data.unnest_columns(&[column])?;
+
+    match explode.alias.as_deref() {
+        Some(alias) => Ok(data.with_column_renamed(column, alias)?),
+        None => Ok(data),
+    }
+}
@@ -0,0 +1,61 @@
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+};
+use url::Url;
+
+// serve HTTP responses with the given status and body on a random local port, the bodies of the received requests are sent to the channel
+pub(crate) async fn serve(
+    status: &'static str,
+    body: &'static str,
+) -> (Url, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = vec![];
+            let mut buffer = [0; 4096];
+
+            // read until the headers and the announced body length were received
+            let received = loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+
+                let text = String::from_utf8_lossy(&request).into_owned();
+                let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                    if read == 0 {
+                        break String::new();
+                    }
+                    continue;
+                };
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or_default();
+
+                if body.len() >= length || read == 0 {
+                    break body.to_string();
+                }
+            };
+            let _ = sender.send(received);
+
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    (
+        Url::parse(format!("http://{address}/").as_str()).unwrap(),
+        receiver,
+    )
+}
@@ -0,0 +1,95 @@
+use datafusion::{
+    arrow::{
+        array::{StringArray, UInt64Array},
+        datatypes::DataType,
+    },
+    error::DataFusionError,
+    execution::context::SessionContext,
+    logical_expr::{create_udf, ColumnarValue, ScalarUDF, Volatility},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Bundle of SQL functions that is registered for the stages of an `Aqueduct`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema_gen", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UdfBundle {
+    /// Hash functions with values that are stable across runs and platforms
+    /// `fnv1a_64(value)` returns the 64 bit FNV-1a hash of a string as `UInt64`
+    Hash,
+
+    /// JSON functions of [datafusion-functions-json](https://github.com/datafusion-contrib/datafusion-functions-json) e.g. `json_get_str(json, key)`
+    /// Requires the `json_functions` feature
+    #[cfg(feature = "json_functions")]
+    Json,
+}
+
+/// Register the functions of the bundles on the context
+pub fn register_udfs(ctx: &SessionContext, bundles: &[UdfBundle]) -> Result<(), DataFusionError> {
+    for bundle in bundles {
+        info!("Registering UDF bundle {bundle:?}");
+
+        match bundle {
+            UdfBundle::Hash => ctx.register_udf(fnv1a_64_udf()),
+            #[cfg(feature = "json_functions")]
+            UdfBundle::Json => {
+                datafusion_functions_json::register_all(&mut *ctx.state_ref().write())?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn fnv1a_64_udf() -> ScalarUDF {
+    create_udf(
+        "fnv1a_64",
+        vec![DataType::Utf8],
+        DataType::UInt64,
+        Volatility::Immutable,
+        Arc::new(|args: &[ColumnarValue]| {
+            let arrays = ColumnarValue::values_to_arrays(args)?;
+            let values = arrays[0]
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| {
+                    DataFusionError::Execution("fnv1a_64 expects a string argument".into())
+                })?;
+
+            let hashes = values
+                .iter()
+                .map(|value| value.map(fnv1a_64))
+                .collect::<UInt64Array>();
+
+            Ok(ColumnarValue::Array(Arc::new(hashes)))
+        }),
+    )
+}
+
+// 64 bit FNV-1a hash of the UTF-8 bytes of a string
+fn fnv1a_64(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a_64_ok() {
+        assert_eq!(0xcbf29ce484222325, fnv1a_64(""));
+        assert_eq!(0xaf63dc4c8601ec8c, fnv1a_64("a"));
+    }
+
+    #[test]
+    fn test_deserialize_unknown_bundle_err() {
+        let result = serde_json::from_str::<Vec<UdfBundle>>(r#"["hash", "md5_custom"]"#);
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("unknown variant `md5_custom`"));
+    }
+}
@@ -0,0 +1,11 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid namespace '{0}', expected a dot separated namespace e.g. 'sales.raw'")]
+    InvalidNamespace(String),
+
+    // -- External
+    #[error("DataFusionError({0})")]
+    DataFusionError(#[from] datafusion::error::DataFusionError),
+    #[error("IcebergError({0})")]
+    IcebergError(#[from] iceberg::Error),
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, sync::Arc};
+
+use datafusion::datasource::MemTable;
+use datafusion::execution::context::SessionContext;
+use futures::TryStreamExt;
+use iceberg::arrow::schema_to_arrow_schema;
+use iceberg::{Catalog, NamespaceIdent, TableIdent};
+use iceberg_catalog_rest::{RestCatalog, RestCatalogConfig};
+use tracing::info;
+
+pub mod error;
+
+pub type Result<T> = core::result::Result<T, error::Error>;
+
+/// Options for loading an Iceberg table from a REST catalog
+#[derive(Debug, Clone)]
+pub struct IcebergReadOptions<'a> {
+    /// URI of the REST catalog e.g. `http://localhost:8181`
+    pub catalog_uri: &'a str,
+    /// Warehouse identifier passed to the REST catalog
+    pub warehouse: Option<&'a str>,
+    /// Dot separated namespace of the table e.g. `sales.raw`
+    pub namespace: &'a str,
+    pub table: &'a str,
+    /// Snapshot to read, defaults to the current snapshot of the table
+    pub snapshot_id: Option<i64>,
+    /// Catalog and FileIO properties (e.g. `s3.endpoint`, `s3.access-key-id`)
+    pub properties: &'a HashMap<String, String>,
+}
+
+/// Register an Iceberg table loaded from a REST catalog using [iceberg-rust](https://docs.rs/iceberg) as an in-memory table
+/// ```rust,ignore
+/// use datafusion::prelude::SessionContext;
+///
+/// let ctx = Arc::new(SessionContext::new());
+/// let properties = HashMap::new();
+/// let options = IcebergReadOptions {
+///     catalog_uri: "http://localhost:8181",
+///     warehouse: None,
+///     namespace: "sales.raw",
+///     table: "orders",
+///     snapshot_id: None,
+///     properties: &properties,
+/// };
+///
+/// register_iceberg_source(ctx.clone(), "orders", options).await.unwrap();
+///
+/// let df = ctx.sql("SELECT * FROM orders").await.unwrap();
+/// df.show().await.unwrap();
+/// ```
+///
+/// The snapshot is scanned into memory when the source is registered, the data files are read through the FileIO of the table
+/// Object store credentials have to be provided as `properties`, see [`file_io_properties`]
+pub async fn register_iceberg_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    options: IcebergReadOptions<'_>,
+) -> Result<()> {
+    let parts = options.namespace.split('.').collect::<Vec<_>>();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(error::Error::InvalidNamespace(
+            options.namespace.to_string(),
+        ));
+    }
+    let namespace = NamespaceIdent::from_strs(parts)?;
+
+    let config = RestCatalogConfig::builder()
+        .uri(options.catalog_uri.to_string())
+        .warehouse_opt(options.warehouse.map(ToString::to_string))
+        .props(options.properties.clone())
+        .build();
+    let catalog = Arc::new(RestCatalog::new(config));
+
+    let table_ident = TableIdent::new(namespace, options.table.to_string());
+    let table = catalog.load_table(&table_ident).await?;
+
+    let scan = match options.snapshot_id {
+        Some(snapshot_id) => table.scan().snapshot_id(snapshot_id).build()?,
+        None => table.scan().build()?,
+    };
+    let batches = scan.to_arrow().await?.try_collect::<Vec<_>>().await?;
+
+    // an empty scan yields no batches to take the schema from
+    let schema = match batches.first() {
+        Some(batch) => batch.schema(),
+        None => Arc::new(schema_to_arrow_schema(table.metadata().current_schema())?),
+    };
+
+    info!(
+        "Registering Iceberg table '{}.{}' as '{source_name}' ({} rows)",
+        options.namespace,
+        options.table,
+        batches.iter().map(|batch| batch.num_rows()).sum::<usize>()
+    );
+    let table = MemTable::try_new(schema, vec![batches])?;
+    ctx.register_table(source_name, Arc::new(table))?;
+
+    Ok(())
+}
+
+/// Map object store storage options (e.g. `aws_access_key_id`) to the FileIO properties of iceberg-rust (e.g. `s3.access-key-id`)
+/// Keys are matched case insensitive, keys without a FileIO equivalent (including FileIO properties) are passed through unchanged
+pub fn file_io_properties(storage_options: &HashMap<String, String>) -> HashMap<String, String> {
+    storage_options
+        .iter()
+        .map(|(key, value)| {
+            let property = match key.to_ascii_lowercase().as_str() {
+                "aws_access_key_id" | "access_key_id" => "s3.access-key-id",
+                "aws_secret_access_key" | "secret_access_key" => "s3.secret-access-key",
+                "aws_session_token" | "aws_token" | "session_token" | "token" => "s3.session-token",
+                "aws_region" | "region" => "s3.region",
+                "aws_endpoint" | "aws_endpoint_url" | "endpoint" | "endpoint_url" => "s3.endpoint",
+                "aws_virtual_hosted_style_request" | "virtual_hosted_style_request" => {
+                    let path_style = !value.eq_ignore_ascii_case("true");
+                    return ("s3.path-style-access".to_string(), path_style.to_string());
+                }
+                "google_service_account_key" | "service_account_key" => "gcs.credentials-json",
+                _ => return (key.clone(), value.clone()),
+            };
+
+            (property.to_string(), value.clone())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_iceberg_source_invalid_namespace_err() {
+        let ctx = Arc::new(SessionContext::new());
+        let properties = HashMap::new();
+        let options = IcebergReadOptions {
+            catalog_uri: "http://localhost:8181",
+            warehouse: None,
+            namespace: "sales..raw",
+            table: "orders",
+            snapshot_id: None,
+            properties: &properties,
+        };
+
+        let result = register_iceberg_source(ctx, "orders", options).await;
+
+        assert!(matches!(result, Err(error::Error::InvalidNamespace(_))));
+    }
+
+    #[test]
+    fn test_file_io_properties_ok() {
+        let storage_options = HashMap::from_iter(vec![
+            ("AWS_ACCESS_KEY_ID".to_string(), "key".to_string()),
+            ("aws_secret_access_key".to_string(), "secret".to_string()),
+            ("aws_region".to_string(), "eu-central-1".to_string()),
+            (
+                "aws_endpoint".to_string(),
+                "http://localhost:9000".to_string(),
+            ),
+            (
+                "aws_virtual_hosted_style_request".to_string(),
+                "false".to_string(),
+            ),
+            ("s3.sse.type".to_string(), "s3".to_string()),
+        ]);
+
+        let properties = file_io_properties(&storage_options);
+
+        assert_eq!(
+            HashMap::from_iter(vec![
+                ("s3.access-key-id".to_string(), "key".to_string()),
+                ("s3.secret-access-key".to_string(), "secret".to_string()),
+                ("s3.region".to_string(), "eu-central-1".to_string()),
+                (
+                    "s3.endpoint".to_string(),
+                    "http://localhost:9000".to_string()
+                ),
+                ("s3.path-style-access".to_string(), "true".to_string()),
+                ("s3.sse.type".to_string(), "s3".to_string()),
+            ]),
+            properties
+        );
+    }
+}
@@ -0,0 +1,17 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Max messages must be greater than 0")]
+    InvalidMaxMessages,
+    #[error("No messages could be deserialized from topic '{0}'")]
+    NoMessages(String),
+
+    // -- External
+    #[error("ArrowError({0})")]
+    ArrowError(#[from] datafusion::arrow::error::ArrowError),
+    #[error("DataFusionError({0})")]
+    DataFusionError(#[from] datafusion::error::DataFusionError),
+    #[error("KafkaError({0})")]
+    KafkaError(#[from] rdkafka::error::KafkaError),
+    #[error("AvroError({0})")]
+    AvroError(#[from] Box<apache_avro::Error>),
+}
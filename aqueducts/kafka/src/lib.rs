@@ -0,0 +1,409 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::json::reader::{infer_json_schema_from_iterator, ReaderBuilder};
+use datafusion::datasource::MemTable;
+use datafusion::execution::context::SessionContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::Message;
+use rdkafka::{Offset, TopicPartitionList};
+use tracing::{debug, info, warn};
+
+pub mod error;
+
+pub type Result<T> = core::result::Result<T, error::Error>;
+
+const DECODER_BATCH_SIZE: usize = 8192;
+const METADATA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Offset to start consuming the topic from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartingOffset {
+    /// Start from the earliest available offset when the consumer group has no committed offset
+    Earliest,
+    /// Start from the latest offset when the consumer group has no committed offset
+    Latest,
+    /// Start from the first offset with a timestamp (milliseconds since epoch) equal to or greater than the provided one, ignoring committed offsets
+    Timestamp(i64),
+}
+
+/// Format of the message values
+#[derive(Debug, Clone)]
+pub enum ValueFormat {
+    /// JSON objects
+    Json,
+    /// Avro datums encoded with the provided Avro schema (JSON schema definition)
+    Avro(String),
+}
+
+/// Options for consuming a bounded window of messages from a Kafka topic
+#[derive(Debug, Clone)]
+pub struct KafkaReadOptions<'a> {
+    pub brokers: &'a str,
+    pub topic: &'a str,
+    pub group_id: &'a str,
+    pub starting_offset: StartingOffset,
+    /// Stop consuming once this number of messages was polled
+    pub max_messages: Option<usize>,
+    /// Stop consuming once no message was received for this duration
+    pub poll_timeout: Duration,
+    pub value_format: ValueFormat,
+    /// Additional librdkafka configuration (e.g. `security.protocol`, `sasl.mechanisms`)
+    pub consumer_options: &'a HashMap<String, String>,
+}
+
+/// Offsets of the messages consumed from a topic, see [`ConsumedOffsets::commit`]
+#[derive(Debug, Clone)]
+pub struct ConsumedOffsets {
+    brokers: String,
+    topic: String,
+    group_id: String,
+    consumer_options: HashMap<String, String>,
+    // next offset to consume per partition
+    offsets: BTreeMap<i32, i64>,
+}
+
+impl ConsumedOffsets {
+    fn new(options: &KafkaReadOptions<'_>) -> Self {
+        Self {
+            brokers: options.brokers.to_string(),
+            topic: options.topic.to_string(),
+            group_id: options.group_id.to_string(),
+            consumer_options: options.consumer_options.clone(),
+            offsets: BTreeMap::new(),
+        }
+    }
+
+    // record a consumed message, the committed offset of a partition is the one following its latest consumed message
+    fn record(&mut self, partition: i32, offset: i64) {
+        let next = self.offsets.entry(partition).or_insert(offset + 1);
+        *next = (*next).max(offset + 1);
+    }
+
+    fn topic_partition_list(&self) -> Result<TopicPartitionList> {
+        let mut partitions = TopicPartitionList::new();
+        for (partition, offset) in self.offsets.iter() {
+            partitions.add_partition_offset(
+                self.topic.as_str(),
+                *partition,
+                Offset::Offset(*offset),
+            )?;
+        }
+
+        Ok(partitions)
+    }
+
+    /// Commit the consumed offsets for the consumer group
+    /// Call it once the consumed messages were processed, messages of offsets that are never committed are consumed again by the next read (at-least-once delivery)
+    pub fn commit(&self) -> Result<()> {
+        if self.offsets.is_empty() {
+            return Ok(());
+        }
+
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", self.brokers.as_str())
+            .set("group.id", self.group_id.as_str())
+            .set("enable.auto.commit", "false");
+        for (key, value) in self.consumer_options.iter() {
+            config.set(key, value);
+        }
+
+        let consumer: BaseConsumer = config.create()?;
+        consumer.commit(&self.topic_partition_list()?, CommitMode::Sync)?;
+
+        info!(
+            "Committed offsets of {} partitions of topic '{}' for consumer group '{}'",
+            self.offsets.len(),
+            self.topic,
+            self.group_id
+        );
+
+        Ok(())
+    }
+}
+
+/// Register a table from a bounded window of Kafka messages using [rdkafka](https://docs.rs/rdkafka)
+/// ```rust,ignore
+/// use datafusion::prelude::SessionContext;
+///
+/// let ctx = Arc::new(SessionContext::new());
+/// let consumer_options = HashMap::new();
+/// let options = KafkaReadOptions {
+///     brokers: "localhost:9092",
+///     topic: "events",
+///     group_id: "aqueducts",
+///     starting_offset: StartingOffset::Earliest,
+///     max_messages: Some(10_000),
+///     poll_timeout: Duration::from_secs(5),
+///     value_format: ValueFormat::Json,
+///     consumer_options: &consumer_options,
+/// };
+///
+/// let offsets = register_kafka_source(ctx.clone(), "events", options).await.unwrap();
+///
+/// let df = ctx.sql("SELECT * FROM events").await.unwrap();
+/// df.show().await.unwrap();
+///
+/// offsets.commit().unwrap();
+/// ```
+///
+/// Consumption stops when `max_messages` were polled or no message was received within `poll_timeout`
+/// The consumed offsets are not committed, the returned `ConsumedOffsets` should be committed once the messages were processed
+/// Messages that cannot be deserialized are skipped and logged with their partition and offset
+pub async fn register_kafka_source(
+    ctx: Arc<SessionContext>,
+    source_name: &str,
+    options: KafkaReadOptions<'_>,
+) -> Result<ConsumedOffsets> {
+    if options.max_messages == Some(0) {
+        return Err(error::Error::InvalidMaxMessages);
+    }
+
+    let avro_schema = match &options.value_format {
+        ValueFormat::Avro(schema) => {
+            Some(apache_avro::Schema::parse_str(schema).map_err(Box::new)?)
+        }
+        ValueFormat::Json => None,
+    };
+
+    let consumer = create_consumer(&options)?;
+
+    let mut offsets = ConsumedOffsets::new(&options);
+    let mut values = vec![];
+    let mut consumed = 0;
+    let mut failed = 0;
+
+    while options.max_messages.is_none_or(|max| consumed < max) {
+        let message = match tokio::time::timeout(options.poll_timeout, consumer.recv()).await {
+            Err(_) => {
+                debug!(
+                    "No message received from topic '{}' within {:?}, stopping",
+                    options.topic, options.poll_timeout
+                );
+                break;
+            }
+            Ok(message) => message?,
+        };
+        consumed += 1;
+        offsets.record(message.partition(), message.offset());
+
+        match decode(message.payload(), avro_schema.as_ref()) {
+            Ok(value) => values.push(value),
+            Err(err) => {
+                failed += 1;
+                warn!(
+                    "Skipping message from topic '{}' partition {} offset {}: {err}",
+                    options.topic,
+                    message.partition(),
+                    message.offset()
+                );
+            }
+        }
+    }
+
+    consumer.unsubscribe();
+
+    info!(
+        "Consumed {consumed} messages from topic '{}' ({failed} failed to deserialize)",
+        options.topic
+    );
+
+    if values.is_empty() {
+        return Err(error::Error::NoMessages(options.topic.to_string()));
+    }
+
+    let batches = into_batches(values)?;
+    let schema = batches[0].schema();
+    let table = MemTable::try_new(schema, vec![batches])?;
+
+    ctx.register_table(source_name, Arc::new(table))?;
+
+    Ok(offsets)
+}
+
+fn create_consumer(options: &KafkaReadOptions<'_>) -> Result<StreamConsumer> {
+    let auto_offset_reset = match options.starting_offset {
+        StartingOffset::Earliest => "earliest",
+        StartingOffset::Latest | StartingOffset::Timestamp(_) => "latest",
+    };
+
+    let mut config = ClientConfig::new();
+    config
+        .set("bootstrap.servers", options.brokers)
+        .set("group.id", options.group_id)
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", auto_offset_reset);
+    for (key, value) in options.consumer_options.iter() {
+        config.set(key, value);
+    }
+
+    let consumer: StreamConsumer = config.create()?;
+
+    match options.starting_offset {
+        StartingOffset::Timestamp(timestamp) => {
+            let metadata = consumer.fetch_metadata(Some(options.topic), METADATA_TIMEOUT)?;
+
+            let mut partitions = TopicPartitionList::new();
+            for partition in metadata
+                .topics()
+                .iter()
+                .filter(|topic| topic.name() == options.topic)
+                .flat_map(|topic| topic.partitions())
+            {
+                partitions.add_partition_offset(
+                    options.topic,
+                    partition.id(),
+                    Offset::Offset(timestamp),
+                )?;
+            }
+
+            let offsets = consumer.offsets_for_times(partitions, METADATA_TIMEOUT)?;
+            consumer.assign(&offsets)?;
+        }
+        StartingOffset::Earliest | StartingOffset::Latest => {
+            consumer.subscribe(&[options.topic])?;
+        }
+    }
+
+    Ok(consumer)
+}
+
+fn decode(
+    payload: Option<&[u8]>,
+    avro_schema: Option<&apache_avro::Schema>,
+) -> std::result::Result<serde_json::Value, String> {
+    let payload = payload.ok_or_else(|| "message has no payload".to_string())?;
+
+    let value = match avro_schema {
+        Some(schema) => {
+            let mut reader = payload;
+            let value = apache_avro::from_avro_datum(schema, &mut reader, None)
+                .map_err(|e| e.to_string())?;
+
+            serde_json::Value::try_from(value).map_err(|e| e.to_string())?
+        }
+        None => serde_json::from_slice::<serde_json::Value>(payload).map_err(|e| e.to_string())?,
+    };
+
+    match value {
+        serde_json::Value::Object(_) => Ok(value),
+        _ => Err("message value is not an object".to_string()),
+    }
+}
+
+fn into_batches(values: Vec<serde_json::Value>) -> Result<Vec<RecordBatch>> {
+    let schema = Arc::new(infer_json_schema_from_iterator(values.iter().map(Ok))?);
+
+    let mut decoder = ReaderBuilder::new(schema)
+        .with_batch_size(DECODER_BATCH_SIZE)
+        .build_decoder()?;
+
+    let mut batches = vec![];
+    for chunk in values.chunks(DECODER_BATCH_SIZE) {
+        decoder.serialize(chunk)?;
+
+        if let Some(batch) = decoder.flush()? {
+            batches.push(batch);
+        }
+    }
+
+    Ok(batches)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::assert_batches_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_json_ok() {
+        let value = decode(Some(br#"{"id": 1, "name": "a"}"#), None).unwrap();
+
+        assert_eq!(serde_json::json!({"id": 1, "name": "a"}), value);
+    }
+
+    #[test]
+    fn test_decode_json_err() {
+        assert!(decode(Some(b"not json"), None).is_err());
+        assert!(decode(Some(b"[1, 2]"), None).is_err());
+        assert!(decode(None, None).is_err());
+    }
+
+    #[test]
+    fn test_decode_avro_ok() {
+        let schema = apache_avro::Schema::parse_str(
+            r#"{"type": "record", "name": "event", "fields": [{"name": "id", "type": "long"}, {"name": "name", "type": "string"}]}"#,
+        )
+        .unwrap();
+        let record = apache_avro::types::Value::Record(vec![
+            ("id".into(), apache_avro::types::Value::Long(1)),
+            ("name".into(), apache_avro::types::Value::String("a".into())),
+        ]);
+        let datum = apache_avro::to_avro_datum(&schema, record).unwrap();
+
+        let value = decode(Some(datum.as_slice()), Some(&schema)).unwrap();
+
+        assert_eq!(serde_json::json!({"id": 1, "name": "a"}), value);
+    }
+
+    #[test]
+    fn test_consumed_offsets_record_ok() {
+        let consumer_options = HashMap::new();
+        let mut offsets = ConsumedOffsets::new(&KafkaReadOptions {
+            brokers: "localhost:9092",
+            topic: "events",
+            group_id: "aqueducts",
+            starting_offset: StartingOffset::Earliest,
+            max_messages: None,
+            poll_timeout: Duration::from_secs(5),
+            value_format: ValueFormat::Json,
+            consumer_options: &consumer_options,
+        });
+
+        offsets.record(0, 4);
+        offsets.record(1, 10);
+        offsets.record(0, 5);
+        offsets.record(0, 3);
+
+        assert_eq!(BTreeMap::from([(0, 6), (1, 11)]), offsets.offsets);
+
+        let partitions = offsets.topic_partition_list().unwrap();
+        assert_eq!(
+            Offset::Offset(6),
+            partitions.find_partition("events", 0).unwrap().offset()
+        );
+        assert_eq!(
+            Offset::Offset(11),
+            partitions.find_partition("events", 1).unwrap().offset()
+        );
+    }
+
+    #[test]
+    fn test_into_batches_ok() {
+        let values = vec![
+            serde_json::json!({"id": 1, "name": "a"}),
+            serde_json::json!({"id": 2}),
+        ];
+
+        let batches = into_batches(values).unwrap();
+
+        assert_batches_eq!(
+            [
+                "+----+------+",
+                "| id | name |",
+                "+----+------+",
+                "| 1  | a    |",
+                "| 2  |      |",
+                "+----+------+",
+            ],
+            batches.as_slice()
+        );
+    }
+}
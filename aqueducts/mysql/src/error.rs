@@ -0,0 +1,17 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Query did not produce a result set: {0}")]
+    NoResultSet(String),
+    #[error("Unsupported MySQL type {1} for column '{0}'")]
+    UnsupportedColumnType(String, String),
+    #[error("Invalid value for column '{0}': {1}")]
+    InvalidValue(String, String),
+
+    // -- External
+    #[error("ArrowError({0})")]
+    ArrowError(#[from] datafusion::arrow::error::ArrowError),
+    #[error("DataFusionError({0})")]
+    DataFusionError(#[from] datafusion::error::DataFusionError),
+    #[error("MySqlError({0})")]
+    MySqlError(#[from] mysql_async::Error),
+}
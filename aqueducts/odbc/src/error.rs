@@ -1,5 +1,16 @@
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error("Unsupported data type {1} for column '{0}'")]
+    UnsupportedDataType(String, datafusion::arrow::datatypes::DataType),
+    #[error("Fetch batch size must be greater than 0")]
+    InvalidFetchBatchSize,
+    #[error("Query did not produce a result set: {0}")]
+    NoCursor(String),
+    #[error("Delete requires at least one predicate column")]
+    EmptyPredicateColumns,
+    #[error("Predicate column '{0}' doesn't exist in the data")]
+    MissingPredicateColumn(String),
+
     // -- External
     #[error("ArrowError({0})")]
     ArrowError(#[from] datafusion::arrow::error::ArrowError),
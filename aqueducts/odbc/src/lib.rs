@@ -1,19 +1,46 @@
 use std::sync::Arc;
 
-use arrow_odbc::odbc_api::{ConnectionOptions, Environment};
+use arrow_odbc::odbc_api::{parameter::InputParameter, Connection, IntoParameter};
 use arrow_odbc::{insert_into_table, OdbcReaderBuilder, OdbcWriter};
 use datafusion::arrow::array::RecordBatchIterator;
 use datafusion::arrow::compute::concat_batches;
-use datafusion::arrow::datatypes::Schema;
+use datafusion::arrow::datatypes::{DataType, Schema};
 use datafusion::arrow::{array::RecordBatch, error::ArrowError};
 use datafusion::datasource::MemTable;
 use datafusion::execution::context::SessionContext;
-use tracing::error;
+use tracing::{debug, error, warn};
 
 pub mod error;
+mod pool;
 
 pub type Result<T> = core::result::Result<T, error::Error>;
 
+/// Value bound to a `?` placeholder of a source query
+#[derive(Debug, Clone, PartialEq)]
+pub enum OdbcParameter {
+    Integer(i64),
+    Text(String),
+}
+
+impl OdbcParameter {
+    fn into_input_parameter(self) -> Box<dyn InputParameter> {
+        match self {
+            OdbcParameter::Integer(value) => Box::new(value),
+            OdbcParameter::Text(value) => Box::new(value.into_parameter()),
+        }
+    }
+}
+
+/// SQL statements executed before and after the data is written, within the same transaction as the write
+/// Use them to e.g. `TRUNCATE` the table before or `ANALYZE` it after the write, a failing statement rolls back the whole write
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteHooks<'a> {
+    /// Statements executed in order before the data is written
+    pub pre_write: &'a [String],
+    /// Statements executed in order after the data is written
+    pub post_write: &'a [String],
+}
+
 /// Register a table via ODBC using [arrow-odbc](https://docs.rs/arrow-odbc)
 /// ```rust,ignore
 /// use datafusion::prelude::SessionContext;
@@ -32,33 +59,52 @@ pub type Result<T> = core::result::Result<T, error::Error>;
 ///
 /// let ctx = SessionContext::new();
 ///
-/// register_odbc_source(&ctx, query, connection_string, "my_table_name").await.unwrap();
+/// register_odbc_source(&ctx, query, connection_string, "my_table_name", &[], None).await.unwrap();
 ///
 /// let df = ctx.sql("SELECT * FROM my_table_name").await.unwrap();
 /// df.show().await.unwrap();
 /// ```
+///
+/// Values can be bound to `?` placeholders in the query using `parameters`, in the order they appear in the query
+///
+/// The number of rows fetched per batch can be tuned using `fetch_batch_size`, if not provided the default of `arrow-odbc` is used (65535 rows)
 pub async fn register_odbc_source(
     ctx: Arc<SessionContext>,
     connection_string: &str,
     query: &str,
     source_name: &str,
+    parameters: &[OdbcParameter],
+    fetch_batch_size: Option<usize>,
 ) -> Result<()> {
-    let odbc_environment = Environment::new().unwrap();
+    if fetch_batch_size == Some(0) {
+        return Err(error::Error::InvalidFetchBatchSize);
+    }
 
-    let connection = odbc_environment
-        .connect_with_connection_string(connection_string, ConnectionOptions::default())?;
+    let connection = pool::connect(connection_string)?;
 
-    let parameters = ();
+    // the bound parameters are not `Send` so they are dropped before the next await point
+    let batches = {
+        let parameters = parameters
+            .iter()
+            .cloned()
+            .map(OdbcParameter::into_input_parameter)
+            .collect::<Vec<_>>();
+
+        let cursor = connection
+            .execute(query, parameters.as_slice())?
+            .ok_or_else(|| error::Error::NoCursor(query.to_string()))?;
 
-    let cursor = connection
-        .execute(query, parameters)?
-        .expect("SELECT statement must produce a cursor");
+        let mut reader_builder = OdbcReaderBuilder::new();
+        if let Some(fetch_batch_size) = fetch_batch_size {
+            reader_builder.with_max_num_rows_per_batch(fetch_batch_size);
+        }
 
-    let reader = OdbcReaderBuilder::new().build(cursor)?;
+        let reader = reader_builder.build(cursor)?;
 
-    let batches = reader
-        .into_iter()
-        .collect::<std::result::Result<Vec<RecordBatch>, ArrowError>>()?;
+        reader
+            .into_iter()
+            .collect::<std::result::Result<Vec<RecordBatch>, ArrowError>>()?
+    };
 
     let df = ctx.read_batches(batches)?;
 
@@ -77,21 +123,85 @@ pub async fn register_odbc_destination(
     connection_string: &str,
     destination_name: &str,
 ) -> Result<()> {
-    let odbc_environment = Environment::new().unwrap();
-
-    let connection = odbc_environment
-        .connect_with_connection_string(connection_string, ConnectionOptions::default())?;
+    let connection = pool::connect(connection_string)?;
 
     let parameters = ();
 
     let query = format!("SELECT * FROM {destination_name} LIMIT 1");
     connection
         .execute(query.as_str(), parameters)?
-        .expect("SELECT statement must produce a cursor");
+        .ok_or_else(|| error::Error::NoCursor(query.clone()))?;
+
+    Ok(())
+}
+
+/// Checks if the provided table for the destination exists
+/// Returns `false` instead of an error when the probe query against the table fails
+pub async fn odbc_table_exists(connection_string: &str, destination_name: &str) -> Result<bool> {
+    let connection = pool::connect(connection_string)?;
+
+    Ok(table_exists(&connection, destination_name))
+}
+
+/// Checks if rows can be inserted into the provided table
+/// Executes an insert of zero rows (`INSERT INTO <table> SELECT * FROM <table> WHERE 1 = 0`) in a transaction that is always rolled back
+pub async fn odbc_write_check(connection_string: &str, destination_name: &str) -> Result<()> {
+    let connection = pool::connect(connection_string)?;
+
+    connection.set_autocommit(false)?;
+
+    let query =
+        format!("INSERT INTO {destination_name} SELECT * FROM {destination_name} WHERE 1 = 0");
+    let result = connection.execute(query.as_str(), ());
+
+    connection.rollback()?;
+    result?;
 
     Ok(())
 }
 
+/// Derive a `CREATE TABLE` statement for the provided arrow schema
+/// Only primitive types are supported, nested types will result in an error
+pub fn create_table_statement(table_name: &str, schema: &Schema) -> Result<String> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let sql_type = match field.data_type() {
+                DataType::Boolean => "BOOLEAN".to_string(),
+                DataType::Int8 | DataType::Int16 | DataType::UInt8 => "SMALLINT".to_string(),
+                DataType::Int32 | DataType::UInt16 => "INTEGER".to_string(),
+                DataType::Int64 | DataType::UInt32 | DataType::UInt64 => "BIGINT".to_string(),
+                DataType::Float16 | DataType::Float32 => "REAL".to_string(),
+                DataType::Float64 => "DOUBLE PRECISION".to_string(),
+                DataType::Decimal128(precision, scale) | DataType::Decimal256(precision, scale) => {
+                    format!("DECIMAL({precision}, {scale})")
+                }
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => "TEXT".to_string(),
+                DataType::Date32 | DataType::Date64 => "DATE".to_string(),
+                DataType::Time32(_) | DataType::Time64(_) => "TIME".to_string(),
+                DataType::Timestamp(_, None) => "TIMESTAMP".to_string(),
+                DataType::Timestamp(_, Some(_)) => "TIMESTAMP WITH TIME ZONE".to_string(),
+                data_type => {
+                    return Err(error::Error::UnsupportedDataType(
+                        field.name().clone(),
+                        data_type.clone(),
+                    ))
+                }
+            };
+
+            let nullable = if field.is_nullable() { "" } else { " NOT NULL" };
+
+            Ok(format!("{} {sql_type}{nullable}", field.name()))
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    Ok(format!(
+        "CREATE TABLE {table_name} ({})",
+        columns.join(", ")
+    ))
+}
+
 /// Write arrow batches to a table via ODBC using [arrow-odbc](https://docs.rs/arrow-odbc)
 /// ```rust,ignore
 /// use datafusion::prelude::SessionContext;
@@ -107,7 +217,7 @@ pub async fn register_odbc_destination(
 ///
 /// let query = "SELECT * FROM my_table WHERE date > '2024-01-01'";
 /// let ctx = SessionContext::new();
-/// register_odbc_source(&ctx, query, connection_string, "my_table_name").await.unwrap();
+/// register_odbc_source(&ctx, query, connection_string, "my_table_name", &[], None).await.unwrap();
 ///
 /// //check if table exists
 /// register_odbc_destination(connection_string, "another_table").await.unwrap();
@@ -116,68 +226,165 @@ pub async fn register_odbc_destination(
 /// let schema = df.schema().as_arrow().clone();
 /// let batches = df.collect().await.unwrap();
 ///
-/// write_arrow_batches(connection_string, "another_table", batches, Arc::new(schema), 1000).await.unwrap();
+/// write_arrow_batches(connection_string, "another_table", batches, Arc::new(schema), 1000, WriteHooks::default()).await.unwrap();
 /// ```
+///
+/// The insert and the statements of the `hooks` are executed within the same transaction which
+/// gets rolled back in case of any errors
 pub async fn write_arrow_batches(
     connection_string: &str,
     destination_name: &str,
     batches: Vec<RecordBatch>,
     schema: Arc<Schema>,
     batch_size: usize,
+    hooks: WriteHooks<'_>,
 ) -> Result<()> {
-    let odbc_environment = Environment::new().unwrap();
-
-    let connection = odbc_environment
-        .connect_with_connection_string(connection_string, ConnectionOptions::default())?;
+    let connection = pool::connect(connection_string)?;
 
     let batches = [concat_batches(&schema, batches.iter())?];
     let mut record_batch_iterator = RecordBatchIterator::new(batches.into_iter().map(Ok), schema);
 
-    insert_into_table(
-        &connection,
-        &mut record_batch_iterator,
-        destination_name,
-        batch_size,
-    )?;
+    write_in_transaction(&connection, hooks, || {
+        insert_into_table(
+            &connection,
+            &mut record_batch_iterator,
+            destination_name,
+            batch_size,
+        )?;
 
-    Ok(())
+        Ok(())
+    })
 }
 
-/// Performs an insert with a prepared statement provided.
-/// Optionally, it can execute preliminary statements (such as `delete from ...`).
-/// All statemets are executed within the same transaction and it gets rolled back
-/// in case of any errors.
-pub async fn custom(
+/// Write arrow batches to a table via ODBC, creating the table first when it doesn't exist yet
+/// The table is created within the write transaction, so databases supporting transactional DDL roll it back together with a failed write.
+/// A table created concurrently by another writer is used as is.
+/// The insert and the statements of the `hooks` are executed within the same transaction which gets rolled back in case of any errors
+pub async fn create_and_write_arrow_batches(
     connection_string: &str,
-    pre_insert: Option<String>,
-    insert: &str,
+    destination_name: &str,
+    create_statement: &str,
     batches: Vec<RecordBatch>,
     schema: Arc<Schema>,
     batch_size: usize,
+    hooks: WriteHooks<'_>,
 ) -> Result<()> {
-    let odbc_environment = Environment::new()?;
+    let connection = pool::connect(connection_string)?;
 
-    let connection = odbc_environment
-        .connect_with_connection_string(connection_string, ConnectionOptions::default())?;
+    let batch = concat_batches(&schema, batches.iter())?;
+    let write = |create: bool, create_failed: &mut bool| {
+        let mut record_batch_iterator =
+            RecordBatchIterator::new([batch.clone()].into_iter().map(Ok), schema.clone());
 
-    let batches = [concat_batches(&schema, batches.iter())?];
-    let record_batch_iterator =
-        RecordBatchIterator::new(batches.into_iter().map(Ok), schema.clone());
+        write_in_transaction(&connection, hooks, || {
+            if create {
+                debug!("Creating table {destination_name} using: {create_statement}");
+                connection
+                    .execute(create_statement, ())
+                    .inspect_err(|_| *create_failed = true)?;
+            }
+
+            insert_into_table(
+                &connection,
+                &mut record_batch_iterator,
+                destination_name,
+                batch_size,
+            )?;
 
-    let mut writer = OdbcWriter::new(batch_size, &schema, connection.prepare(&insert)?)?;
+            Ok(())
+        })
+    };
 
-    let _ = connection.set_autocommit(false);
+    // the table is probed before the transaction starts as a failing query aborts the transaction on some databases
+    let create = !table_exists(&connection, destination_name);
+    let mut create_failed = false;
 
-    let result = || -> Result<()> {
-        if let Some(stmt) = pre_insert {
-            connection.execute(&stmt, ())?;
+    match write(create, &mut create_failed) {
+        // losing a race against a concurrent writer creating the same table is not an error
+        Err(_) if create_failed && table_exists(&connection, destination_name) => {
+            debug!("Table {destination_name} was created concurrently");
+            write(false, &mut create_failed)
         }
+        result => result,
+    }
+}
+
+/// Deletes the rows of a table matching the key tuples present in the provided batches
+/// Executes `DELETE FROM <table> WHERE <col_1> = ? AND <col_2> = ?` as a prepared statement bound to the predicate columns of every row
+/// Rows with null keys don't match any row. All deletes and the statements of the `hooks` are executed
+/// within the same transaction which gets rolled back in case of any errors
+pub async fn delete(
+    connection_string: &str,
+    destination_name: &str,
+    predicate_columns: &[String],
+    batches: Vec<RecordBatch>,
+    schema: Arc<Schema>,
+    batch_size: usize,
+    hooks: WriteHooks<'_>,
+) -> Result<()> {
+    if predicate_columns.is_empty() {
+        return Err(error::Error::EmptyPredicateColumns);
+    }
+
+    let indices = predicate_columns
+        .iter()
+        .map(|column| {
+            schema
+                .index_of(column)
+                .map_err(|_| error::Error::MissingPredicateColumn(column.clone()))
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    let key_schema = Arc::new(schema.project(&indices)?);
+    let keys = concat_batches(&schema, batches.iter())?.project(&indices)?;
+
+    let predicate = predicate_columns
+        .iter()
+        .map(|column| format!("{column} = ?"))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let statement = format!("DELETE FROM {destination_name} WHERE {predicate}");
+    debug!("Deleting rows using: {statement}");
+
+    let connection = pool::connect(connection_string)?;
+
+    let record_batch_iterator =
+        RecordBatchIterator::new([keys].into_iter().map(Ok), key_schema.clone());
+
+    let mut writer = OdbcWriter::new(batch_size, &key_schema, connection.prepare(&statement)?)?;
+
+    write_in_transaction(&connection, hooks, || {
         writer.write_all(record_batch_iterator)?;
 
         Ok(())
-    };
+    })
+}
+
+// probe the table by querying a single record from it, any failure of the probe is treated as a missing table
+fn table_exists(connection: &Connection<'_>, table_name: &str) -> bool {
+    let query = format!("SELECT * FROM {table_name} LIMIT 1");
+
+    match connection.execute(query.as_str(), ()) {
+        Ok(_) => true,
+        Err(err) => {
+            warn!("Probing table {table_name} failed, assuming it doesn't exist: {err}");
+            false
+        }
+    }
+}
 
-    match result() {
+// execute the write between the statements of the hooks within a single transaction which gets rolled back in case of any errors
+fn write_in_transaction(
+    connection: &pool::PooledConnection,
+    hooks: WriteHooks<'_>,
+    write: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    connection.set_autocommit(false)?;
+
+    let result = execute_statements(connection, hooks.pre_write)
+        .and_then(|_| write())
+        .and_then(|_| execute_statements(connection, hooks.post_write));
+
+    match result {
         Ok(_) => {
             connection.commit()?;
             Ok(())
@@ -190,8 +397,50 @@ pub async fn custom(
     }
 }
 
+// execute the statements of a write hook in order
+fn execute_statements(connection: &Connection<'_>, statements: &[String]) -> Result<()> {
+    for stmt in statements {
+        debug!("Executing statement: {stmt}");
+        connection.execute(stmt, ())?;
+    }
+
+    Ok(())
+}
+
+/// Performs an insert with a prepared statement provided.
+/// Optionally, it can execute preliminary statements (such as `delete from ...`).
+/// All statemets, including the ones of the `hooks`, are executed within the same transaction and it gets rolled back
+/// in case of any errors.
+pub async fn custom(
+    connection_string: &str,
+    pre_insert: Option<String>,
+    insert: &str,
+    batches: Vec<RecordBatch>,
+    schema: Arc<Schema>,
+    batch_size: usize,
+    hooks: WriteHooks<'_>,
+) -> Result<()> {
+    let connection = pool::connect(connection_string)?;
+
+    let batches = [concat_batches(&schema, batches.iter())?];
+    let record_batch_iterator =
+        RecordBatchIterator::new(batches.into_iter().map(Ok), schema.clone());
+
+    let mut writer = OdbcWriter::new(batch_size, &schema, connection.prepare(insert)?)?;
+
+    write_in_transaction(&connection, hooks, || {
+        if let Some(stmt) = pre_insert {
+            connection.execute(&stmt, ())?;
+        }
+        writer.write_all(record_batch_iterator)?;
+
+        Ok(())
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use arrow_odbc::odbc_api::{ConnectionOptions, Environment};
     use datafusion::arrow::array::*;
     use datafusion::{assert_batches_eq, prelude::*};
     use std::sync::Arc;
@@ -215,6 +464,8 @@ mod tests {
             connection_string,
             "SELECT * FROM temp_readings WHERE timestamp::date BETWEEN '2024-01-01' AND '2024-01-31'",
             "my_table",
+            &[],
+            None,
         )
         .await
         .unwrap();
@@ -239,6 +490,123 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_register_odbc_source_parameters_ok() {
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+
+        let ctx = Arc::new(SessionContext::new());
+
+        register_odbc_source(
+            ctx.clone(),
+            connection_string,
+            "SELECT * FROM temp_readings WHERE timestamp::date BETWEEN CAST(? AS date) AND CAST(? AS date) AND location_id = ?",
+            "my_table",
+            &[
+                OdbcParameter::Text("2024-01-01".into()),
+                OdbcParameter::Text("2024-01-31".into()),
+                OdbcParameter::Integer(1),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+
+        let result = ctx
+            .sql("SELECT count(*) num_rows FROM my_table")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_batches_eq!(
+            &[
+                "+----------+",
+                "| num_rows |",
+                "+----------+",
+                "| 120      |",
+                "+----------+",
+            ],
+            result.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_register_odbc_source_fetch_batch_size_ok() {
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+
+        let ctx = Arc::new(SessionContext::new());
+
+        register_odbc_source(
+            ctx.clone(),
+            connection_string,
+            "SELECT * FROM temp_readings WHERE timestamp::date BETWEEN '2024-01-01' AND '2024-01-31'",
+            "my_table",
+            &[],
+            Some(100),
+        )
+        .await
+        .unwrap();
+
+        let batches = ctx
+            .table("my_table")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        assert_eq!(10, batches.len());
+        assert!(batches.iter().all(|batch| batch.num_rows() == 100));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_register_odbc_source_zero_fetch_batch_size_err() {
+        let ctx = Arc::new(SessionContext::new());
+
+        let result = register_odbc_source(ctx, "", "SELECT 1", "my_table", &[], Some(0)).await;
+
+        assert!(matches!(result, Err(error::Error::InvalidFetchBatchSize)));
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_register_odbc_source_no_cursor_err() {
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+
+        let ctx = Arc::new(SessionContext::new());
+
+        let result = register_odbc_source(
+            ctx,
+            connection_string,
+            "CREATE TEMPORARY TABLE test_no_cursor (id int)",
+            "my_table",
+            &[],
+            None,
+        )
+        .await;
+
+        assert!(matches!(result, Err(error::Error::NoCursor(_))));
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     async fn test_register_odbc_destination_ok() {
@@ -294,12 +662,254 @@ mod tests {
             vec![record_batch],
             schema,
             100,
+            WriteHooks::default(),
         )
         .await;
 
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_write_arrow_batches_hooks_ok() {
+        let odbc_environment = Environment::new().unwrap();
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+        let connection = odbc_environment
+            .connect_with_connection_string(connection_string, ConnectionOptions::default())
+            .unwrap();
+        let _ = connection
+            .execute("drop table if exists test_write_hooks_ok", ())
+            .unwrap();
+        let _ = connection
+            .execute("create table test_write_hooks_ok (id int, value text)", ())
+            .unwrap();
+        let _ = connection
+            .execute(
+                "insert into test_write_hooks_ok values (1, 'stale'), (2, 'kept')",
+                (),
+            )
+            .unwrap();
+
+        let record_batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int32Array::from(vec![1])) as ArrayRef),
+            (
+                "value",
+                Arc::new(StringArray::from(vec!["fresh"])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let schema = record_batch.schema();
+
+        write_arrow_batches(
+            connection_string,
+            "test_write_hooks_ok",
+            vec![record_batch],
+            schema,
+            100,
+            WriteHooks {
+                pre_write: &["delete from test_write_hooks_ok where value = 'stale'".to_string()],
+                post_write: &["analyze test_write_hooks_ok".to_string()],
+            },
+        )
+        .await
+        .unwrap();
+
+        let cursor = connection
+            .execute("select * from test_write_hooks_ok order by id", ())
+            .unwrap()
+            .unwrap();
+        let result = OdbcReaderBuilder::new().build(cursor).unwrap();
+        for batch in result {
+            assert_batches_eq!(
+                [
+                    "+----+-------+",
+                    "| id | value |",
+                    "+----+-------+",
+                    "| 1  | fresh |",
+                    "| 2  | kept  |",
+                    "+----+-------+",
+                ],
+                &[batch.unwrap()]
+            );
+        }
+    }
+
+    /// Checks the statements of the hooks are rolled back together with the write
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_write_arrow_batches_hooks_failed() {
+        let odbc_environment = Environment::new().unwrap();
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+        let connection = odbc_environment
+            .connect_with_connection_string(connection_string, ConnectionOptions::default())
+            .unwrap();
+        let _ = connection
+            .execute("drop table if exists test_write_hooks_failed", ())
+            .unwrap();
+        let _ = connection
+            .execute(
+                "create table test_write_hooks_failed (id int, value text)",
+                (),
+            )
+            .unwrap();
+        let _ = connection
+            .execute(
+                "insert into test_write_hooks_failed values (1, 'stale')",
+                (),
+            )
+            .unwrap();
+
+        let record_batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int32Array::from(vec![1])) as ArrayRef),
+            (
+                "value",
+                Arc::new(StringArray::from(vec!["fresh"])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let schema = record_batch.schema();
+
+        let result = write_arrow_batches(
+            connection_string,
+            "test_write_hooks_failed",
+            vec![record_batch],
+            schema,
+            100,
+            WriteHooks {
+                pre_write: &["delete from test_write_hooks_failed".to_string()],
+                post_write: &["update WRONG_TABLE set value = 'x'".to_string()],
+            },
+        )
+        .await;
+
+        assert!(result.is_err());
+
+        let cursor = connection
+            .execute("select * from test_write_hooks_failed order by id", ())
+            .unwrap()
+            .unwrap();
+        let result = OdbcReaderBuilder::new().build(cursor).unwrap();
+        for batch in result {
+            assert_batches_eq!(
+                [
+                    "+----+-------+",
+                    "| id | value |",
+                    "+----+-------+",
+                    "| 1  | stale |",
+                    "+----+-------+",
+                ],
+                &[batch.unwrap()]
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_odbc_write_check() {
+        let odbc_environment = Environment::new().unwrap();
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+        let connection = odbc_environment
+            .connect_with_connection_string(connection_string, ConnectionOptions::default())
+            .unwrap();
+        let _ = connection
+            .execute("drop table if exists test_write_check", ())
+            .unwrap();
+        let _ = connection
+            .execute("create table test_write_check (id int)", ())
+            .unwrap();
+
+        assert!(odbc_write_check(connection_string, "test_write_check")
+            .await
+            .is_ok());
+        assert!(
+            odbc_write_check(connection_string, "test_write_check_missing")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_create_and_write_arrow_batches_ok() {
+        let odbc_environment = Environment::new().unwrap();
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+        let connection = odbc_environment
+            .connect_with_connection_string(connection_string, ConnectionOptions::default())
+            .unwrap();
+        let _ = connection
+            .execute("drop table if exists test_create_and_write_ok", ())
+            .unwrap();
+
+        let record_batch = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef),
+            (
+                "value",
+                Arc::new(StringArray::from(vec!["a", "b"])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let schema = record_batch.schema();
+
+        let create_statement =
+            create_table_statement("test_create_and_write_ok", schema.as_ref()).unwrap();
+        assert!(
+            !odbc_table_exists(connection_string, "test_create_and_write_ok")
+                .await
+                .unwrap()
+        );
+
+        create_and_write_arrow_batches(
+            connection_string,
+            "test_create_and_write_ok",
+            create_statement.as_str(),
+            vec![record_batch],
+            schema,
+            100,
+            WriteHooks::default(),
+        )
+        .await
+        .unwrap();
+
+        let cursor = connection
+            .execute("select * from test_create_and_write_ok order by id", ())
+            .unwrap()
+            .unwrap();
+        let result = OdbcReaderBuilder::new().build(cursor).unwrap();
+        for batch in result {
+            assert_batches_eq!(
+                [
+                    "+----+-------+",
+                    "| id | value |",
+                    "+----+-------+",
+                    "| 1  | a     |",
+                    "| 2  | b     |",
+                    "+----+-------+",
+                ],
+                &[batch.unwrap()]
+            );
+        }
+    }
+
     /// Tests a trasaction with a delete and an insert
     #[tokio::test]
     #[tracing_test::traced_test]
@@ -334,6 +944,7 @@ mod tests {
             vec![record_batch],
             schema.clone(),
             100,
+            WriteHooks::default(),
         )
         .await;
 
@@ -353,6 +964,7 @@ mod tests {
             vec![new_batch],
             schema,
             50,
+            WriteHooks::default(),
         )
         .await
         .unwrap();
@@ -377,6 +989,106 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_delete_ok() {
+        let odbc_environment = Environment::new().unwrap();
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+        ";
+        let connection = odbc_environment
+            .connect_with_connection_string(connection_string, ConnectionOptions::default())
+            .unwrap();
+        let _ = connection
+            .execute("drop table if exists test_delete_ok", ())
+            .unwrap();
+        let _ = connection
+            .execute(
+                "create table test_delete_ok (id int, region text, value text)",
+                (),
+            )
+            .unwrap();
+        let _ = connection
+            .execute(
+                "insert into test_delete_ok values (1, 'eu', 'a'), (1, 'us', 'b'), (2, 'eu', 'c')",
+                (),
+            )
+            .unwrap();
+
+        let keys = RecordBatch::try_from_iter(vec![
+            ("id", Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef),
+            (
+                "region",
+                Arc::new(StringArray::from(vec!["eu", "eu"])) as ArrayRef,
+            ),
+            (
+                "value",
+                Arc::new(StringArray::from(vec!["ignored", "ignored"])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let schema = keys.schema();
+
+        delete(
+            connection_string,
+            "test_delete_ok",
+            &["id".to_string(), "region".to_string()],
+            vec![keys],
+            schema,
+            100,
+            WriteHooks::default(),
+        )
+        .await
+        .unwrap();
+
+        let cursor = connection
+            .execute("select * from test_delete_ok order by id", ())
+            .unwrap()
+            .unwrap();
+        let result = OdbcReaderBuilder::new().build(cursor).unwrap();
+        for batch in result {
+            assert_batches_eq!(
+                [
+                    "+----+--------+-------+",
+                    "| id | region | value |",
+                    "+----+--------+-------+",
+                    "| 1  | us     | b     |",
+                    "+----+--------+-------+",
+                ],
+                &[batch.unwrap()]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_missing_predicate_column_err() {
+        let keys = RecordBatch::try_from_iter(vec![(
+            "id",
+            Arc::new(Int32Array::from(vec![1])) as ArrayRef,
+        )])
+        .unwrap();
+        let schema = keys.schema();
+
+        let result = delete(
+            "",
+            "table",
+            &["region".to_string()],
+            vec![keys],
+            schema,
+            100,
+            WriteHooks::default(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(error::Error::MissingPredicateColumn(column)) if column == "region"
+        ));
+    }
+
     /// Checks transaction is rolled back in case of error
     #[tokio::test]
     #[tracing_test::traced_test]
@@ -411,6 +1123,7 @@ mod tests {
             vec![record_batch],
             schema.clone(),
             100,
+            WriteHooks::default(),
         )
         .await;
 
@@ -430,6 +1143,7 @@ mod tests {
             vec![new_batch],
             schema,
             50,
+            WriteHooks::default(),
         )
         .await
         .ok();
@@ -0,0 +1,196 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    ops::Deref,
+    sync::{Mutex, OnceLock},
+};
+
+use arrow_odbc::odbc_api::{Connection, ConnectionOptions, Environment};
+use tracing::{debug, warn};
+
+use super::Result;
+
+/// Maximum number of idle connections kept per connection string
+const MAX_IDLE_CONNECTIONS: usize = 4;
+
+static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+static IDLE_CONNECTIONS: OnceLock<Mutex<HashMap<String, Vec<Connection<'static>>>>> =
+    OnceLock::new();
+
+/// Process wide ODBC environment, initialized on first use
+fn environment() -> Result<&'static Environment> {
+    if let Some(environment) = ENVIRONMENT.get() {
+        return Ok(environment);
+    }
+
+    let environment = Environment::new()?;
+
+    Ok(ENVIRONMENT.get_or_init(|| environment))
+}
+
+/// Take an idle connection for the connection string from the pool or open a new one
+/// The connection is returned to the pool when the `PooledConnection` is dropped
+pub(crate) fn connect(connection_string: &str) -> Result<PooledConnection> {
+    while let Some(connection) = take_idle(connection_string) {
+        match connection.is_dead() {
+            Ok(false) => return Ok(PooledConnection::new(connection_string, connection)),
+            _ => debug!("Discarding dead pooled ODBC connection"),
+        }
+    }
+
+    let connection = environment()?
+        .connect_with_connection_string(connection_string, ConnectionOptions::default())?;
+
+    Ok(PooledConnection::new(connection_string, connection))
+}
+
+fn idle_connections() -> &'static Mutex<HashMap<String, Vec<Connection<'static>>>> {
+    IDLE_CONNECTIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn take_idle(connection_string: &str) -> Option<Connection<'static>> {
+    idle_connections()
+        .lock()
+        .ok()?
+        .get_mut(connection_string)?
+        .pop()
+}
+
+/// A connection borrowed from the pool
+/// Transactions are tracked so a connection with an unfinished transaction is never returned to the pool
+pub(crate) struct PooledConnection {
+    connection_string: String,
+    connection: Option<Connection<'static>>,
+    open_transaction: Cell<bool>,
+}
+
+impl PooledConnection {
+    fn new(connection_string: &str, connection: Connection<'static>) -> Self {
+        Self {
+            connection_string: connection_string.to_string(),
+            connection: Some(connection),
+            open_transaction: Cell::new(false),
+        }
+    }
+
+    /// Disabling autocommit starts a transaction which has to be finished with `commit` or `rollback`
+    pub(crate) fn set_autocommit(&self, enabled: bool) -> Result<()> {
+        (**self).set_autocommit(enabled)?;
+        self.open_transaction.set(!enabled);
+
+        Ok(())
+    }
+
+    pub(crate) fn commit(&self) -> Result<()> {
+        (**self).commit()?;
+        self.open_transaction.set(false);
+
+        Ok(())
+    }
+
+    pub(crate) fn rollback(&self) -> Result<()> {
+        (**self).rollback()?;
+        self.open_transaction.set(false);
+
+        Ok(())
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+            .as_ref()
+            .expect("connection is only taken on drop")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+
+        // resetting autocommit would commit an unfinished transaction (e.g. after a failed rollback),
+        // the connection is closed instead which lets the database discard the transaction
+        if self.open_transaction.get() {
+            warn!("Discarding ODBC connection with an unfinished transaction");
+            return;
+        }
+
+        // connections are only reused in autocommit mode
+        if let Err(err) = connection.set_autocommit(true) {
+            warn!("Discarding ODBC connection, failed to reset autocommit: {err}");
+            return;
+        }
+
+        let Ok(mut idle_connections) = idle_connections().lock() else {
+            return;
+        };
+        let idle = idle_connections
+            .entry(std::mem::take(&mut self.connection_string))
+            .or_default();
+
+        if idle.len() < MAX_IDLE_CONNECTIONS {
+            idle.push(connection);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idle_count(connection_string: &str) -> usize {
+        idle_connections()
+            .lock()
+            .unwrap()
+            .get(connection_string)
+            .map(Vec::len)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_connect_reuses_idle_connection_ok() {
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+            ApplicationName=aqueducts_pool_test;\
+        ";
+
+        let connection = connect(connection_string).unwrap();
+        connection.set_autocommit(false).unwrap();
+        connection.commit().unwrap();
+        drop(connection);
+
+        assert_eq!(1, idle_count(connection_string));
+
+        let connection = connect(connection_string).unwrap();
+
+        assert_eq!(0, idle_count(connection_string));
+        assert!(!connection.is_dead().unwrap());
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_connect_discards_unfinished_transaction_ok() {
+        let connection_string: &str = "\
+            Driver={PostgreSQL Unicode};\
+            Server=localhost;\
+            UID=postgres;\
+            PWD=postgres;\
+            ApplicationName=aqueducts_pool_unfinished_test;\
+        ";
+
+        let connection = connect(connection_string).unwrap();
+        connection.set_autocommit(false).unwrap();
+        drop(connection);
+
+        assert_eq!(0, idle_count(connection_string));
+        assert!(logs_contain("unfinished transaction"));
+    }
+}
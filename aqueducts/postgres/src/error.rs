@@ -0,0 +1,15 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Unsupported Postgres type {1} for column '{0}'")]
+    UnsupportedColumnType(String, String),
+    #[error("Unsupported data type {1} for column '{0}'")]
+    UnsupportedDataType(String, datafusion::arrow::datatypes::DataType),
+
+    // -- External
+    #[error("ArrowError({0})")]
+    ArrowError(#[from] datafusion::arrow::error::ArrowError),
+    #[error("DataFusionError({0})")]
+    DataFusionError(#[from] datafusion::error::DataFusionError),
+    #[error("PostgresError({0})")]
+    PostgresError(#[from] tokio_postgres::Error),
+}
@@ -0,0 +1,533 @@
+use bytes::{BufMut, BytesMut};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use datafusion::arrow::{
+    array::{
+        Array, ArrayRef, AsArray, BinaryArray, BooleanArray, Date32Array, Float32Array,
+        Float64Array, Int16Array, Int32Array, Int64Array, StringArray, Time64MicrosecondArray,
+        TimestampMicrosecondArray,
+    },
+    datatypes::{
+        DataType, Date32Type, Field, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+        Time64MicrosecondType, TimeUnit, TimestampMicrosecondType,
+    },
+};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{
+    binary_copy::BinaryCopyOutRow,
+    types::{to_sql_checked, FromSql, IsNull, ToSql, Type},
+};
+
+use crate::{error, Result};
+
+type BoxError = Box<dyn Error + Sync + Send>;
+
+fn unix_epoch() -> NaiveDate {
+    DateTime::UNIX_EPOCH.date_naive()
+}
+
+/// Arrow type a Postgres column is read as and that the data is cast to before it is written to a column
+///
+/// | Postgres                                           | Arrow                           |
+/// |----------------------------------------------------|---------------------------------|
+/// | `boolean`                                          | `Boolean`                       |
+/// | `smallint`, `integer`, `bigint`                    | `Int16`, `Int32`, `Int64`       |
+/// | `real`, `double precision`                         | `Float32`, `Float64`            |
+/// | `numeric`                                          | `Utf8`, exact decimal text      |
+/// | `text`, `varchar`, `char`, `name`, `json`, `jsonb`, `uuid` | `Utf8`                  |
+/// | `bytea`                                            | `Binary`                        |
+/// | `date`                                             | `Date32`                        |
+/// | `timestamp`                                        | `Timestamp(Microsecond, None)`  |
+/// | `timestamptz`                                      | `Timestamp(Microsecond, "UTC")` |
+/// | `time`                                             | `Time64(Microsecond)`           |
+pub fn arrow_type(column: &str, pg_type: &Type) -> Result<DataType> {
+    let data_type = match *pg_type {
+        Type::BOOL => DataType::Boolean,
+        Type::INT2 => DataType::Int16,
+        Type::INT4 => DataType::Int32,
+        Type::INT8 => DataType::Int64,
+        Type::FLOAT4 => DataType::Float32,
+        Type::FLOAT8 => DataType::Float64,
+        Type::BYTEA => DataType::Binary,
+        Type::DATE => DataType::Date32,
+        Type::TIMESTAMP => DataType::Timestamp(TimeUnit::Microsecond, None),
+        // timestamptz values are transferred in UTC independent of the session time zone
+        Type::TIMESTAMPTZ => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        Type::TIME => DataType::Time64(TimeUnit::Microsecond),
+        ref pg_type if <Text as FromSql>::accepts(pg_type) => DataType::Utf8,
+        ref pg_type => {
+            return Err(error::Error::UnsupportedColumnType(
+                column.to_string(),
+                pg_type.to_string(),
+            ))
+        }
+    };
+
+    Ok(data_type)
+}
+
+/// Build the array of a single column from rows read with `COPY ... TO STDOUT (FORMAT binary)`
+pub(crate) fn array(rows: &[BinaryCopyOutRow], index: usize, field: &Field) -> Result<ArrayRef> {
+    macro_rules! collect {
+        ($type:ty, $array:ty) => {
+            Arc::new(
+                rows.iter()
+                    .map(|row| Ok(row.try_get::<Option<$type>>(index)?))
+                    .collect::<Result<$array>>()?,
+            ) as ArrayRef
+        };
+        ($type:ty, $array:ty, $map:expr) => {
+            Arc::new(
+                rows.iter()
+                    .map(|row| Ok(row.try_get::<Option<$type>>(index)?.map($map)))
+                    .collect::<Result<$array>>()?,
+            ) as ArrayRef
+        };
+    }
+
+    let array = match field.data_type() {
+        DataType::Boolean => collect!(bool, BooleanArray),
+        DataType::Int16 => collect!(i16, Int16Array),
+        DataType::Int32 => collect!(i32, Int32Array),
+        DataType::Int64 => collect!(i64, Int64Array),
+        DataType::Float32 => collect!(f32, Float32Array),
+        DataType::Float64 => collect!(f64, Float64Array),
+        DataType::Utf8 => collect!(Text, StringArray, |value| value.0),
+        DataType::Binary => collect!(Vec<u8>, BinaryArray),
+        DataType::Date32 => collect!(NaiveDate, Date32Array, |value| {
+            (value - unix_epoch()).num_days() as i32
+        }),
+        DataType::Timestamp(_, None) => {
+            collect!(NaiveDateTime, TimestampMicrosecondArray, |value| value
+                .and_utc()
+                .timestamp_micros())
+        }
+        DataType::Timestamp(_, Some(_)) => Arc::new(
+            rows.iter()
+                .map(|row| {
+                    Ok(row
+                        .try_get::<Option<DateTime<Utc>>>(index)?
+                        .map(|value| value.timestamp_micros()))
+                })
+                .collect::<Result<TimestampMicrosecondArray>>()?
+                .with_timezone("UTC"),
+        ),
+        DataType::Time64(_) => collect!(NaiveTime, Time64MicrosecondArray, |value| {
+            i64::from(value.num_seconds_from_midnight()) * 1_000_000
+                + i64::from(value.nanosecond() / 1_000)
+        }),
+        data_type => {
+            return Err(error::Error::UnsupportedDataType(
+                field.name().clone(),
+                data_type.clone(),
+            ))
+        }
+    };
+
+    Ok(array)
+}
+
+/// Values of an array cast to the `arrow_type` of the target column, written with `COPY ... FROM STDIN (FORMAT binary)`
+pub(crate) fn values(array: &dyn Array, column: &str) -> Result<Vec<Box<dyn ToSql + Sync>>> {
+    macro_rules! boxed {
+        ($iter:expr) => {
+            $iter
+                .map(|value| Box::new(value) as Box<dyn ToSql + Sync>)
+                .collect()
+        };
+    }
+
+    let values = match array.data_type() {
+        DataType::Boolean => boxed!(array.as_boolean().iter()),
+        DataType::Int16 => boxed!(array.as_primitive::<Int16Type>().iter()),
+        DataType::Int32 => boxed!(array.as_primitive::<Int32Type>().iter()),
+        DataType::Int64 => boxed!(array.as_primitive::<Int64Type>().iter()),
+        DataType::Float32 => boxed!(array.as_primitive::<Float32Type>().iter()),
+        DataType::Float64 => boxed!(array.as_primitive::<Float64Type>().iter()),
+        DataType::Utf8 => boxed!(array
+            .as_string::<i32>()
+            .iter()
+            .map(|value| value.map(|value| Text(value.to_string())))),
+        DataType::Binary => boxed!(array
+            .as_binary::<i32>()
+            .iter()
+            .map(|value| value.map(<[u8]>::to_vec))),
+        DataType::Date32 => boxed!(array
+            .as_primitive::<Date32Type>()
+            .iter()
+            .map(|value| value.map(|days| unix_epoch() + chrono::TimeDelta::days(days.into())))),
+        DataType::Timestamp(TimeUnit::Microsecond, None) => boxed!(array
+            .as_primitive::<TimestampMicrosecondType>()
+            .iter()
+            .map(|value| value
+                .and_then(DateTime::from_timestamp_micros)
+                .map(|value| value.naive_utc()))),
+        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => boxed!(array
+            .as_primitive::<TimestampMicrosecondType>()
+            .iter()
+            .map(|value| value.and_then(DateTime::from_timestamp_micros))),
+        DataType::Time64(TimeUnit::Microsecond) => boxed!(array
+            .as_primitive::<Time64MicrosecondType>()
+            .iter()
+            .map(|value| value.and_then(|micros| {
+                NaiveTime::from_num_seconds_from_midnight_opt(
+                    (micros / 1_000_000) as u32,
+                    (micros % 1_000_000) as u32 * 1_000,
+                )
+            }))),
+        data_type => {
+            return Err(error::Error::UnsupportedDataType(
+                column.to_string(),
+                data_type.clone(),
+            ))
+        }
+    };
+
+    Ok(values)
+}
+
+/// Text representation of values of text-like types, `json`, `jsonb`, `numeric` and `uuid`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Text(pub(crate) String);
+
+impl<'a> FromSql<'a> for Text {
+    fn from_sql(pg_type: &Type, raw: &'a [u8]) -> std::result::Result<Self, BoxError> {
+        let text = match *pg_type {
+            Type::JSONB => match raw.split_first() {
+                Some((1, json)) => std::str::from_utf8(json)?.to_string(),
+                _ => return Err("unsupported jsonb version".into()),
+            },
+            Type::NUMERIC => numeric::decode(raw)?,
+            Type::UUID => uuid::decode(raw)?,
+            _ => std::str::from_utf8(raw)?.to_string(),
+        };
+
+        Ok(Text(text))
+    }
+
+    fn accepts(pg_type: &Type) -> bool {
+        matches!(
+            *pg_type,
+            Type::TEXT
+                | Type::VARCHAR
+                | Type::BPCHAR
+                | Type::NAME
+                | Type::UNKNOWN
+                | Type::JSON
+                | Type::JSONB
+                | Type::NUMERIC
+                | Type::UUID
+        )
+    }
+}
+
+impl ToSql for Text {
+    fn to_sql(&self, pg_type: &Type, out: &mut BytesMut) -> std::result::Result<IsNull, BoxError> {
+        match *pg_type {
+            Type::JSONB => {
+                out.put_u8(1);
+                out.put_slice(self.0.as_bytes());
+            }
+            Type::NUMERIC => numeric::encode(self.0.as_str(), out)?,
+            Type::UUID => uuid::encode(self.0.as_str(), out)?,
+            _ => out.put_slice(self.0.as_bytes()),
+        }
+
+        Ok(IsNull::No)
+    }
+
+    fn accepts(pg_type: &Type) -> bool {
+        <Text as FromSql>::accepts(pg_type)
+    }
+
+    to_sql_checked!();
+}
+
+// binary format of `numeric`: digit count, weight of the first digit, sign and display scale followed by base 10000 digits
+mod numeric {
+    use super::*;
+
+    const NEGATIVE: u16 = 0x4000;
+    const NAN: u16 = 0xC000;
+    const INFINITY: u16 = 0xD000;
+    const NEGATIVE_INFINITY: u16 = 0xF000;
+
+    pub(super) fn decode(raw: &[u8]) -> std::result::Result<String, BoxError> {
+        let read = |pos: usize| -> std::result::Result<u16, BoxError> {
+            raw.get(pos..pos + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .ok_or_else(|| "invalid numeric value".into())
+        };
+
+        let ndigits = read(0)? as usize;
+        let weight = read(2)? as i16 as i32;
+        let sign = read(4)?;
+        let scale = read(6)? as usize;
+
+        match sign {
+            NAN => return Ok("NaN".into()),
+            INFINITY => return Ok("Infinity".into()),
+            NEGATIVE_INFINITY => return Ok("-Infinity".into()),
+            _ => {}
+        }
+
+        let digits = (0..ndigits)
+            .map(|digit| read(8 + digit * 2))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let digit = |pos: i32| {
+            usize::try_from(pos)
+                .ok()
+                .and_then(|pos| digits.get(pos).copied())
+                .unwrap_or(0)
+        };
+
+        let mut text = String::new();
+        if sign == NEGATIVE {
+            text.push('-');
+        }
+
+        match weight {
+            weight if weight < 0 => text.push('0'),
+            weight => {
+                text.push_str(digit(0).to_string().as_str());
+                for pos in 1..=weight {
+                    text.push_str(format!("{:04}", digit(pos)).as_str());
+                }
+            }
+        }
+
+        if scale > 0 {
+            let mut fraction = String::new();
+            let mut pos = weight + 1;
+            while fraction.len() < scale {
+                fraction.push_str(format!("{:04}", digit(pos)).as_str());
+                pos += 1;
+            }
+            fraction.truncate(scale);
+
+            text.push('.');
+            text.push_str(fraction.as_str());
+        }
+
+        Ok(text)
+    }
+
+    pub(super) fn encode(text: &str, out: &mut BytesMut) -> std::result::Result<(), BoxError> {
+        let header = |out: &mut BytesMut, ndigits: usize, weight: i16, sign: u16, scale: usize| {
+            out.put_u16(ndigits as u16);
+            out.put_i16(weight);
+            out.put_u16(sign);
+            out.put_u16(scale as u16);
+        };
+
+        let text = text.trim();
+        let special = match text {
+            "NaN" => Some(NAN),
+            "Infinity" | "+Infinity" => Some(INFINITY),
+            "-Infinity" => Some(NEGATIVE_INFINITY),
+            _ => None,
+        };
+        if let Some(sign) = special {
+            header(out, 0, 0, sign, 0);
+            return Ok(());
+        }
+
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+            None => (unsigned, None),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if (integer.is_empty() && fraction.is_empty())
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(format!("invalid numeric value '{text}'").into());
+        }
+
+        let (integer, fraction) = match exponent {
+            Some(exponent) => {
+                let exponent = exponent
+                    .parse::<i32>()
+                    .map_err(|_| format!("invalid numeric value '{text}'"))?;
+                shift_point(integer, fraction, exponent)
+                    .ok_or_else(|| format!("numeric value '{text}' is out of range"))?
+            }
+            None => (integer.to_string(), fraction.to_string()),
+        };
+
+        // align the digits to groups of 4 around the decimal point
+        let integer = integer.trim_start_matches('0');
+        let integer = format!("{}{integer}", "0".repeat((4 - integer.len() % 4) % 4));
+        let scale = fraction.len();
+        let fraction = format!("{fraction}{}", "0".repeat((4 - scale % 4) % 4));
+
+        let mut weight = (integer.len() / 4) as i16 - 1;
+        let mut digits = integer
+            .as_bytes()
+            .chunks(4)
+            .chain(fraction.as_bytes().chunks(4))
+            .map(|group| {
+                group
+                    .iter()
+                    .fold(0u16, |value, digit| value * 10 + u16::from(digit - b'0'))
+            })
+            .collect::<Vec<_>>();
+
+        while digits.first() == Some(&0) {
+            digits.remove(0);
+            weight -= 1;
+        }
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            weight = 0;
+        }
+
+        let sign = if negative && !digits.is_empty() {
+            NEGATIVE
+        } else {
+            0
+        };
+        header(out, digits.len(), weight, sign, scale);
+        for digit in digits {
+            out.put_u16(digit);
+        }
+
+        Ok(())
+    }
+
+    // integer and fraction digits of a value in scientific notation after moving the decimal point by the exponent
+    // returns `None` if the value exceeds the digits postgres allows before or after the decimal point
+    fn shift_point(integer: &str, fraction: &str, exponent: i32) -> Option<(String, String)> {
+        const MAX_INTEGER_DIGITS: i64 = 131072;
+        const MAX_FRACTION_DIGITS: i64 = 16383;
+
+        let digits = format!("{integer}{fraction}");
+        let point = integer.len() as i64 + i64::from(exponent);
+        let scale = fraction.len() as i64 - i64::from(exponent);
+        if point > MAX_INTEGER_DIGITS || scale > MAX_FRACTION_DIGITS {
+            return None;
+        }
+
+        let shifted = if point <= 0 {
+            (
+                String::new(),
+                format!("{}{digits}", "0".repeat(-point as usize)),
+            )
+        } else if point as usize >= digits.len() {
+            let zeros = "0".repeat(point as usize - digits.len());
+            (format!("{digits}{zeros}"), String::new())
+        } else {
+            let (integer, fraction) = digits.split_at(point as usize);
+            (integer.to_string(), fraction.to_string())
+        };
+
+        Some(shifted)
+    }
+}
+
+// binary format of `uuid`: 16 bytes
+mod uuid {
+    use super::*;
+
+    pub(super) fn decode(raw: &[u8]) -> std::result::Result<String, BoxError> {
+        if raw.len() != 16 {
+            return Err("invalid uuid value".into());
+        }
+
+        let hex = raw.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        Ok(format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        ))
+    }
+
+    pub(super) fn encode(text: &str, out: &mut BytesMut) -> std::result::Result<(), BoxError> {
+        let hex = text.replace('-', "");
+        if hex.len() != 32 {
+            return Err(format!("invalid uuid value '{text}'").into());
+        }
+
+        for pos in (0..32).step_by(2) {
+            out.put_u8(u8::from_str_radix(&hex[pos..pos + 2], 16)?);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(text: &str, pg_type: &Type) -> String {
+        let mut out = BytesMut::new();
+        Text(text.into()).to_sql(pg_type, &mut out).unwrap();
+
+        Text::from_sql(pg_type, &out).unwrap().0
+    }
+
+    #[test]
+    fn test_numeric_round_trip_ok() {
+        for value in [
+            "0",
+            "12345.6",
+            "-0.00005",
+            "100000000",
+            "3.1400",
+            "NaN",
+            "-Infinity",
+        ] {
+            assert_eq!(value, round_trip(value, &Type::NUMERIC));
+        }
+    }
+
+    #[test]
+    fn test_numeric_scientific_notation_ok() {
+        for (value, expected) in [
+            ("1e-7", "0.0000001"),
+            ("-2.5E3", "-2500"),
+            ("1.50e1", "15.0"),
+            ("12.34e+2", "1234"),
+            ("0.5e0", "0.5"),
+        ] {
+            assert_eq!(expected, round_trip(value, &Type::NUMERIC));
+        }
+    }
+
+    #[test]
+    fn test_numeric_invalid_err() {
+        for value in ["1e", "e5", "1e5.5", "1e999999"] {
+            let mut out = BytesMut::new();
+
+            assert!(Text(value.into()).to_sql(&Type::NUMERIC, &mut out).is_err());
+        }
+    }
+
+    #[test]
+    fn test_uuid_round_trip_ok() {
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+        assert_eq!(uuid, round_trip(uuid, &Type::UUID));
+    }
+
+    #[test]
+    fn test_arrow_type_unsupported_err() {
+        let result = arrow_type("location", &Type::POINT);
+
+        assert!(matches!(
+            result,
+            Err(error::Error::UnsupportedColumnType(_, _))
+        ));
+    }
+}
@@ -68,6 +68,36 @@ pub mod serde {
 
         Ok(url)
     }
+
+    /// try to deserialize an optional URL using `deserialize_file_location`
+    pub fn deserialize_optional_file_location<'de, D>(
+        deserializer: D,
+    ) -> core::result::Result<Option<Url>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct FileLocation(#[serde(deserialize_with = "deserialize_file_location")] Url);
+
+        let location = Option::<FileLocation>::deserialize(deserializer)?;
+
+        Ok(location.map(|FileLocation(url)| url))
+    }
+
+    /// try to deserialize a list of URLs using `deserialize_file_location`
+    pub fn deserialize_file_locations<'de, D>(
+        deserializer: D,
+    ) -> core::result::Result<Vec<Url>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct FileLocation(#[serde(deserialize_with = "deserialize_file_location")] Url);
+
+        let locations = Vec::<FileLocation>::deserialize(deserializer)?;
+
+        Ok(locations.into_iter().map(|FileLocation(url)| url).collect())
+    }
 }
 
 /// object store handlers
@@ -106,6 +136,23 @@ pub mod store {
             return Ok(());
         }
 
+        #[cfg(feature = "http")]
+        if location.scheme() == "http" || location.scheme() == "https" {
+            let store = http_store::build_store(location, storage_options)?;
+            let _ = ctx
+                .runtime_env()
+                .register_object_store(location, Arc::new(store));
+
+            return Ok(());
+        }
+
+        #[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+        if let Some(store) = cloud_store::build_store(location, storage_options)? {
+            let _ = ctx.runtime_env().register_object_store(location, store);
+
+            return Ok(());
+        }
+
         let scheme = Url::parse(&format!("{}://", location.scheme())).unwrap();
         if let Some(factory) = deltalake::storage::factories().get(&scheme) {
             let (store, _prefix) =
@@ -121,4 +168,942 @@ pub mod store {
             ))
         }
     }
+
+    /// storage options of the location that are only applied to object stores registered by [`register_object_store`]
+    /// deltalake builds the object stores of delta tables itself and silently ignores these keys, falling back to the default credentials
+    // the keys depend on the enabled cloud storage features
+    #[allow(unused_mut, unused_variables)]
+    pub fn unsupported_delta_options<'a>(
+        location: &Url,
+        storage_options: &'a HashMap<String, String>,
+    ) -> Vec<&'a str> {
+        let mut keys: Vec<&str> = vec![];
+
+        #[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+        if !matches!(location.scheme(), "file" | "memory" | "http" | "https") {
+            keys.extend([
+                retry::MAX_RETRIES,
+                retry::RETRY_TIMEOUT,
+                retry::BACKOFF_INITIAL,
+            ]);
+        }
+
+        #[cfg(feature = "s3")]
+        if matches!(location.scheme(), "s3" | "s3a") {
+            keys.extend([
+                assume_role::ROLE_ARN,
+                assume_role::ROLE_SESSION_NAME,
+                assume_role::EXTERNAL_ID,
+            ]);
+        }
+
+        #[cfg(feature = "gcs")]
+        if location.scheme() == "gs" {
+            keys.extend([
+                cloud_store::HMAC_ACCESS_KEY,
+                cloud_store::HMAC_SECRET,
+                impersonation::SERVICE_ACCOUNT_IMPERSONATION,
+            ]);
+        }
+
+        #[cfg(feature = "azure")]
+        if matches!(location.scheme(), "az" | "adl" | "azure" | "abfs" | "abfss") {
+            keys.push(cloud_store::USE_MANAGED_IDENTITY);
+        }
+
+        let mut unsupported = storage_options
+            .keys()
+            .map(String::as_str)
+            .filter(|key| keys.iter().any(|k| k.eq_ignore_ascii_case(key)))
+            .collect::<Vec<_>>();
+        unsupported.sort_unstable();
+        unsupported
+    }
+
+    /// retry configuration of object stores parsed from the storage options
+    #[cfg(any(feature = "http", feature = "s3", feature = "gcs", feature = "azure"))]
+    pub mod retry {
+        use deltalake::DeltaTableError;
+        use object_store::{BackoffConfig, RetryConfig};
+        use std::{collections::HashMap, time::Duration};
+
+        /// maximum number of times a failed request is retried e.g. `10`
+        pub const MAX_RETRIES: &str = "max_retries";
+
+        /// maximum duration since the first attempt after which a failed request is no longer retried e.g. `3m`
+        pub const RETRY_TIMEOUT: &str = "retry_timeout";
+
+        /// backoff before the first retry, doubled with every further retry e.g. `100ms`
+        pub const BACKOFF_INITIAL: &str = "backoff_initial";
+
+        /// check if a storage option key configures the retries of the object store
+        pub fn is_retry_option(key: &str) -> bool {
+            matches!(key, MAX_RETRIES | RETRY_TIMEOUT | BACKOFF_INITIAL)
+        }
+
+        /// build the retry config from the storage options, keys that are absent fall back to the `object_store` defaults
+        pub fn parse_retry_config(
+            storage_options: &HashMap<String, String>,
+        ) -> Result<RetryConfig, DeltaTableError> {
+            let mut retry_config = RetryConfig::default();
+
+            if let Some(value) = storage_options.get(MAX_RETRIES) {
+                retry_config.max_retries = value
+                    .parse()
+                    .map_err(|e| invalid_option(MAX_RETRIES, value, e))?;
+            }
+            if let Some(value) = storage_options.get(RETRY_TIMEOUT) {
+                retry_config.retry_timeout = parse_duration(RETRY_TIMEOUT, value)?;
+            }
+            if let Some(value) = storage_options.get(BACKOFF_INITIAL) {
+                retry_config.backoff = BackoffConfig {
+                    init_backoff: parse_duration(BACKOFF_INITIAL, value)?,
+                    ..BackoffConfig::default()
+                };
+            }
+
+            Ok(retry_config)
+        }
+
+        fn parse_duration(key: &str, value: &str) -> Result<Duration, DeltaTableError> {
+            humantime::parse_duration(value).map_err(|e| invalid_option(key, value, e))
+        }
+
+        fn invalid_option(
+            key: &str,
+            value: &str,
+            error: impl std::fmt::Display,
+        ) -> DeltaTableError {
+            DeltaTableError::Generic(format!(
+                "invalid value '{value}' for storage option '{key}': {error}"
+            ))
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_parse_retry_config_ok() {
+                let storage_options = HashMap::from([
+                    (MAX_RETRIES.into(), "3".into()),
+                    (RETRY_TIMEOUT.into(), "1m".into()),
+                    (BACKOFF_INITIAL.into(), "250ms".into()),
+                ]);
+
+                let retry_config = parse_retry_config(&storage_options).unwrap();
+
+                assert_eq!(3, retry_config.max_retries);
+                assert_eq!(Duration::from_secs(60), retry_config.retry_timeout);
+                assert_eq!(
+                    Duration::from_millis(250),
+                    retry_config.backoff.init_backoff
+                );
+            }
+
+            #[test]
+            fn test_parse_retry_config_defaults_ok() {
+                let storage_options = HashMap::from([("timeout".into(), "30s".into())]);
+
+                let retry_config = parse_retry_config(&storage_options).unwrap();
+                let default = RetryConfig::default();
+
+                assert_eq!(default.max_retries, retry_config.max_retries);
+                assert_eq!(default.retry_timeout, retry_config.retry_timeout);
+                assert_eq!(
+                    default.backoff.init_backoff,
+                    retry_config.backoff.init_backoff
+                );
+            }
+
+            #[test]
+            fn test_parse_retry_config_invalid_err() {
+                let storage_options = HashMap::from([(RETRY_TIMEOUT.into(), "soon".into())]);
+
+                assert!(parse_retry_config(&storage_options).is_err());
+            }
+        }
+    }
+
+    /// object stores for cloud storage locations (`s3://`, `gs://`, `az://`, `abfss://`) built with the retry config of the storage options
+    #[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+    pub mod cloud_store {
+        use deltalake::DeltaTableError;
+        use object_store::ObjectStore;
+        use std::{collections::HashMap, sync::Arc};
+        use url::Url;
+
+        use super::retry::{is_retry_option, parse_retry_config};
+
+        /// build the object store for the location, returns `None` if the scheme isn't a supported cloud storage
+        /// keys are matched case insensitive and keys unknown to the store are ignored
+        pub fn build_store(
+            location: &Url,
+            storage_options: &HashMap<String, String>,
+        ) -> Result<Option<Arc<dyn ObjectStore>>, DeltaTableError> {
+            let retry_config = parse_retry_config(storage_options)?;
+            let options = storage_options
+                .iter()
+                .filter(|(key, _)| !is_retry_option(key))
+                .map(|(key, value)| (key.to_ascii_lowercase(), value.clone()))
+                .collect::<Vec<_>>();
+
+            let store: Arc<dyn ObjectStore> = match location.scheme() {
+                #[cfg(feature = "s3")]
+                "s3" | "s3a" => Arc::new(
+                    s3_builder(location, &options)
+                        .with_retry(retry_config)
+                        .build()?,
+                ),
+                #[cfg(feature = "gcs")]
+                "gs" => gcs_store(location, &options, retry_config)?,
+                #[cfg(feature = "azure")]
+                "az" | "adl" | "azure" | "abfs" | "abfss" => Arc::new(
+                    azure_builder(location, &options)?
+                        .with_retry(retry_config)
+                        .build()?,
+                ),
+                _ => return Ok(None),
+            };
+
+            Ok(Some(store))
+        }
+
+        /// builder for S3 and S3 compatible stores (e.g. MinIO) configured through `endpoint`, `region` and `allow_http`
+        /// plain `http://` endpoints are allowed without setting `allow_http`
+        #[cfg(feature = "s3")]
+        pub fn s3_builder(
+            location: &Url,
+            options: &[(String, String)],
+        ) -> object_store::aws::AmazonS3Builder {
+            use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
+
+            let builder = config_keys::<AmazonS3ConfigKey>(options).fold(
+                AmazonS3Builder::from_env().with_url(location.as_str()),
+                |builder, (key, value)| builder.with_config(key, value),
+            );
+
+            let builder = match builder.get_config_value(&AmazonS3ConfigKey::Endpoint) {
+                Some(endpoint) if endpoint.starts_with("http://") => builder.with_allow_http(true),
+                _ => builder,
+            };
+
+            match super::assume_role::AssumeRoleCredentialProvider::from_options(options) {
+                Some(provider) => builder.with_credentials(Arc::new(provider)),
+                None => builder,
+            }
+        }
+
+        /// access key of a GCS HMAC key, the bucket is accessed through the S3 compatible XML API when set together with `secret`
+        #[cfg(feature = "gcs")]
+        pub const HMAC_ACCESS_KEY: &str = "access_key";
+
+        /// secret of a GCS HMAC key
+        #[cfg(feature = "gcs")]
+        pub const HMAC_SECRET: &str = "secret";
+
+        // endpoint of the S3 compatible XML API of GCS
+        #[cfg(feature = "gcs")]
+        const GCS_XML_ENDPOINT: &str = "https://storage.googleapis.com";
+
+        /// store for GCS buckets, the credential is picked from the options in this order:
+        /// HMAC keys (`access_key` and `secret`), the service account in `service_account_impersonation` impersonated using
+        /// the configured credentials and the configured credentials (key file or application default credentials)
+        #[cfg(feature = "gcs")]
+        pub fn gcs_store(
+            location: &Url,
+            options: &[(String, String)],
+            retry_config: object_store::RetryConfig,
+        ) -> Result<Arc<dyn ObjectStore>, DeltaTableError> {
+            use object_store::{
+                aws::AmazonS3Builder,
+                gcp::{GoogleCloudStorageBuilder, GoogleConfigKey},
+            };
+
+            use super::impersonation::ImpersonatedCredentialProvider;
+
+            let option = |name: &str| {
+                options
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            };
+
+            if let (Some(access_key), Some(secret)) = (option(HMAC_ACCESS_KEY), option(HMAC_SECRET))
+            {
+                let store = AmazonS3Builder::new()
+                    .with_endpoint(GCS_XML_ENDPOINT)
+                    .with_bucket_name(location.host_str().unwrap_or_default())
+                    .with_region("auto")
+                    .with_access_key_id(access_key)
+                    .with_secret_access_key(secret)
+                    .with_retry(retry_config)
+                    .build()?;
+
+                return Ok(Arc::new(store));
+            }
+
+            let builder = config_keys::<GoogleConfigKey>(options).fold(
+                GoogleCloudStorageBuilder::from_env().with_url(location.as_str()),
+                |builder, (key, value)| builder.with_config(key, value),
+            );
+
+            let builder = match ImpersonatedCredentialProvider::target(options) {
+                Some(target) => {
+                    // the credentials of the configured store are used to impersonate the target
+                    let source = builder.clone().build()?.credentials().clone();
+                    let provider = ImpersonatedCredentialProvider::new(target, source);
+
+                    builder.with_credentials(Arc::new(provider))
+                }
+                None => builder,
+            };
+
+            Ok(Arc::new(builder.with_retry(retry_config).build()?))
+        }
+
+        /// selects the managed identity of the host (e.g. an Azure VM or an AKS pod) as credential of Azure stores
+        /// credentials set through `AZURE_*` environment variables are ignored, a user-assigned identity is selected with `client_id`, `object_id` or `msi_resource_id`
+        #[cfg(feature = "azure")]
+        pub const USE_MANAGED_IDENTITY: &str = "use_managed_identity";
+
+        /// builder for Azure stores, the credential is picked from the options (or `AZURE_*` environment variables) in this order:
+        /// access key, bearer token, client secret, workload identity (`federated_token_file`), SAS, Azure CLI (`use_azure_cli`) and the managed identity of the host
+        /// with `use_managed_identity` the managed identity is used and configuring any other credential is an error
+        #[cfg(feature = "azure")]
+        pub fn azure_builder(
+            location: &Url,
+            options: &[(String, String)],
+        ) -> Result<object_store::azure::MicrosoftAzureBuilder, DeltaTableError> {
+            use object_store::azure::{AzureConfigKey, MicrosoftAzureBuilder};
+
+            let use_managed_identity = options.iter().any(|(key, value)| {
+                key == USE_MANAGED_IDENTITY && value.eq_ignore_ascii_case("true")
+            });
+
+            if !use_managed_identity {
+                return Ok(config_keys::<AzureConfigKey>(options).fold(
+                    MicrosoftAzureBuilder::from_env().with_url(location.as_str()),
+                    |builder, (key, value)| builder.with_config(key, value),
+                ));
+            }
+
+            let credential = config_keys::<AzureConfigKey>(options).find(|(key, _)| {
+                matches!(
+                    key,
+                    AzureConfigKey::AccessKey
+                        | AzureConfigKey::SasKey
+                        | AzureConfigKey::ClientSecret
+                        | AzureConfigKey::Token
+                        | AzureConfigKey::FederatedTokenFile
+                        | AzureConfigKey::UseAzureCli
+                )
+            });
+            if let Some((key, _)) = credential {
+                return Err(DeltaTableError::Generic(format!(
+                    "storage option '{}' can't be combined with '{USE_MANAGED_IDENTITY}'",
+                    key.as_ref()
+                )));
+            }
+
+            // the builder isn't created from the environment so that credentials in env vars don't take precedence
+            Ok(config_keys::<AzureConfigKey>(options).fold(
+                MicrosoftAzureBuilder::new().with_url(location.as_str()),
+                |builder, (key, value)| builder.with_config(key, value),
+            ))
+        }
+
+        // parse the options known to the store into its config keys
+        fn config_keys<K: std::str::FromStr>(
+            options: &[(String, String)],
+        ) -> impl Iterator<Item = (K, String)> + '_ {
+            options
+                .iter()
+                .filter_map(|(key, value)| Some((key.parse::<K>().ok()?, value.clone())))
+        }
+
+        #[cfg(all(test, feature = "gcs"))]
+        mod gcs_tests {
+            use super::*;
+
+            #[test]
+            fn test_gcs_store_hmac_ok() {
+                let location = Url::parse("gs://bucket/prefix/file.csv").unwrap();
+                let options = vec![
+                    (HMAC_ACCESS_KEY.into(), "GOOG1EXAMPLE".into()),
+                    (HMAC_SECRET.into(), "secret".into()),
+                ];
+
+                let store = gcs_store(&location, &options, Default::default()).unwrap();
+
+                assert!(store.to_string().starts_with("AmazonS3"));
+            }
+
+            #[test]
+            fn test_gcs_store_impersonation_ok() {
+                let location = Url::parse("gs://bucket/prefix/file.csv").unwrap();
+                let options = vec![(
+                    crate::store::impersonation::SERVICE_ACCOUNT_IMPERSONATION.into(),
+                    "reader@project.iam.gserviceaccount.com".into(),
+                )];
+
+                let store = gcs_store(&location, &options, Default::default()).unwrap();
+
+                assert!(store.to_string().starts_with("GoogleCloudStorage"));
+            }
+        }
+
+        #[cfg(all(test, feature = "s3"))]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_unsupported_delta_options_ok() {
+                let storage_options = HashMap::from([
+                    ("AWS_REGION".into(), "eu-central-1".into()),
+                    (
+                        "ROLE_ARN".into(),
+                        "arn:aws:iam::123456789012:role/reader".into(),
+                    ),
+                    ("max_retries".into(), "3".into()),
+                ]);
+
+                let s3 = Url::parse("s3://bucket/table").unwrap();
+                let local = Url::parse("file:///tmp/table").unwrap();
+
+                assert_eq!(
+                    vec!["ROLE_ARN", "max_retries"],
+                    super::super::unsupported_delta_options(&s3, &storage_options)
+                );
+                assert!(
+                    super::super::unsupported_delta_options(&local, &storage_options).is_empty()
+                );
+            }
+
+            #[test]
+            fn test_build_store_s3_ok() {
+                let location = Url::parse("s3://bucket/prefix/file.csv").unwrap();
+                let storage_options = HashMap::from([
+                    ("AWS_REGION".into(), "eu-central-1".into()),
+                    ("max_retries".into(), "3".into()),
+                    ("unknown".into(), "value".into()),
+                ]);
+
+                let store = build_store(&location, &storage_options).unwrap();
+
+                assert!(store.is_some());
+            }
+
+            #[test]
+            fn test_s3_builder_http_endpoint_ok() {
+                use object_store::{aws::AmazonS3ConfigKey, ClientConfigKey};
+
+                let location = Url::parse("s3://bucket/prefix/file.csv").unwrap();
+                let options = vec![
+                    ("endpoint".into(), "http://localhost:9000".into()),
+                    ("region".into(), "us-east-1".into()),
+                ];
+
+                let builder = s3_builder(&location, &options);
+
+                assert_eq!(
+                    Some("http://localhost:9000".to_string()),
+                    builder.get_config_value(&AmazonS3ConfigKey::Endpoint)
+                );
+                assert_eq!(
+                    Some("us-east-1".to_string()),
+                    builder.get_config_value(&AmazonS3ConfigKey::Region)
+                );
+                assert_eq!(
+                    Some("true".to_string()),
+                    builder
+                        .get_config_value(&AmazonS3ConfigKey::Client(ClientConfigKey::AllowHttp))
+                );
+                assert!(builder.build().is_ok());
+            }
+
+            #[test]
+            fn test_build_store_unsupported_scheme_ok() {
+                let location = Url::parse("hdfs://namenode/file.csv").unwrap();
+
+                let store = build_store(&location, &HashMap::new()).unwrap();
+
+                assert!(store.is_none());
+            }
+        }
+    }
+
+    #[cfg(all(test, feature = "azure"))]
+    mod azure_tests {
+        use object_store::azure::AzureConfigKey;
+        use url::Url;
+
+        use super::cloud_store::{azure_builder, USE_MANAGED_IDENTITY};
+
+        fn location() -> Url {
+            Url::parse("abfss://container@account.dfs.core.windows.net/prefix/file.csv").unwrap()
+        }
+
+        #[test]
+        fn test_azure_builder_managed_identity_ok() {
+            let options = vec![
+                (USE_MANAGED_IDENTITY.into(), "true".into()),
+                ("client_id".into(), "user-assigned-identity".into()),
+            ];
+
+            let builder = azure_builder(&location(), &options).unwrap();
+
+            assert_eq!(
+                Some("user-assigned-identity".to_string()),
+                builder.get_config_value(&AzureConfigKey::ClientId)
+            );
+            assert_eq!(None, builder.get_config_value(&AzureConfigKey::AccessKey));
+            assert!(builder.build().is_ok());
+        }
+
+        #[test]
+        fn test_azure_builder_managed_identity_conflict_err() {
+            let options = vec![
+                (USE_MANAGED_IDENTITY.into(), "true".into()),
+                ("access_key".into(), "a2V5".into()),
+            ];
+
+            assert!(azure_builder(&location(), &options).is_err());
+        }
+
+        #[test]
+        fn test_azure_builder_static_credentials_ok() {
+            let options = vec![("access_key".into(), "a2V5".into())];
+
+            let builder = azure_builder(&location(), &options).unwrap();
+
+            assert_eq!(
+                Some("a2V5".to_string()),
+                builder.get_config_value(&AzureConfigKey::AccessKey)
+            );
+        }
+
+        #[test]
+        fn test_azure_builder_workload_identity_ok() {
+            let options = vec![
+                (
+                    "federated_token_file".into(),
+                    "/var/run/secrets/token".into(),
+                ),
+                ("client_id".into(), "client".into()),
+                ("tenant_id".into(), "tenant".into()),
+            ];
+
+            let builder = azure_builder(&location(), &options).unwrap();
+
+            assert_eq!(
+                Some("/var/run/secrets/token".to_string()),
+                builder.get_config_value(&AzureConfigKey::FederatedTokenFile)
+            );
+        }
+    }
+
+    /// temporary S3 credentials obtained by assuming an IAM role through STS
+    #[cfg(feature = "s3")]
+    pub mod assume_role {
+        use aws_config::{BehaviorVersion, Region};
+        use object_store::{aws::AwsCredential, CredentialProvider};
+        use std::{
+            sync::Arc,
+            time::{Duration, SystemTime},
+        };
+        use tokio::sync::{Mutex, OnceCell};
+
+        /// ARN of the role to assume e.g. `arn:aws:iam::123456789012:role/reader`
+        pub const ROLE_ARN: &str = "role_arn";
+
+        /// name of the role session, defaults to `aqueducts`
+        pub const ROLE_SESSION_NAME: &str = "role_session_name";
+
+        /// external id required by the trust policy of the role
+        pub const EXTERNAL_ID: &str = "external_id";
+
+        // credentials are refreshed this long before they expire
+        const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+        /// Provides credentials of an assumed role, the role is assumed using the default AWS credential chain (env, profile, instance metadata)
+        ///
+        /// The credentials are cached and the role is assumed again 5 minutes before they expire, so long running pipelines keep working.
+        /// Sessions last for the default duration of 1 hour, which is also the maximum when the base credentials are themselves an assumed role (role chaining)
+        #[derive(Debug)]
+        pub struct AssumeRoleCredentialProvider {
+            role_arn: String,
+            session_name: String,
+            external_id: Option<String>,
+            region: Option<String>,
+            client: OnceCell<aws_sdk_sts::Client>,
+            cache: Mutex<Option<(Arc<AwsCredential>, SystemTime)>>,
+        }
+
+        impl AssumeRoleCredentialProvider {
+            /// create a provider from the (lowercase) storage options, returns `None` when no `role_arn` is configured
+            pub fn from_options(options: &[(String, String)]) -> Option<Self> {
+                let option = |keys: &[&str]| {
+                    options
+                        .iter()
+                        .find(|(key, _)| keys.contains(&key.as_str()))
+                        .map(|(_, value)| value.clone())
+                };
+
+                Some(Self {
+                    role_arn: option(&[ROLE_ARN])?,
+                    session_name: option(&[ROLE_SESSION_NAME]).unwrap_or("aqueducts".into()),
+                    external_id: option(&[EXTERNAL_ID]),
+                    region: option(&["region", "aws_region", "aws_default_region"]),
+                    client: OnceCell::new(),
+                    cache: Mutex::new(None),
+                })
+            }
+
+            async fn client(&self) -> &aws_sdk_sts::Client {
+                self.client
+                    .get_or_init(|| async {
+                        let loader = aws_config::defaults(BehaviorVersion::latest());
+                        let loader = match &self.region {
+                            Some(region) => loader.region(Region::new(region.clone())),
+                            None => loader,
+                        };
+
+                        aws_sdk_sts::Client::new(&loader.load().await)
+                    })
+                    .await
+            }
+
+            async fn assume_role(
+                &self,
+            ) -> Result<(Arc<AwsCredential>, SystemTime), Box<dyn std::error::Error + Send + Sync>>
+            {
+                let response = self
+                    .client()
+                    .await
+                    .assume_role()
+                    .role_arn(self.role_arn.as_str())
+                    .role_session_name(self.session_name.as_str())
+                    .set_external_id(self.external_id.clone())
+                    .send()
+                    .await?;
+
+                let credentials = response.credentials().ok_or(format!(
+                    "no credentials returned for role '{}'",
+                    self.role_arn
+                ))?;
+                let expiration = SystemTime::try_from(*credentials.expiration())?;
+                let credential = AwsCredential {
+                    key_id: credentials.access_key_id().to_string(),
+                    secret_key: credentials.secret_access_key().to_string(),
+                    token: Some(credentials.session_token().to_string()),
+                };
+
+                Ok((Arc::new(credential), expiration))
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl CredentialProvider for AssumeRoleCredentialProvider {
+            type Credential = AwsCredential;
+
+            async fn get_credential(&self) -> object_store::Result<Arc<AwsCredential>> {
+                let mut cache = self.cache.lock().await;
+
+                if let Some((credential, expiration)) = cache.as_ref() {
+                    if SystemTime::now() + REFRESH_BEFORE_EXPIRY < *expiration {
+                        return Ok(credential.clone());
+                    }
+                }
+
+                let (credential, expiration) =
+                    self.assume_role()
+                        .await
+                        .map_err(|source| object_store::Error::Generic {
+                            store: "S3",
+                            source,
+                        })?;
+                *cache = Some((credential.clone(), expiration));
+
+                Ok(credential)
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_from_options_ok() {
+                let options = vec![
+                    (
+                        ROLE_ARN.into(),
+                        "arn:aws:iam::123456789012:role/reader".into(),
+                    ),
+                    (EXTERNAL_ID.into(), "partner".into()),
+                    ("region".into(), "eu-central-1".into()),
+                ];
+
+                let provider = AssumeRoleCredentialProvider::from_options(&options).unwrap();
+
+                assert_eq!("arn:aws:iam::123456789012:role/reader", provider.role_arn);
+                assert_eq!("aqueducts", provider.session_name);
+                assert_eq!(Some("partner".to_string()), provider.external_id);
+                assert_eq!(Some("eu-central-1".to_string()), provider.region);
+            }
+
+            #[test]
+            fn test_from_options_static_credentials_ok() {
+                let options = vec![
+                    ("access_key_id".into(), "key".into()),
+                    ("secret_access_key".into(), "secret".into()),
+                ];
+
+                assert!(AssumeRoleCredentialProvider::from_options(&options).is_none());
+            }
+        }
+    }
+
+    /// short-lived GCS credentials obtained by impersonating a service account through the IAM Credentials API
+    #[cfg(feature = "gcs")]
+    pub mod impersonation {
+        use object_store::{
+            gcp::{GcpCredential, GcpCredentialProvider},
+            CredentialProvider,
+        };
+        use serde::{Deserialize, Serialize};
+        use std::{
+            sync::Arc,
+            time::{Duration, SystemTime},
+        };
+        use tokio::sync::Mutex;
+
+        /// email of the service account to impersonate e.g. `reader@project.iam.gserviceaccount.com`
+        pub const SERVICE_ACCOUNT_IMPERSONATION: &str = "service_account_impersonation";
+
+        const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.full_control";
+
+        // lifetime of the requested access tokens, the maximum without extending it through an organization policy
+        const LIFETIME: &str = "3600s";
+
+        // access tokens are refreshed this long before they expire
+        const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+        #[derive(Debug, Serialize)]
+        struct GenerateAccessTokenRequest<'a> {
+            scope: [&'a str; 1],
+            lifetime: &'a str,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct GenerateAccessTokenResponse {
+            access_token: String,
+            expire_time: String,
+        }
+
+        /// Provides access tokens of an impersonated service account, generated using the credentials of a source provider
+        ///
+        /// The tokens are cached and generated again 5 minutes before they expire, so long running pipelines keep working.
+        /// The source credentials need the `cloud-platform` scope and the `roles/iam.serviceAccountTokenCreator` role on the target,
+        /// which is the case for application default credentials of a user and the metadata server of GCE and GKE
+        /// but not for service account key files, whose tokens are restricted to GCS
+        #[derive(Debug)]
+        pub struct ImpersonatedCredentialProvider {
+            target: String,
+            source: GcpCredentialProvider,
+            client: reqwest::Client,
+            cache: Mutex<Option<(Arc<GcpCredential>, SystemTime)>>,
+        }
+
+        impl ImpersonatedCredentialProvider {
+            /// create a provider impersonating the `target` service account using the credentials of `source`
+            pub fn new(target: String, source: GcpCredentialProvider) -> Self {
+                Self {
+                    target,
+                    source,
+                    client: reqwest::Client::new(),
+                    cache: Mutex::new(None),
+                }
+            }
+
+            /// service account to impersonate configured in the (lowercase) storage options
+            pub fn target(options: &[(String, String)]) -> Option<String> {
+                options
+                    .iter()
+                    .find(|(key, _)| key == SERVICE_ACCOUNT_IMPERSONATION)
+                    .map(|(_, value)| value.clone())
+            }
+
+            async fn generate_access_token(
+                &self,
+            ) -> Result<(Arc<GcpCredential>, SystemTime), Box<dyn std::error::Error + Send + Sync>>
+            {
+                let source = self.source.get_credential().await?;
+                let url = format!(
+                    "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+                    self.target
+                );
+
+                let response = self
+                    .client
+                    .post(url)
+                    .bearer_auth(source.bearer.as_str())
+                    .json(&GenerateAccessTokenRequest {
+                        scope: [SCOPE],
+                        lifetime: LIFETIME,
+                    })
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<GenerateAccessTokenResponse>()
+                    .await?;
+
+                let expiration = humantime::parse_rfc3339_weak(response.expire_time.as_str())?;
+                let credential = GcpCredential {
+                    bearer: response.access_token,
+                };
+
+                Ok((Arc::new(credential), expiration))
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl CredentialProvider for ImpersonatedCredentialProvider {
+            type Credential = GcpCredential;
+
+            async fn get_credential(&self) -> object_store::Result<Arc<GcpCredential>> {
+                let mut cache = self.cache.lock().await;
+
+                if let Some((credential, expiration)) = cache.as_ref() {
+                    if SystemTime::now() + REFRESH_BEFORE_EXPIRY < *expiration {
+                        return Ok(credential.clone());
+                    }
+                }
+
+                let (credential, expiration) =
+                    self.generate_access_token().await.map_err(|source| {
+                        object_store::Error::Generic {
+                            store: "GCS",
+                            source,
+                        }
+                    })?;
+                *cache = Some((credential.clone(), expiration));
+
+                Ok(credential)
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_target_ok() {
+                let options = vec![
+                    (
+                        SERVICE_ACCOUNT_IMPERSONATION.into(),
+                        "reader@project.iam.gserviceaccount.com".into(),
+                    ),
+                    ("service_account".into(), "/keys/source.json".into()),
+                ];
+
+                assert_eq!(
+                    Some("reader@project.iam.gserviceaccount.com".to_string()),
+                    ImpersonatedCredentialProvider::target(&options)
+                );
+                assert!(ImpersonatedCredentialProvider::target(&options[1..]).is_none());
+            }
+        }
+    }
+
+    /// read-only object store for files served over `http`/`https`
+    #[cfg(feature = "http")]
+    pub mod http_store {
+        use deltalake::DeltaTableError;
+        use http::{HeaderMap, HeaderName, HeaderValue};
+        use object_store::{
+            http::{HttpBuilder, HttpStore},
+            ClientConfigKey, ClientOptions,
+        };
+        use std::collections::HashMap;
+        use url::Url;
+
+        use super::retry::{is_retry_option, parse_retry_config};
+
+        /// prefix of storage option keys that are sent as request headers (e.g. `header.Authorization: Bearer <token>`)
+        pub const HEADER_PREFIX: &str = "header.";
+
+        /// build an HTTP store for the origin of the location
+        /// storage options prefixed with `header.` are sent as default headers, retry options configure the retries and all other keys are parsed as client options (e.g. `timeout`, `user_agent`)
+        pub fn build_store(
+            location: &Url,
+            storage_options: &HashMap<String, String>,
+        ) -> Result<HttpStore, DeltaTableError> {
+            let mut headers = HeaderMap::new();
+            let mut client_options = ClientOptions::new();
+
+            for (key, value) in storage_options {
+                if is_retry_option(key) {
+                    continue;
+                }
+
+                if let Some(name) = key.strip_prefix(HEADER_PREFIX) {
+                    let name = HeaderName::try_from(name).map_err(|e| {
+                        DeltaTableError::Generic(format!("invalid header name '{name}': {e}"))
+                    })?;
+                    let value = HeaderValue::try_from(value.as_str()).map_err(|e| {
+                        DeltaTableError::Generic(format!("invalid value for header '{name}': {e}"))
+                    })?;
+
+                    headers.insert(name, value);
+                } else {
+                    client_options =
+                        client_options.with_config(key.parse::<ClientConfigKey>()?, value);
+                }
+            }
+
+            let store = HttpBuilder::new()
+                .with_url(location.origin().ascii_serialization())
+                .with_client_options(client_options.with_default_headers(headers))
+                .with_retry(parse_retry_config(storage_options)?)
+                .build()?;
+
+            Ok(store)
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn test_build_store_ok() {
+                let location = Url::parse("https://example.com/data/file.csv").unwrap();
+                let storage_options = HashMap::from([
+                    ("header.Authorization".into(), "Bearer token".into()),
+                    ("timeout".into(), "30s".into()),
+                    ("max_retries".into(), "5".into()),
+                ]);
+
+                assert!(build_store(&location, &storage_options).is_ok());
+            }
+
+            #[test]
+            fn test_build_store_invalid_header_err() {
+                let location = Url::parse("https://example.com/data/file.csv").unwrap();
+                let storage_options =
+                    HashMap::from([("header.Invalid Header".into(), "value".into())]);
+
+                assert!(build_store(&location, &storage_options).is_err());
+            }
+
+            #[test]
+            fn test_build_store_unknown_option_err() {
+                let location = Url::parse("http://localhost:8080/file.csv").unwrap();
+                let storage_options = HashMap::from([("unknown".into(), "value".into())]);
+
+                assert!(build_store(&location, &storage_options).is_err());
+            }
+        }
+    }
 }
@@ -37,3 +37,10 @@ CREATE TABLE test_custom_delete_insert_failed (
     id          INTEGER,
     value       VARCHAR(50)
 );
+
+CREATE TABLE test_postgres_round_trip (
+    id          INTEGER NOT NULL,
+    name        TEXT,
+    amount      NUMERIC(10, 2),
+    created_at  TIMESTAMP WITH TIME ZONE
+);